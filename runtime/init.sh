@@ -12,6 +12,20 @@ AUTH_STATE_MOUNTED=0
 SESSIONS_ROOT=""
 TASKS_ROOT=""
 INITIAL_TASK_ID=""
+MOUNT_BACKEND="9p"
+
+# mount_share <tag> <target> [ro]
+mount_share() {
+    if [ "$MOUNT_BACKEND" = "virtiofs" ]; then
+        if [ "${3:-}" = "ro" ]; then
+            mount -t virtiofs -o ro "$1" "$2"
+        else
+            mount -t virtiofs "$1" "$2"
+        fi
+    else
+        mount -t 9p -o "trans=virtio,version=9p2000.L${3:+,$3}" "$1" "$2"
+    fi
+}
 
 wait_for_taskd_port() {
     PORT_HEX=$(printf '%04X' "$RPC_PORT")
@@ -40,6 +54,8 @@ modprobe netfs 2>/dev/null || true
 modprobe 9pnet 2>/dev/null || true
 modprobe 9pnet_virtio 2>/dev/null || true
 modprobe 9p 2>/dev/null || true
+modprobe fuse 2>/dev/null || true
+modprobe virtiofs 2>/dev/null || true
 
 if ! grep -q $'\t9p$' /proc/filesystems 2>/dev/null; then
     KVER=$(uname -r)
@@ -50,6 +66,13 @@ if ! grep -q $'\t9p$' /proc/filesystems 2>/dev/null; then
     insmod "$MODROOT/fs/9p/9p.ko" 2>/dev/null || true
 fi
 
+if ! grep -q $'\tvirtiofs$' /proc/filesystems 2>/dev/null; then
+    KVER=$(uname -r)
+    MODROOT="/usr/lib/modules/$KVER/kernel"
+    insmod "$MODROOT/fs/fuse/fuse.ko" 2>/dev/null || true
+    insmod "$MODROOT/fs/fuse/virtiofs.ko" 2>/dev/null || true
+fi
+
 for arg in $(cat /proc/cmdline); do
     case "$arg" in
         piwork.sessions_root=*)
@@ -58,6 +81,9 @@ for arg in $(cat /proc/cmdline); do
         piwork.task_id=*)
             INITIAL_TASK_ID="${arg#piwork.task_id=}"
             ;;
+        piwork.mount_backend=*)
+            MOUNT_BACKEND="${arg#piwork.mount_backend=}"
+            ;;
     esac
 done
 
@@ -67,9 +93,11 @@ udhcpc -i eth0 -q -n -t 3 -T 1
 # QEMU user-mode networking provides DNS at 10.0.2.3
 echo "nameserver 10.0.2.3" > /etc/resolv.conf
 
-# Mount working folder if available (9p virtio share)
+echo "Mount backend: $MOUNT_BACKEND"
+
+# Mount working folder if available
 mkdir -p "$WORKDIR"
-if mount -t 9p -o trans=virtio,version=9p2000.L workdir "$WORKDIR"; then
+if mount_share workdir "$WORKDIR"; then
     echo "Mounted working folder at $WORKDIR"
     export PI_WORKING_DIR="$WORKDIR"
     export PIWORK_WORKSPACE_ROOT="$WORKDIR"
@@ -78,18 +106,41 @@ else
     echo "No working folder mounted"
 fi
 
-# Mount task state folder if available (9p virtio share)
+# Mount active task state if available (shares scoped to one task).
+# The task dir itself lives on the guest's root fs; only `outputs` (writable) and `uploads`
+# (read-only) are host shares, so unmounting either exposes nothing writable on the host.
 mkdir -p "$TASK_STATE_DIR"
-if mount -t 9p -o trans=virtio,version=9p2000.L taskstate "$TASK_STATE_DIR"; then
-    TASK_STATE_MOUNTED=1
-    echo "Mounted task state at $TASK_STATE_DIR"
+if [ -n "$INITIAL_TASK_ID" ]; then
+    TASK_MOUNT_DIR="$TASK_STATE_DIR/$INITIAL_TASK_ID"
+    TASK_SESSION_DIR="$TASK_STATE_DIR/sessions/$INITIAL_TASK_ID"
+    mkdir -p "$TASK_MOUNT_DIR/outputs" "$TASK_MOUNT_DIR/uploads"
+
+    if mount_share taskoutputs "$TASK_MOUNT_DIR/outputs"; then
+        TASK_STATE_MOUNTED=1
+        echo "Mounted task outputs at $TASK_MOUNT_DIR/outputs"
+
+        if mount_share taskuploads "$TASK_MOUNT_DIR/uploads" ro; then
+            echo "Mounted task uploads (read-only) at $TASK_MOUNT_DIR/uploads"
+        else
+            echo "No task uploads mounted"
+        fi
+    else
+        echo "No task state mounted"
+    fi
+
+    mkdir -p "$TASK_SESSION_DIR"
+    if mount_share tasksession "$TASK_SESSION_DIR"; then
+        echo "Mounted task session at $TASK_SESSION_DIR"
+    else
+        echo "No task session mounted"
+    fi
 else
-    echo "No task state mounted"
+    echo "No task state mounted (no active task)"
 fi
 
-# Mount auth state folder if available (9p virtio share)
+# Mount auth state folder if available
 mkdir -p "$AUTH_STATE_DIR"
-if mount -t 9p -o trans=virtio,version=9p2000.L authstate "$AUTH_STATE_DIR"; then
+if mount_share authstate "$AUTH_STATE_DIR"; then
     AUTH_STATE_MOUNTED=1
     echo "Mounted auth state at $AUTH_STATE_DIR"
 else
@@ -15,6 +15,70 @@ cleanup() {
 }
 trap cleanup EXIT
 
+# Task switches restart the VM (task state is mounted per task), so poll until it settles.
+wait_for_active_task() {
+    local task_id="$1"
+    for _ in $(seq 1 120); do
+        local snapshot
+        snapshot=$(echo '{"cmd":"state_snapshot"}' | nc -w 3 localhost 19385 || true)
+        if python - "$task_id" "$snapshot" <<'PY'
+import json
+import sys
+
+task_id, raw = sys.argv[1], sys.argv[2]
+try:
+    snapshot = json.loads(raw)
+except Exception:
+    sys.exit(1)
+
+task = snapshot.get("task", {})
+runtime = snapshot.get("runtime", {})
+ready = task.get("currentTaskId") == task_id and runtime.get("rpcConnected") and not runtime.get("taskSwitching")
+sys.exit(0 if ready else 1)
+PY
+        then
+            return 0
+        fi
+        sleep 0.5
+    done
+
+    return 1
+}
+
+# Prints the exitCode of a raw `system_bash` RPC response once it shows up in the app log.
+wait_for_system_bash_exit_code() {
+    local request_id="$1"
+    for _ in $(seq 1 20); do
+        local exit_code
+        exit_code=$(python - "$LOG_FILE" "$request_id" <<'PY'
+import json
+import sys
+
+log_path, request_id = sys.argv[1], sys.argv[2]
+with open(log_path, encoding="utf-8", errors="replace") as handle:
+    for line in handle:
+        if "received:" not in line or request_id not in line:
+            continue
+        raw = line.split("received:", 1)[1].strip()
+        try:
+            payload = json.loads(json.loads(raw))
+        except Exception:
+            continue
+        if payload.get("id") == request_id and payload.get("ok"):
+            print(payload.get("result", {}).get("exitCode", ""))
+            break
+PY
+)
+        if [[ -n "$exit_code" ]]; then
+            echo "$exit_code"
+            return 0
+        fi
+        sleep 0.5
+    done
+
+    return 1
+}
+
 mkdir -p "$TASK_A_DIR" "$TASK_B_DIR"
 printf "alpha-secret-%s\n" "$SUFFIX" > "$TASK_A_DIR/secret-a.txt"
 printf "beta-note-%s\n" "$SUFFIX" > "$TASK_B_DIR/public-b.txt"
@@ -84,30 +148,33 @@ if [[ -z "$TASK_A_ID" || -z "$TASK_B_ID" ]]; then
 fi
 
 mise run test-set-task "$TASK_B_ID" >/dev/null
-sleep 1
+if ! wait_for_active_task "$TASK_B_ID"; then
+    echo "[scope-negative] runtime did not settle on task $TASK_B_ID"
+    exit 1
+fi
 
-echo "[scope-negative] check 1/4: traversal read blocked"
+echo "[scope-negative] check 1/6: traversal read blocked"
 TRAVERSAL_RESULT=$(printf '{"cmd":"preview_read","taskId":"%s","relativePath":"../task-a/secret-a.txt"}\n' "$TASK_B_ID" | nc -w 2 localhost 19385)
 if [[ "$TRAVERSAL_RESULT" != *"ERR: Invalid relative path component"* && "$TRAVERSAL_RESULT" != *"ERR: relativePath must not traverse parent directories"* ]]; then
     echo "[scope-negative] unexpected traversal result: $TRAVERSAL_RESULT"
     exit 1
 fi
 
-echo "[scope-negative] check 2/4: symlink read blocked"
+echo "[scope-negative] check 2/6: symlink read blocked"
 SYMLINK_RESULT=$(printf '{"cmd":"preview_read","taskId":"%s","relativePath":"link-to-a.txt"}\n' "$TASK_B_ID" | nc -w 2 localhost 19385)
 if [[ "$SYMLINK_RESULT" != *"ERR: Symlink previews are not allowed"* ]]; then
     echo "[scope-negative] unexpected symlink result: $SYMLINK_RESULT"
     exit 1
 fi
 
-echo "[scope-negative] check 3/4: direct task file read still works"
+echo "[scope-negative] check 3/6: direct task file read still works"
 OWN_RESULT=$(printf '{"cmd":"preview_read","taskId":"%s","relativePath":"public-b.txt"}\n' "$TASK_B_ID" | nc -w 2 localhost 19385)
 if [[ "$OWN_RESULT" != *"beta-note-$SUFFIX"* ]]; then
     echo "[scope-negative] failed own file read check"
     exit 1
 fi
 
-echo "[scope-negative] check 4/4: guest rejects workingFolderRelative escape"
+echo "[scope-negative] check 4/6: guest rejects workingFolderRelative escape"
 RPC_REQ_ID="scope_negative_escape_$SUFFIX"
 printf '{"id":"%s","type":"create_or_open_task","payload":{"taskId":"bad-scope-%s","provider":"anthropic","model":"claude-opus-4-5","thinkingLevel":"high","workingFolderRelative":"../escape"}}\n' "$RPC_REQ_ID" "$SUFFIX" | nc -w 2 localhost 19385 >/dev/null
 
@@ -125,6 +192,47 @@ if [[ "$ESCAPE_BLOCKED" -ne 1 ]]; then
     exit 1
 fi
 
+echo "[scope-negative] check 5/6: guest writes to uploads are denied"
+UPLOAD_REQ_ID="scope_negative_upload_$SUFFIX"
+UPLOAD_PROBE="write-probe-$SUFFIX.txt"
+printf '{"id":"%s","type":"system_bash","payload":{"command":"printf probe > /mnt/taskstate/%s/uploads/%s"}}\n' "$UPLOAD_REQ_ID" "$TASK_B_ID" "$UPLOAD_PROBE" | nc -w 2 localhost 19385 >/dev/null
+
+UPLOAD_EXIT_CODE=$(wait_for_system_bash_exit_code "$UPLOAD_REQ_ID" || true)
+
+if [[ -z "$UPLOAD_EXIT_CODE" || "$UPLOAD_EXIT_CODE" == "0" ]]; then
+    echo "[scope-negative] uploads write was not denied (exitCode=${UPLOAD_EXIT_CODE:-missing})"
+    exit 1
+fi
+
+# The guest runs as root, so it can drop the read-only share; nothing writable may sit behind it.
+UNMOUNT_REQ_ID="scope_negative_upload_unmount_$SUFFIX"
+printf '{"id":"%s","type":"system_bash","payload":{"command":"umount /mnt/taskstate/%s/uploads; printf probe > /mnt/taskstate/%s/uploads/%s; printf probe > /mnt/taskstate/%s/task.json"}}\n' "$UNMOUNT_REQ_ID" "$TASK_B_ID" "$TASK_B_ID" "$UPLOAD_PROBE" "$TASK_B_ID" | nc -w 2 localhost 19385 >/dev/null
+
+if ! wait_for_system_bash_exit_code "$UNMOUNT_REQ_ID" >/dev/null; then
+    echo "[scope-negative] missing result for uploads unmount probe"
+    exit 1
+fi
+
+if [[ -e "$TASKS_DIR/$TASK_B_ID/uploads/$UPLOAD_PROBE" ]]; then
+    echo "[scope-negative] uploads probe file reached host: $UPLOAD_PROBE"
+    exit 1
+fi
+
+if grep -q '^probe$' "$TASKS_DIR/$TASK_B_ID/task.json"; then
+    echo "[scope-negative] guest overwrote task.json on the host"
+    exit 1
+fi
+
+echo "[scope-negative] check 6/6: guest cannot read another task's state"
+CROSS_TASK_REQ_ID="scope_negative_cross_task_$SUFFIX"
+printf '{"id":"%s","type":"system_bash","payload":{"command":"umount /mnt/taskstate/%s/outputs /mnt/taskstate/%s/uploads; cat /mnt/taskstate/%s/task.json"}}\n' "$CROSS_TASK_REQ_ID" "$TASK_B_ID" "$TASK_B_ID" "$TASK_A_ID" | nc -w 2 localhost 19385 >/dev/null
+
+CROSS_TASK_EXIT_CODE=$(wait_for_system_bash_exit_code "$CROSS_TASK_REQ_ID" || true)
+if [[ -z "$CROSS_TASK_EXIT_CODE" || "$CROSS_TASK_EXIT_CODE" == "0" ]]; then
+    echo "[scope-negative] cross-task read was not blocked (exitCode=${CROSS_TASK_EXIT_CODE:-missing})"
+    exit 1
+fi
+
 mise run test-open-preview "$TASK_B_ID" "public-b.txt" >/dev/null
 sleep 1
 mise run test-dump-state >/dev/null
@@ -2,6 +2,8 @@ use serde::Serialize;
 use serde_json::{Map, Value};
 use std::path::Path;
 
+use crate::persist;
+
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AuthStoreEntry {
@@ -45,6 +47,7 @@ pub fn set_api_key(path: &Path, provider: &str, key: &str) -> Result<(), String>
         return Err("API key is required".to_string());
     }
 
+    let _lock = persist::lock(path)?;
     let mut map = read_map(path)?;
     map.insert(
         normalized_provider.to_string(),
@@ -60,6 +63,7 @@ pub fn delete_provider(path: &Path, provider: &str) -> Result<(), String> {
         return Ok(());
     }
 
+    let _lock = persist::lock(path)?;
     let mut map = read_map(path)?;
     map.remove(normalized_provider);
 
@@ -76,12 +80,34 @@ pub fn import_from_path(dest_path: &Path, source_path: &Path) -> Result<(), Stri
         return Err("Source auth file is empty".to_string());
     }
 
+    let _lock = persist::lock(dest_path)?;
     let mut dest_map = read_map(dest_path)?;
     dest_map.extend(source_map);
 
     write_map(dest_path, dest_map)
 }
 
+/// Every credential value in the store (API keys, OAuth tokens), for redacting them from exports.
+pub fn secret_values(path: &Path) -> Result<Vec<String>, String> {
+    fn collect(value: &Value, secrets: &mut Vec<String>) {
+        match value {
+            Value::String(text) => secrets.push(text.clone()),
+            Value::Array(items) => items.iter().for_each(|item| collect(item, secrets)),
+            Value::Object(map) => map
+                .iter()
+                .filter(|(key, _)| key.as_str() != "type")
+                .for_each(|(_, item)| collect(item, secrets)),
+            _ => {}
+        }
+    }
+
+    let mut secrets = Vec::new();
+    for value in read_map(path)?.values() {
+        collect(value, &mut secrets);
+    }
+    Ok(secrets)
+}
+
 fn read_map(path: &Path) -> Result<Map<String, Value>, String> {
     if !path.exists() {
         return Ok(Map::new());
@@ -108,11 +134,7 @@ fn write_map(path: &Path, map: Map<String, Value>) -> Result<(), String> {
     }
 
     let content = serde_json::to_string_pretty(&Value::Object(map)).map_err(|error| error.to_string())?;
-    std::fs::write(path, content).map_err(|error| error.to_string())?;
-
-    set_permissions(path);
-
-    Ok(())
+    persist::write_private(path, content.as_bytes())
 }
 
 fn entry_type(value: &Value) -> String {
@@ -123,15 +145,6 @@ fn entry_type(value: &Value) -> String {
         .to_string()
 }
 
-fn set_permissions(path: &Path) {
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::PermissionsExt;
-        let permissions = std::fs::Permissions::from_mode(0o600);
-        let _ = std::fs::set_permissions(path, permissions);
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
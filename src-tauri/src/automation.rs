@@ -0,0 +1,598 @@
+//! Local automation API: JSON-RPC 2.0 over a loopback TCP socket, one request or batch per
+//! line. It is off unless `PIWORK_AUTOMATION=1`. When on, the port and a per-session token are
+//! written owner-only to `<data dir>/automation.json`; a connection must call `authenticate`
+//! with that token before anything else.
+//!
+//! `subscribe` streams host [`HostEvent`]s to the connection as `event` notifications until
+//! `unsubscribe` or disconnect.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex};
+
+use crate::persist;
+use crate::rpc_bus::RpcBus;
+
+pub const AUTOMATION_ENV_VAR: &str = "PIWORK_AUTOMATION";
+pub const ENDPOINT_FILE: &str = "automation.json";
+/// Requests longer than this are rejected and the connection closed.
+const MAX_REQUEST_BYTES: u64 = 4 * 1024 * 1024;
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const INTERNAL_ERROR: i64 = -32603;
+/// The operation was valid but failed, e.g. the VM didn't boot.
+pub const OPERATION_FAILED: i64 = -32000;
+pub const UNAUTHORIZED: i64 = -32001;
+pub const NOT_FOUND: i64 = -32002;
+
+/// `vm`: VM `ready` / `error`. `rpc`: every guest RPC line. `tasks`: task store changes.
+pub const TOPICS: [&str; 3] = ["vm", "rpc", "tasks"];
+
+/// Where clients find the server; rewritten on every start.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Endpoint {
+    pub host: String,
+    pub port: u16,
+    pub token: String,
+    pub pid: u32,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+impl RpcError {
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    /// A valid request that failed in the backend (whose errors are plain strings). Methods
+    /// check their params and targets first, so those failures carry their own codes.
+    pub fn failed(message: String) -> Self {
+        Self::new(OPERATION_FAILED, message)
+    }
+}
+
+/// A host event as subscribers see it.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HostEvent {
+    pub topic: String,
+    pub event: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task_id: Option<String>,
+    #[serde(default)]
+    pub payload: Value,
+}
+
+impl HostEvent {
+    /// A `vm_event`. An `rpc` line is named after its taskd event, or `response` for a
+    /// response, and carries the parsed line as its payload.
+    pub fn from_vm_event(event: &str, message: &str) -> Self {
+        if event != "rpc" {
+            return Self {
+                topic: "vm".to_string(),
+                event: event.to_string(),
+                task_id: None,
+                payload: Value::String(message.to_string()),
+            };
+        }
+
+        let line = serde_json::from_str(message).unwrap_or_else(|_| Value::String(message.to_string()));
+        let field = |name: &str| line.get(name).and_then(Value::as_str).map(str::to_string);
+        Self {
+            topic: "rpc".to_string(),
+            event: field("event").unwrap_or_else(|| "response".to_string()),
+            task_id: field("taskId"),
+            payload: line,
+        }
+    }
+
+    /// A task store change, named like the webview event for it (e.g. `task_updated`).
+    pub fn task(event: &str, task_id: Option<&str>, payload: Value) -> Self {
+        Self {
+            topic: "tasks".to_string(),
+            event: event.to_string(),
+            task_id: task_id.map(str::to_string),
+            payload,
+        }
+    }
+}
+
+/// Which events a subscriber wants; an empty list or missing `taskId` matches everything.
+#[derive(Deserialize, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Filter {
+    #[serde(default)]
+    pub topics: Vec<String>,
+    #[serde(default)]
+    pub task_id: Option<String>,
+    #[serde(default)]
+    pub events: Vec<String>,
+}
+
+impl Filter {
+    pub fn validate(&self) -> Result<(), String> {
+        match self.topics.iter().find(|topic| !TOPICS.contains(&topic.as_str())) {
+            Some(topic) => Err(format!("Invalid topic {topic}; expected one of {}", TOPICS.join(", "))),
+            None => Ok(()),
+        }
+    }
+
+    pub fn matches(&self, event: &HostEvent) -> bool {
+        (self.topics.is_empty() || self.topics.contains(&event.topic))
+            && (self.events.is_empty() || self.events.contains(&event.event))
+            && self
+                .task_id
+                .as_ref()
+                .is_none_or(|task_id| event.task_id.as_ref() == Some(task_id))
+    }
+}
+
+/// Fans host events out to subscribed connections, here and on the test server.
+#[derive(Clone, Default)]
+pub struct Events {
+    bus: Arc<RpcBus>,
+}
+
+impl Events {
+    pub fn publish(&self, event: &HostEvent) {
+        if let Ok(line) = serde_json::to_string(event) {
+            self.bus.publish(&line);
+        }
+    }
+
+    /// Events published from now on; pass the receiver to [`forward`] and the key to
+    /// [`unsubscribe`](Self::unsubscribe).
+    pub fn subscribe_keyed(&self) -> (u64, Receiver<String>) {
+        self.bus.subscribe_keyed()
+    }
+
+    /// Disconnects a keyed subscription's receiver, ending a [`forward`] blocked on it.
+    pub fn unsubscribe(&self, key: u64) {
+        self.bus.unsubscribe(key);
+    }
+}
+
+/// Hands each event from `lines` that matches `filter` to `send`, until `send` returns false,
+/// `cancelled` is set or `lines` disconnects. `cancelled` is checked for every line, matching or
+/// not, so a cancelled subscription never outlives the next event.
+pub fn forward(
+    lines: &Receiver<String>,
+    filter: &Filter,
+    cancelled: &AtomicBool,
+    mut send: impl FnMut(HostEvent) -> bool,
+) {
+    for line in lines {
+        if cancelled.load(Ordering::Relaxed) {
+            return;
+        }
+        let Ok(event) = serde_json::from_str::<HostEvent>(&line) else {
+            continue;
+        };
+        if filter.matches(&event) && !send(event) {
+            return;
+        }
+    }
+}
+
+/// Deserializes a method's `params`; a missing `params` reads as `{}`.
+pub fn params<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    let params = if params.is_null() {
+        Value::Object(serde_json::Map::new())
+    } else {
+        params
+    };
+    serde_json::from_value(params).map_err(|error| RpcError::new(INVALID_PARAMS, error.to_string()))
+}
+
+/// Serializes a method's result.
+pub fn result<T: Serialize>(value: T) -> Result<Value, RpcError> {
+    serde_json::to_value(value).map_err(|error| RpcError::new(INTERNAL_ERROR, error.to_string()))
+}
+
+/// Binds an ephemeral loopback port, publishes the endpoint file and serves connections on a
+/// background thread, each handled by `dispatch(method, params)`.
+pub fn start(
+    data_dir: &Path,
+    events: Events,
+    dispatch: impl Fn(&str, Value) -> Result<Value, RpcError> + Send + Sync + 'static,
+) -> Result<Endpoint, String> {
+    let listener = TcpListener::bind("127.0.0.1:0").map_err(|error| error.to_string())?;
+    let endpoint = Endpoint {
+        host: "127.0.0.1".to_string(),
+        port: listener.local_addr().map_err(|error| error.to_string())?.port(),
+        token: new_token()?,
+        pid: std::process::id(),
+    };
+
+    std::fs::create_dir_all(data_dir).map_err(|error| error.to_string())?;
+    let content = serde_json::to_string_pretty(&endpoint).map_err(|error| error.to_string())?;
+    persist::write_private(&data_dir.join(ENDPOINT_FILE), content.as_bytes())?;
+
+    let token = Arc::new(endpoint.token.clone());
+    let dispatch = Arc::new(dispatch);
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else { continue };
+            let (token, events, dispatch) = (token.clone(), events.clone(), dispatch.clone());
+            std::thread::spawn(move || {
+                if let Err(error) = serve_connection(stream, &token, events, &*dispatch) {
+                    eprintln!("[rust:automation] connection closed: {error}");
+                }
+            });
+        }
+    });
+
+    Ok(endpoint)
+}
+
+fn serve_connection(
+    stream: TcpStream,
+    token: &str,
+    events: Events,
+    dispatch: &dyn Fn(&str, Value) -> Result<Value, RpcError>,
+) -> Result<(), String> {
+    // Responses and subscription notifications share the stream, a line at a time.
+    let writer = Arc::new(Mutex::new(stream.try_clone().map_err(|error| error.to_string())?));
+    let write_line = {
+        let writer = writer.clone();
+        move |line: &str| writeln!(writer.lock().unwrap(), "{line}").is_ok()
+    };
+    let mut reader = BufReader::new(stream);
+    let mut session = Session::new(token, events, Arc::new(write_line.clone()));
+
+    loop {
+        let mut line = String::new();
+        let read = (&mut reader)
+            .take(MAX_REQUEST_BYTES + 1)
+            .read_line(&mut line)
+            .map_err(|error| error.to_string())?;
+        if read == 0 {
+            return Ok(());
+        }
+        if read as u64 > MAX_REQUEST_BYTES {
+            let response = error_response(&Value::Null, &RpcError::new(INVALID_REQUEST, "Request too large"));
+            write_line(&response.to_string());
+            return Err("request too large".to_string());
+        }
+
+        if let Some(response) = session.handle_line(&line, dispatch) {
+            if !write_line(&response) {
+                return Err("write failed".to_string());
+            }
+        }
+    }
+}
+
+/// Writes one line to the connection; false once it is gone.
+pub type Notify = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+
+/// One connection's state: whether it has authenticated, and its event subscriptions.
+pub struct Session<'a> {
+    token: &'a str,
+    authenticated: bool,
+    events: Events,
+    notify: Notify,
+    /// Cancel flag and bus key of each subscription.
+    subscriptions: HashMap<u64, (Arc<AtomicBool>, u64)>,
+    next_subscription: u64,
+}
+
+impl<'a> Session<'a> {
+    pub fn new(token: &'a str, events: Events, notify: Notify) -> Self {
+        Self {
+            token,
+            authenticated: false,
+            events,
+            notify,
+            subscriptions: HashMap::new(),
+            next_subscription: 1,
+        }
+    }
+
+    /// The response line for one request line, or `None` when nothing is owed (notifications
+    /// and blank lines).
+    pub fn handle_line(
+        &mut self,
+        line: &str,
+        dispatch: &dyn Fn(&str, Value) -> Result<Value, RpcError>,
+    ) -> Option<String> {
+        if line.trim().is_empty() {
+            return None;
+        }
+
+        let message: Value = match serde_json::from_str(line) {
+            Ok(message) => message,
+            Err(error) => {
+                return Some(error_response(&Value::Null, &RpcError::new(PARSE_ERROR, error.to_string())).to_string())
+            }
+        };
+
+        match message {
+            Value::Array(batch) if batch.is_empty() => {
+                Some(error_response(&Value::Null, &RpcError::new(INVALID_REQUEST, "Empty batch")).to_string())
+            }
+            Value::Array(batch) => {
+                let responses: Vec<Value> = batch
+                    .into_iter()
+                    .filter_map(|request| self.handle(request, dispatch))
+                    .collect();
+                (!responses.is_empty()).then(|| Value::Array(responses).to_string())
+            }
+            request => self.handle(request, dispatch).map(|response| response.to_string()),
+        }
+    }
+
+    fn handle(&mut self, request: Value, dispatch: &dyn Fn(&str, Value) -> Result<Value, RpcError>) -> Option<Value> {
+        #[derive(Deserialize)]
+        struct Request {
+            jsonrpc: String,
+            #[serde(default)]
+            id: Option<Value>,
+            method: String,
+            #[serde(default)]
+            params: Value,
+        }
+
+        let id = request.get("id").cloned();
+        let request = match serde_json::from_value::<Request>(request) {
+            Ok(request) if request.jsonrpc == "2.0" => request,
+            _ => {
+                return Some(error_response(
+                    &id.unwrap_or(Value::Null),
+                    &RpcError::new(INVALID_REQUEST, "Not a JSON-RPC 2.0 request"),
+                ))
+            }
+        };
+
+        let outcome = match request.method.as_str() {
+            "authenticate" => self.authenticate(request.params),
+            _ if !self.authenticated => Err(RpcError::new(
+                UNAUTHORIZED,
+                "Call authenticate with the session token first",
+            )),
+            "subscribe" => self.subscribe(request.params),
+            "unsubscribe" => self.unsubscribe(request.params),
+            method => dispatch(method, request.params),
+        };
+
+        // Requests without an id are notifications and get no response.
+        let id = request.id?;
+        Some(match outcome {
+            Ok(result) => serde_json::json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(error) => error_response(&id, &error),
+        })
+    }
+
+    fn authenticate(&mut self, params: Value) -> Result<Value, RpcError> {
+        #[derive(Deserialize)]
+        struct Params {
+            token: String,
+        }
+
+        let Params { token } = self::params(params)?;
+        if !constant_time_eq(token.as_bytes(), self.token.as_bytes()) {
+            return Err(RpcError::new(UNAUTHORIZED, "Invalid token"));
+        }
+
+        self.authenticated = true;
+        Ok(serde_json::json!({ "authenticated": true }))
+    }
+
+    /// Starts sending events matching the filter in `params` as `event` notifications, tagged
+    /// with the returned subscription id.
+    fn subscribe(&mut self, params: Value) -> Result<Value, RpcError> {
+        let filter: Filter = self::params(params)?;
+        filter
+            .validate()
+            .map_err(|error| RpcError::new(INVALID_PARAMS, error))?;
+
+        let id = self.next_subscription;
+        self.next_subscription += 1;
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let (key, lines) = self.events.subscribe_keyed();
+        self.subscriptions.insert(id, (cancelled.clone(), key));
+
+        let notify = self.notify.clone();
+        std::thread::spawn(move || {
+            forward(&lines, &filter, &cancelled, |event| {
+                let mut params = serde_json::to_value(event).unwrap_or_default();
+                params["subscription"] = id.into();
+                notify(&serde_json::json!({ "jsonrpc": "2.0", "method": "event", "params": params }).to_string())
+            });
+        });
+
+        Ok(serde_json::json!({ "subscription": id }))
+    }
+
+    fn unsubscribe(&mut self, params: Value) -> Result<Value, RpcError> {
+        #[derive(Deserialize)]
+        struct Params {
+            subscription: u64,
+        }
+
+        let Params { subscription } = self::params(params)?;
+        let (cancelled, key) = self
+            .subscriptions
+            .remove(&subscription)
+            .ok_or_else(|| RpcError::new(NOT_FOUND, format!("Subscription {subscription} not found")))?;
+        cancelled.store(true, Ordering::Relaxed);
+        self.events.unsubscribe(key);
+        Ok(Value::Null)
+    }
+}
+
+impl Drop for Session<'_> {
+    fn drop(&mut self) {
+        for (cancelled, key) in self.subscriptions.values() {
+            cancelled.store(true, Ordering::Relaxed);
+            self.events.unsubscribe(*key);
+        }
+    }
+}
+
+fn error_response(id: &Value, error: &RpcError) -> Value {
+    serde_json::json!({ "jsonrpc": "2.0", "id": id, "error": error })
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// 256 bits from the OS random number generator, hex-encoded.
+fn new_token() -> Result<String, String> {
+    let mut bytes = [0u8; 32];
+    getrandom::fill(&mut bytes).map_err(|error| format!("Failed to generate automation token: {error}"))?;
+    Ok(crate::persist::to_hex(&bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dispatch(method: &str, params: Value) -> Result<Value, RpcError> {
+        match method {
+            "echo" => Ok(params),
+            "tasks.get" => Err(RpcError::new(NOT_FOUND, "Task t1 not found")),
+            _ => Err(RpcError::new(METHOD_NOT_FOUND, format!("Unknown method {method}"))),
+        }
+    }
+
+    fn session() -> (Session<'static>, Events, Receiver<String>) {
+        let events = Events::default();
+        let (sender, notifications) = std::sync::mpsc::channel();
+        let sender = Mutex::new(sender);
+        let notify: Notify = Arc::new(move |line: &str| sender.lock().unwrap().send(line.to_string()).is_ok());
+        (Session::new("secret", events.clone(), notify), events, notifications)
+    }
+
+    fn respond(session: &mut Session, line: &str) -> Value {
+        serde_json::from_str(&session.handle_line(line, &dispatch).expect("response")).expect("json")
+    }
+
+    #[test]
+    fn requests_need_the_session_token() {
+        let (mut session, _, _) = session();
+
+        let denied = respond(
+            &mut session,
+            r#"{"jsonrpc":"2.0","id":1,"method":"echo","params":{"a":1}}"#,
+        );
+        assert_eq!(denied["error"]["code"], UNAUTHORIZED);
+        let wrong = respond(
+            &mut session,
+            r#"{"jsonrpc":"2.0","id":2,"method":"authenticate","params":{"token":"nope"}}"#,
+        );
+        assert_eq!(wrong["error"]["code"], UNAUTHORIZED);
+        let ok = respond(
+            &mut session,
+            r#"{"jsonrpc":"2.0","id":3,"method":"authenticate","params":{"token":"secret"}}"#,
+        );
+        assert_eq!(ok["result"]["authenticated"], true);
+
+        let echoed = respond(
+            &mut session,
+            r#"{"jsonrpc":"2.0","id":"x","method":"echo","params":{"a":1}}"#,
+        );
+        assert_eq!(
+            echoed,
+            serde_json::json!({ "jsonrpc": "2.0", "id": "x", "result": { "a": 1 } })
+        );
+    }
+
+    #[test]
+    fn errors_batches_and_notifications_follow_json_rpc() {
+        let (mut session, _, _) = session();
+        session.authenticated = true;
+
+        assert_eq!(respond(&mut session, "{")["error"]["code"], PARSE_ERROR);
+        assert_eq!(
+            respond(&mut session, r#"{"id":1,"method":"echo"}"#)["error"]["code"],
+            INVALID_REQUEST
+        );
+        assert_eq!(
+            respond(&mut session, r#"{"jsonrpc":"2.0","id":1,"method":"nope"}"#)["error"]["code"],
+            METHOD_NOT_FOUND
+        );
+        assert!(session
+            .handle_line(r#"{"jsonrpc":"2.0","method":"echo"}"#, &dispatch)
+            .is_none());
+
+        let batch = respond(
+            &mut session,
+            r#"[{"jsonrpc":"2.0","id":1,"method":"echo","params":[1]},{"jsonrpc":"2.0","method":"echo"},{"jsonrpc":"2.0","id":2,"method":"tasks.get"}]"#,
+        );
+        assert_eq!(batch.as_array().map(Vec::len), Some(2));
+        assert_eq!(batch[0]["result"], serde_json::json!([1]));
+        assert_eq!(batch[1]["error"]["code"], NOT_FOUND);
+        assert_ne!(new_token().expect("token"), new_token().expect("token"));
+    }
+
+    #[test]
+    fn subscriptions_stream_matching_events_until_cancelled() {
+        use std::time::Duration;
+
+        let (mut session, events, notifications) = session();
+        session.authenticated = true;
+
+        let invalid = respond(
+            &mut session,
+            r#"{"jsonrpc":"2.0","id":1,"method":"subscribe","params":{"topics":["nope"]}}"#,
+        );
+        assert_eq!(invalid["error"]["code"], INVALID_PARAMS);
+        let subscribed = respond(
+            &mut session,
+            r#"{"jsonrpc":"2.0","id":2,"method":"subscribe","params":{"topics":["rpc"],"taskId":"t1","events":["agent_end"]}}"#,
+        );
+        let id = subscribed["result"]["subscription"].clone();
+
+        let agent_end = |task_id: &str| format!(r#"{{"type":"event","event":"agent_end","taskId":"{task_id}"}}"#);
+        events.publish(&HostEvent::from_vm_event("rpc", &agent_end("t2")));
+        events.publish(&HostEvent::from_vm_event("ready", "READY"));
+        events.publish(&HostEvent::task("task_updated", Some("t1"), Value::Null));
+        events.publish(&HostEvent::from_vm_event("rpc", &agent_end("t1")));
+
+        let notification: Value =
+            serde_json::from_str(&notifications.recv_timeout(Duration::from_secs(5)).expect("event")).unwrap();
+        assert_eq!(notification["method"], "event");
+        assert_eq!(notification["params"]["subscription"], id);
+        assert_eq!(notification["params"]["taskId"], "t1");
+        assert_eq!(notification["params"]["payload"]["event"], "agent_end");
+
+        // The forwarding thread holds the other reference to `notify` until it exits.
+        assert_eq!(Arc::strong_count(&session.notify), 2);
+        let request = format!(r#"{{"jsonrpc":"2.0","id":3,"method":"unsubscribe","params":{{"subscription":{id}}}}}"#);
+        assert_eq!(respond(&mut session, &request)["result"], Value::Null);
+        events.publish(&HostEvent::from_vm_event("ready", "READY"));
+        events.publish(&HostEvent::from_vm_event("rpc", &agent_end("t1")));
+        assert!(notifications.recv_timeout(Duration::from_millis(200)).is_err());
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while Arc::strong_count(&session.notify) > 1 {
+            assert!(
+                std::time::Instant::now() < deadline,
+                "subscription thread still running"
+            );
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
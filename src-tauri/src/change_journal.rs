@@ -0,0 +1,396 @@
+use notify::event::{CreateKind, ModifyKind, RemoveKind, RenameMode};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const TASK_CHANGES_FILE: &str = "changes.jsonl";
+
+/// Repeated events for the same file and kind inside this window are journaled once.
+const COALESCE_WINDOW: Duration = Duration::from_millis(500);
+pub const IGNORED_COMPONENTS: [&str; 1] = [".git"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Created,
+    Modified,
+    Deleted,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChangeRoot {
+    #[serde(rename = "workingFolder")]
+    WorkingFolder,
+    #[serde(rename = "outputs")]
+    Outputs,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileChange {
+    pub task_id: String,
+    pub timestamp: u64,
+    pub kind: ChangeKind,
+    pub root: ChangeRoot,
+    pub path: String,
+}
+
+struct ActiveWatch {
+    task_id: String,
+    _watcher: RecommendedWatcher,
+}
+
+/// The watcher for the active task, if any. Replaced whenever a different task becomes active.
+#[derive(Default)]
+pub struct ChangeWatcherState {
+    inner: Mutex<Option<ActiveWatch>>,
+}
+
+pub fn task_changes_file(tasks_dir: &Path, task_id: &str) -> PathBuf {
+    crate::task_store::task_dir(tasks_dir, task_id).join(TASK_CHANGES_FILE)
+}
+
+pub fn append_change(journal_path: &Path, change: &FileChange) -> Result<(), String> {
+    if let Some(parent) = journal_path.parent() {
+        std::fs::create_dir_all(parent).map_err(|error| error.to_string())?;
+    }
+
+    let mut line = serde_json::to_string(change).map_err(|error| error.to_string())?;
+    line.push('\n');
+
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(journal_path)
+        .map_err(|error| error.to_string())?;
+    file.write_all(line.as_bytes()).map_err(|error| error.to_string())
+}
+
+/// Reads the task's change journal oldest-first. Malformed lines (e.g. a torn final write) are skipped.
+pub fn list_changes(tasks_dir: &Path, task_id: &str) -> Result<Vec<FileChange>, String> {
+    let journal_path = task_changes_file(tasks_dir, task_id);
+    if !journal_path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let file = std::fs::File::open(&journal_path).map_err(|error| error.to_string())?;
+    let mut changes = Vec::new();
+
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(|error| error.to_string())?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match serde_json::from_str::<FileChange>(&line) {
+            Ok(change) => changes.push(change),
+            Err(error) => eprintln!("[rust:changes] skipping malformed journal line: {error}"),
+        }
+    }
+
+    Ok(changes)
+}
+
+/// Starts journaling changes under `roots` for `task_id`, replacing any previous watch.
+///
+/// Each recorded change is appended to `tasks/<taskId>/changes.jsonl` and passed to `on_change`.
+pub fn watch<F>(
+    state: &ChangeWatcherState,
+    tasks_dir: &Path,
+    task_id: &str,
+    roots: &[(ChangeRoot, PathBuf)],
+    on_change: F,
+) -> Result<(), String>
+where
+    F: Fn(&FileChange) + Send + 'static,
+{
+    let mut inner = state.inner.lock().unwrap();
+    if inner.as_ref().is_some_and(|active| active.task_id == task_id) {
+        return Ok(());
+    }
+    inner.take();
+
+    let roots: Vec<(ChangeRoot, PathBuf)> = roots
+        .iter()
+        .filter_map(|(root, path)| match path.canonicalize() {
+            Ok(canonical) if canonical.is_dir() => Some((*root, canonical)),
+            _ => {
+                eprintln!("[rust:changes] skipping missing watch root: {}", path.display());
+                None
+            }
+        })
+        .collect();
+
+    let journal_path = task_changes_file(tasks_dir, task_id);
+    let handler_task_id = task_id.to_string();
+    let handler_roots = roots.clone();
+    let last_recorded: Mutex<Option<(FileChange, SystemTime)>> = Mutex::new(None);
+
+    let mut watcher = notify::recommended_watcher(move |result: notify::Result<Event>| {
+        let event = match result {
+            Ok(event) => event,
+            Err(error) => {
+                eprintln!("[rust:changes] watch error: {error}");
+                return;
+            }
+        };
+
+        for change in changes_from_event(&event, &handler_task_id, &handler_roots, now_millis()) {
+            let mut last = last_recorded.lock().unwrap();
+            if is_duplicate(last.as_ref(), &change) {
+                continue;
+            }
+
+            if let Err(error) = append_change(&journal_path, &change) {
+                eprintln!("[rust:changes] failed to append change: {error}");
+                continue;
+            }
+
+            on_change(&change);
+            *last = Some((change, SystemTime::now()));
+        }
+    })
+    .map_err(|error| error.to_string())?;
+
+    for (_, path) in &roots {
+        watcher
+            .watch(path, RecursiveMode::Recursive)
+            .map_err(|error| format!("Failed to watch {}: {error}", path.display()))?;
+    }
+
+    eprintln!("[rust:changes] watching {} root(s) for task {task_id}", roots.len());
+
+    *inner = Some(ActiveWatch {
+        task_id: task_id.to_string(),
+        _watcher: watcher,
+    });
+
+    Ok(())
+}
+
+pub fn unwatch(state: &ChangeWatcherState) {
+    state.inner.lock().unwrap().take();
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |value| u64::try_from(value.as_millis()).unwrap_or(u64::MAX))
+}
+
+fn is_duplicate(last: Option<&(FileChange, SystemTime)>, change: &FileChange) -> bool {
+    let Some((previous, recorded_at)) = last else {
+        return false;
+    };
+
+    previous.kind == change.kind
+        && previous.root == change.root
+        && previous.path == change.path
+        && recorded_at.elapsed().is_ok_and(|elapsed| elapsed < COALESCE_WINDOW)
+}
+
+fn classify(kind: EventKind) -> Vec<ChangeKind> {
+    match kind {
+        EventKind::Create(CreateKind::Folder)
+        | EventKind::Remove(RemoveKind::Folder)
+        | EventKind::Access(_)
+        | EventKind::Modify(ModifyKind::Metadata(_)) => Vec::new(),
+        EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(RenameMode::To)) => vec![ChangeKind::Created],
+        EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(RenameMode::From)) => vec![ChangeKind::Deleted],
+        // Rename with both ends known: paths are [from, to].
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => vec![ChangeKind::Deleted, ChangeKind::Created],
+        EventKind::Modify(_) | EventKind::Any | EventKind::Other => vec![ChangeKind::Modified],
+    }
+}
+
+fn changes_from_event(
+    event: &Event,
+    task_id: &str,
+    roots: &[(ChangeRoot, PathBuf)],
+    timestamp: u64,
+) -> Vec<FileChange> {
+    let kinds = classify(event.kind);
+    let paired = kinds.len() == event.paths.len();
+
+    event
+        .paths
+        .iter()
+        .enumerate()
+        .filter_map(|(index, path)| {
+            let kind = if paired { kinds[index] } else { *kinds.first()? };
+
+            // Renames and generic events can't tell files from folders; only files belong in the journal.
+            if kind != ChangeKind::Deleted && path.is_dir() {
+                return None;
+            }
+
+            let (root, relative) = relative_to_roots(path, roots)?;
+            Some(FileChange {
+                task_id: task_id.to_string(),
+                timestamp,
+                kind,
+                root,
+                path: relative,
+            })
+        })
+        .collect()
+}
+
+fn relative_to_roots(path: &Path, roots: &[(ChangeRoot, PathBuf)]) -> Option<(ChangeRoot, String)> {
+    // Prefer the most specific root in case one is nested inside another.
+    let (root, root_path) = roots
+        .iter()
+        .filter(|(_, root_path)| path.starts_with(root_path))
+        .max_by_key(|(_, root_path)| root_path.components().count())?;
+
+    let mut segments = Vec::new();
+    for component in path.strip_prefix(root_path).ok()?.components() {
+        let Component::Normal(value) = component else {
+            return None;
+        };
+        let segment = value.to_string_lossy();
+        if IGNORED_COMPONENTS.contains(&segment.as_ref()) {
+            return None;
+        }
+        segments.push(segment.to_string());
+    }
+
+    if segments.is_empty() {
+        return None;
+    }
+
+    Some((*root, segments.join("/")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    fn temp_dir() -> PathBuf {
+        let suffix = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let counter = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+        std::env::temp_dir().join(format!("piwork-changes-{suffix}-{counter}"))
+    }
+
+    fn event(kind: EventKind, paths: &[&Path]) -> Event {
+        let mut event = Event::new(kind);
+        for path in paths {
+            event = event.add_path(path.to_path_buf());
+        }
+        event
+    }
+
+    fn change(kind: ChangeKind, path: &str) -> FileChange {
+        FileChange {
+            task_id: "task-1".to_string(),
+            timestamp: 1,
+            kind,
+            root: ChangeRoot::WorkingFolder,
+            path: path.to_string(),
+        }
+    }
+
+    #[test]
+    fn journal_round_trips_and_skips_malformed_lines() {
+        let dir = temp_dir();
+        let journal_path = task_changes_file(&dir, "task-1");
+
+        append_change(&journal_path, &change(ChangeKind::Created, "a.txt")).expect("append");
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&journal_path)
+            .and_then(|mut file| file.write_all(b"{\"torn\n"))
+            .expect("append garbage");
+        append_change(&journal_path, &change(ChangeKind::Deleted, "a.txt")).expect("append");
+
+        let changes = list_changes(&dir, "task-1").expect("list");
+
+        assert_eq!(
+            changes,
+            vec![
+                change(ChangeKind::Created, "a.txt"),
+                change(ChangeKind::Deleted, "a.txt")
+            ]
+        );
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn missing_journal_lists_empty() {
+        let dir = temp_dir();
+
+        assert!(list_changes(&dir, "task-1").expect("list").is_empty());
+    }
+
+    #[test]
+    fn events_map_to_relative_paths_under_the_most_specific_root() {
+        let workspace = PathBuf::from("/work");
+        let outputs = PathBuf::from("/work/out");
+        let roots = vec![
+            (ChangeRoot::WorkingFolder, workspace.clone()),
+            (ChangeRoot::Outputs, outputs.clone()),
+        ];
+
+        let created = changes_from_event(
+            &event(EventKind::Create(CreateKind::File), &[&outputs.join("report.md")]),
+            "task-1",
+            &roots,
+            7,
+        );
+        assert_eq!(created.len(), 1);
+        assert_eq!(created[0].root, ChangeRoot::Outputs);
+        assert_eq!(created[0].path, "report.md");
+        assert_eq!(created[0].kind, ChangeKind::Created);
+
+        let renamed = changes_from_event(
+            &event(
+                EventKind::Modify(ModifyKind::Name(RenameMode::Both)),
+                &[&workspace.join("src/old.rs"), &workspace.join("src/new.rs")],
+            ),
+            "task-1",
+            &roots,
+            7,
+        );
+        let summary: Vec<(ChangeKind, &str)> = renamed.iter().map(|item| (item.kind, item.path.as_str())).collect();
+        assert_eq!(
+            summary,
+            vec![(ChangeKind::Deleted, "src/old.rs"), (ChangeKind::Created, "src/new.rs")]
+        );
+    }
+
+    #[test]
+    fn ignored_and_outside_paths_are_dropped() {
+        let roots = vec![(ChangeRoot::WorkingFolder, PathBuf::from("/work"))];
+        let kind = EventKind::Modify(ModifyKind::Data(notify::event::DataChange::Content));
+
+        assert!(changes_from_event(&event(kind, &[Path::new("/work/.git/index")]), "task-1", &roots, 1).is_empty());
+        assert!(changes_from_event(&event(kind, &[Path::new("/elsewhere/a.txt")]), "task-1", &roots, 1).is_empty());
+        assert!(changes_from_event(
+            &event(
+                EventKind::Access(notify::event::AccessKind::Any),
+                &[Path::new("/work/a.txt")]
+            ),
+            "task-1",
+            &roots,
+            1
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn repeated_change_inside_window_is_coalesced() {
+        let first = change(ChangeKind::Modified, "a.txt");
+        let recorded = (first.clone(), SystemTime::now());
+
+        assert!(is_duplicate(Some(&recorded), &first));
+        assert!(!is_duplicate(Some(&recorded), &change(ChangeKind::Deleted, "a.txt")));
+        assert!(!is_duplicate(None, &first));
+    }
+}
@@ -0,0 +1,349 @@
+//! `piwork-cli`: drives the runtime without the UI, for CI and batch jobs. It shares the app's
+//! data directory, so tasks it creates show up in the app and vice versa.
+//!
+//! stdout is JSON lines: every guest RPC line as-is, plus `{"type":"cli",...}` records for the
+//! selected task, each journaled file change, and the final outcome. Logs go to stderr.
+//!
+//! Like the app, a run snapshots the working folder and journals its changes, so the task can be
+//! restored from the app afterwards.
+
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::auth_store;
+use crate::change_journal::{self, ChangeWatcherState, FileChange};
+use crate::rpc_bus::RpcBus;
+use crate::task_service::{self, TaskService};
+use crate::task_status::{RpcStatusTracker, TaskStatus};
+use crate::task_store::{self, TaskMetadata};
+use crate::vm::{self, VmHost};
+
+const APP_IDENTIFIER: &str = "com.pi.work";
+const DATA_DIR_ENV_VAR: &str = "PIWORK_DATA_DIR";
+const DEFAULT_RUN_TIMEOUT_SECS: u64 = 60 * 60;
+
+const USAGE: &str = "\
+usage:
+  piwork-cli run --prompt <text|-> [--task <id> | --title <title>] [--folder <path>]
+                 [--provider <name>] [--model <id>] [--thinking <level>] [--timeout <secs>]
+  piwork-cli tasks
+  piwork-cli auth list
+  piwork-cli auth set <provider>        (reads the API key from stdin)
+
+options for every command:
+  --data-dir <path>      defaults to $PIWORK_DATA_DIR, then the app's data directory
+  --runtime-dir <path>   defaults to $PIWORK_RUNTIME_DIR, then <data-dir>/runtime
+
+exit status: 0 when the run completed, 1 when it failed, 2 for usage or setup errors";
+
+/// Entry point of the `piwork-cli` binary.
+pub fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    match run_command(&args) {
+        Ok(code) => code,
+        Err(error) => {
+            eprintln!("piwork-cli: {error}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+#[derive(Default)]
+struct Options {
+    positional: Vec<String>,
+    prompt: Option<String>,
+    task_id: Option<String>,
+    title: Option<String>,
+    folder: Option<String>,
+    provider: Option<String>,
+    model: Option<String>,
+    thinking_level: Option<String>,
+    timeout_secs: Option<u64>,
+    data_dir: Option<PathBuf>,
+    runtime_dir: Option<PathBuf>,
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            options.positional.push(arg.clone());
+            continue;
+        }
+
+        let value = args.next().cloned().ok_or_else(|| format!("{arg} needs a value"))?;
+        match arg.as_str() {
+            "--prompt" => options.prompt = Some(value),
+            "--task" => options.task_id = Some(value),
+            "--title" => options.title = Some(value),
+            "--folder" => options.folder = Some(value),
+            "--provider" => options.provider = Some(value),
+            "--model" => options.model = Some(value),
+            "--thinking" => options.thinking_level = Some(value),
+            "--timeout" => {
+                options.timeout_secs = Some(value.parse().map_err(|_| format!("invalid --timeout {value:?}"))?);
+            }
+            "--data-dir" => options.data_dir = Some(PathBuf::from(value)),
+            "--runtime-dir" => options.runtime_dir = Some(PathBuf::from(value)),
+            _ => return Err(format!("unknown option {arg}\n\n{USAGE}")),
+        }
+    }
+
+    Ok(options)
+}
+
+fn run_command(args: &[String]) -> Result<ExitCode, String> {
+    if args.is_empty() || args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{USAGE}");
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let options = parse_options(args)?;
+    let data_dir = match options.data_dir.clone() {
+        Some(data_dir) => data_dir,
+        None => default_data_dir()?,
+    };
+    let positional: Vec<&str> = options.positional.iter().map(String::as_str).collect();
+
+    match positional[..] {
+        ["run"] => run_prompt(&options, &data_dir),
+        ["tasks"] => {
+            for task in task_store::list_tasks(&data_dir.join("tasks"))? {
+                print_json(&serde_json::to_value(task).map_err(|error| error.to_string())?);
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+        ["auth", "list"] => {
+            let summary = auth_store::summary(&auth_file(&data_dir))?;
+            print_json(&serde_json::to_value(summary).map_err(|error| error.to_string())?);
+            Ok(ExitCode::SUCCESS)
+        }
+        ["auth", "set", provider] => {
+            let mut key = String::new();
+            std::io::stdin()
+                .read_to_string(&mut key)
+                .map_err(|error| error.to_string())?;
+            auth_store::set_api_key(&auth_file(&data_dir), provider, &key)?;
+            Ok(ExitCode::SUCCESS)
+        }
+        _ => Err(format!("unknown command\n\n{USAGE}")),
+    }
+}
+
+/// Boots the runtime for a new or existing task, sends one prompt and waits for it to finish.
+fn run_prompt(options: &Options, data_dir: &Path) -> Result<ExitCode, String> {
+    let prompt = match options.prompt.as_deref() {
+        Some("-") => {
+            let mut prompt = String::new();
+            std::io::stdin()
+                .read_to_string(&mut prompt)
+                .map_err(|error| error.to_string())?;
+            prompt
+        }
+        Some(prompt) => prompt.to_string(),
+        None => return Err(format!("run needs --prompt\n\n{USAGE}")),
+    };
+    if prompt.trim().is_empty() {
+        return Err("prompt is empty".to_string());
+    }
+
+    if auth_store::summary(&auth_file(data_dir))?.entries.is_empty() {
+        return Err("no API keys configured; add one with `piwork-cli auth set <provider>`".to_string());
+    }
+
+    let tasks_dir = data_dir.join("tasks");
+    let state = vm::VmState::default();
+    // Before anything is saved: the app (or another run) may already have a VM up.
+    vm::reserve(&state, data_dir)?;
+    let bus = Arc::new(RpcBus::default());
+    let service = TaskService {
+        tasks_dir: tasks_dir.clone(),
+        vm: &state,
+        bus: &bus,
+    };
+    let task = select_task(&service, options, &prompt)?;
+    print_json(&serde_json::json!({ "type": "cli", "event": "task", "task": task }));
+
+    let runtime_dir = match options.runtime_dir.clone() {
+        Some(runtime_dir) => runtime_dir,
+        None => crate::runtime_dir_in(data_dir),
+    };
+    let tracker = Arc::new(RpcStatusTracker::default());
+    tracker.reset(Some(&task.id));
+
+    let on_rpc_line = {
+        let (bus, tracker, tasks_dir) = (bus.clone(), tracker.clone(), tasks_dir.clone());
+        move |line: &str| {
+            println!("{line}");
+            bus.publish(line);
+            if let Some((task_id, status)) = tracker.observe_change(line) {
+                if let Err(error) = task_store::set_status(&tasks_dir, &task_id, status) {
+                    eprintln!("[rust:cli] {task_id} -> {}: {error}", status.as_str());
+                }
+            }
+        }
+    };
+
+    let host = CliHost {
+        data_dir: data_dir.to_path_buf(),
+        watcher: Arc::new(ChangeWatcherState::default()),
+    };
+    let result = crate::launch_task_vm(
+        &host,
+        &state,
+        data_dir,
+        &runtime_dir,
+        task.working_folder.as_deref(),
+        Some(&task.id),
+        on_rpc_line,
+    )
+    .and_then(|_| crate::wait_for_vm_ready(&state))
+    .and_then(|()| {
+        service.run_prompt(
+            &task,
+            &prompt,
+            Duration::from_secs(options.timeout_secs.unwrap_or(DEFAULT_RUN_TIMEOUT_SECS)),
+        )
+    });
+    vm::stop(&state);
+    change_journal::unwatch(&host.watcher);
+
+    let (status, error, code) = match result {
+        Ok(()) => (TaskStatus::Completed, None, ExitCode::SUCCESS),
+        Err(error) => (TaskStatus::Failed, Some(error), ExitCode::from(1)),
+    };
+    // Covers failures the guest never reported, e.g. a VM that didn't boot.
+    if let Err(error) = task_store::set_status(&tasks_dir, &task.id, status) {
+        eprintln!("[rust:cli] failed to record outcome: {error}");
+    }
+    print_json(&serde_json::json!({
+        "type": "cli",
+        "event": "outcome",
+        "taskId": task.id,
+        "status": status.as_str(),
+        "error": error,
+    }));
+
+    Ok(code)
+}
+
+/// The task named by `--task` with any setting overrides applied, or a new one.
+fn select_task(service: &TaskService, options: &Options, prompt: &str) -> Result<TaskMetadata, String> {
+    let mut task = if let Some(task_id) = options.task_id.as_deref() {
+        match options.folder.as_deref() {
+            Some(folder) => service.bind_folder(task_id, folder)?,
+            None => service.load(task_id)?,
+        }
+    } else {
+        let title = options
+            .title
+            .clone()
+            .unwrap_or_else(|| prompt.lines().next().unwrap_or_default().chars().take(60).collect());
+        task_service::new_task(&title, options.folder.clone())
+    };
+
+    for (field, value) in [
+        (&mut task.provider, &options.provider),
+        (&mut task.model, &options.model),
+        (&mut task.thinking_level, &options.thinking_level),
+    ] {
+        if value.is_some() {
+            field.clone_from(value);
+        }
+    }
+
+    service.create(task)
+}
+
+#[derive(Clone)]
+struct CliHost {
+    data_dir: PathBuf,
+    watcher: Arc<ChangeWatcherState>,
+}
+
+impl VmHost for CliHost {
+    fn data_dir(&self) -> Result<PathBuf, String> {
+        Ok(self.data_dir.clone())
+    }
+
+    fn emit_vm_event(&self, event: &str, message: String) {
+        // RPC lines are already printed by the line handler.
+        if event != "rpc" {
+            print_json(&serde_json::json!({ "type": "vm_event", "event": event, "message": message }));
+        }
+    }
+
+    fn change_watcher(&self) -> &ChangeWatcherState {
+        &self.watcher
+    }
+
+    fn file_changed(&self, change: &FileChange) {
+        print_json(&serde_json::json!({ "type": "cli", "event": "file_changed", "change": change }));
+    }
+}
+
+fn print_json(value: &serde_json::Value) {
+    println!("{value}");
+}
+
+fn auth_file(data_dir: &Path) -> PathBuf {
+    data_dir.join("auth").join("default").join("auth.json")
+}
+
+/// The directory the app uses (Tauri's app data dir for [`APP_IDENTIFIER`]).
+fn default_data_dir() -> Result<PathBuf, String> {
+    if let Some(data_dir) = std::env::var_os(DATA_DIR_ENV_VAR) {
+        return Ok(PathBuf::from(data_dir));
+    }
+
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    let base = if cfg!(target_os = "macos") {
+        home.map(|home| home.join("Library").join("Application Support"))
+    } else if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| home.map(|home| home.join(".local").join("share")))
+    };
+
+    base.map(|base| base.join(APP_IDENTIFIER))
+        .ok_or_else(|| format!("can't find the data directory; set {DATA_DIR_ENV_VAR} or pass --data-dir"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(raw: &[&str]) -> Vec<String> {
+        raw.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn options_split_commands_from_flags() {
+        let options = parse_options(&args(&[
+            "run",
+            "--prompt",
+            "Summarize",
+            "--timeout",
+            "90",
+            "--model",
+            "m1",
+        ]))
+        .expect("options");
+
+        assert_eq!(options.positional, vec!["run".to_string()]);
+        assert_eq!(options.prompt.as_deref(), Some("Summarize"));
+        assert_eq!(options.timeout_secs, Some(90));
+        assert_eq!(options.model.as_deref(), Some("m1"));
+        assert!(parse_options(&args(&["run", "--prompt"])).is_err());
+        assert!(parse_options(&args(&["run", "--timeout", "soon"])).is_err());
+        assert!(parse_options(&args(&["run", "--verbose", "1"])).is_err());
+    }
+}
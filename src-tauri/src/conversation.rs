@@ -0,0 +1,170 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Version written by this build. Files without `schemaVersion` are version 0.
+pub const CONVERSATION_SCHEMA_VERSION: u64 = 1;
+
+/// A task's conversation as cached by the frontend's message accumulator.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Conversation {
+    pub schema_version: u64,
+    pub messages: Vec<ConversationMessage>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ConversationMessage {
+    pub role: MessageRole,
+    pub blocks: Vec<ContentBlock>,
+    #[serde(default)]
+    pub is_streaming: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MessageRole {
+    User,
+    Assistant,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case", rename_all_fields = "camelCase")]
+pub enum ContentBlock {
+    Text {
+        text: String,
+        #[serde(default)]
+        is_streaming: bool,
+    },
+    Thinking {
+        text: String,
+        #[serde(default)]
+        is_streaming: bool,
+        #[serde(default)]
+        is_collapsed: bool,
+    },
+    ToolCall {
+        id: String,
+        name: String,
+        input: String,
+        #[serde(default)]
+        is_streaming: bool,
+        #[serde(default)]
+        is_collapsed: bool,
+    },
+    ToolResult {
+        tool_call_id: String,
+        output: String,
+        #[serde(default)]
+        is_error: bool,
+        #[serde(default)]
+        is_streaming: bool,
+        #[serde(default)]
+        is_collapsed: bool,
+    },
+}
+
+impl Conversation {
+    /// Parses a stored or submitted conversation, migrating older schema versions.
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        let value: Value = serde_json::from_str(raw).map_err(|error| format!("Invalid conversation JSON: {error}"))?;
+        let value = migrate(value)?;
+        serde_json::from_value(value).map_err(|error| format!("Invalid conversation: {error}"))
+    }
+
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string(self).map_err(|error| error.to_string())
+    }
+
+    /// The text blocks of every message, one per line. Thinking and tool blocks are left out.
+    pub fn text(&self) -> String {
+        self.messages
+            .iter()
+            .flat_map(|message| &message.blocks)
+            .filter_map(|block| match block {
+                ContentBlock::Text { text, .. } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<&str>>()
+            .join("\n")
+    }
+}
+
+/// Upgrades a raw conversation to [`CONVERSATION_SCHEMA_VERSION`], one version at a time.
+fn migrate(mut value: Value) -> Result<Value, String> {
+    let Some(object) = value.as_object_mut() else {
+        return Err("Invalid conversation: expected an object".to_string());
+    };
+
+    let mut version = match object.get("schemaVersion") {
+        None => 0,
+        Some(raw) => raw
+            .as_u64()
+            .ok_or_else(|| "Invalid conversation: schemaVersion must be a number".to_string())?,
+    };
+
+    if version > CONVERSATION_SCHEMA_VERSION {
+        return Err(format!(
+            "Conversation schemaVersion {version} is newer than supported ({CONVERSATION_SCHEMA_VERSION})"
+        ));
+    }
+
+    while version < CONVERSATION_SCHEMA_VERSION {
+        match version {
+            // v0 is the unversioned accumulator output; v1 only adds the version field.
+            0 => {}
+            _ => unreachable!("missing conversation migration from v{version}"),
+        }
+        version += 1;
+    }
+
+    object.insert("schemaVersion".to_string(), Value::from(version));
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_frontend_format_and_stamps_version() {
+        let raw = serde_json::json!({
+            "messages": [
+                { "role": "user", "blocks": [{ "type": "text", "text": "hi", "isStreaming": false }], "isStreaming": false },
+                {
+                    "role": "assistant",
+                    "blocks": [
+                        { "type": "thinking", "text": "hmm", "isStreaming": false, "isCollapsed": true },
+                        { "type": "tool_call", "id": "t1", "name": "bash", "input": "{}", "isStreaming": false, "isCollapsed": true },
+                        { "type": "tool_result", "toolCallId": "t1", "output": "ok", "isError": false, "isStreaming": false, "isCollapsed": true },
+                        { "type": "text", "text": "done", "isStreaming": false }
+                    ],
+                    "isStreaming": false
+                }
+            ]
+        })
+        .to_string();
+
+        let conversation = Conversation::parse(&raw).expect("parse");
+
+        assert_eq!(conversation.schema_version, CONVERSATION_SCHEMA_VERSION);
+        assert_eq!(conversation.messages.len(), 2);
+        assert_eq!(conversation.text(), "hi\ndone");
+        assert!(matches!(
+            &conversation.messages[1].blocks[2],
+            ContentBlock::ToolResult { tool_call_id, .. } if tool_call_id == "t1"
+        ));
+
+        let round_trip = Conversation::parse(&conversation.to_json().expect("json")).expect("reparse");
+        assert_eq!(round_trip, conversation);
+    }
+
+    #[test]
+    fn rejects_corrupt_payloads() {
+        assert!(Conversation::parse("{not json").is_err());
+        assert!(Conversation::parse("[]").is_err());
+        assert!(Conversation::parse(r#"{"messages":[{"role":"system","blocks":[]}]}"#).is_err());
+        assert!(Conversation::parse(r#"{"messages":[{"role":"user","blocks":[{"type":"image"}]}]}"#).is_err());
+        assert!(Conversation::parse(r#"{"schemaVersion":99,"messages":[]}"#).is_err());
+    }
+}
@@ -0,0 +1,309 @@
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::{Seek, SeekFrom, Write};
+use std::path::Path;
+
+use crate::conversation::{Conversation, ConversationMessage};
+use crate::persist;
+
+/// Compacted conversation state; the log is replayed on top of it.
+pub const CONVERSATION_SNAPSHOT_FILE: &str = "conversation.json";
+/// Append-only changes since the last compaction, one JSON entry per line.
+pub const CONVERSATION_LOG_FILE: &str = "conversation.jsonl";
+
+/// The log is folded into the snapshot once it outgrows the snapshot, but never below this size,
+/// so replay stays bounded without compacting short conversations on every save.
+const MIN_COMPACT_LOG_BYTES: u64 = 256 * 1024;
+
+/// The log opens with a `Base` entry naming the snapshot it extends, so a log left behind by an
+/// interrupted compaction is recognised as stale instead of being replayed over the new snapshot.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(tag = "op", rename_all = "camelCase")]
+enum LogEntry {
+    Base { snapshot: String },
+    Put { index: usize, message: ConversationMessage },
+    Truncate { len: usize },
+}
+
+struct Loaded {
+    conversation: Conversation,
+    /// Byte length of the log up to the last intact entry.
+    log_len: u64,
+    snapshot_len: u64,
+    /// SHA-256 of the snapshot bytes, as recorded in the log's `Base` entry.
+    snapshot_hash: String,
+}
+
+/// Rebuilds a task's conversation from its snapshot and log. `Ok(None)` when neither exists.
+pub fn load(task_folder: &Path) -> Result<Option<Conversation>, String> {
+    Ok(read(task_folder)?.map(|loaded| loaded.conversation))
+}
+
+/// Records `conversation` as the task's current state by appending only the messages that
+/// changed since the last save.
+pub fn save(task_folder: &Path, conversation: &Conversation) -> Result<(), String> {
+    let loaded = match read(task_folder) {
+        Ok(loaded) => loaded,
+        Err(error) => {
+            // An unreadable snapshot is superseded by the new state rather than blocking saves.
+            eprintln!("[rust:conversation-log] replacing unreadable conversation: {error}");
+            return compact(task_folder, conversation);
+        }
+    };
+
+    let Some(loaded) = loaded else {
+        return compact(task_folder, conversation);
+    };
+
+    let entries = diff(&loaded.conversation, conversation);
+    if entries.is_empty() {
+        return Ok(());
+    }
+
+    let mut payload = String::new();
+    let base = LogEntry::Base {
+        snapshot: loaded.snapshot_hash.clone(),
+    };
+    let header = (loaded.log_len == 0).then_some(&base);
+    for entry in header.into_iter().chain(&entries) {
+        payload.push_str(&serde_json::to_string(entry).map_err(|error| error.to_string())?);
+        payload.push('\n');
+    }
+
+    let log_len = loaded.log_len + payload.len() as u64;
+    if log_len > loaded.snapshot_len.max(MIN_COMPACT_LOG_BYTES) {
+        return compact(task_folder, conversation);
+    }
+
+    let mut log = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(false)
+        .open(task_folder.join(CONVERSATION_LOG_FILE))
+        .map_err(|error| error.to_string())?;
+    // Drops a torn tail left by a crash so the new entries start on a clean line.
+    log.set_len(loaded.log_len).map_err(|error| error.to_string())?;
+    log.seek(SeekFrom::End(0)).map_err(|error| error.to_string())?;
+    log.write_all(payload.as_bytes()).map_err(|error| error.to_string())?;
+    log.sync_data().map_err(|error| error.to_string())
+}
+
+fn read(task_folder: &Path) -> Result<Option<Loaded>, String> {
+    let snapshot_path = task_folder.join(CONVERSATION_SNAPSHOT_FILE);
+    let log_path = task_folder.join(CONVERSATION_LOG_FILE);
+
+    // Every save starts by writing a snapshot, so a log without one has nothing to extend.
+    let raw = match std::fs::read_to_string(&snapshot_path) {
+        Ok(raw) => raw,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(error.to_string()),
+    };
+    let mut conversation = Conversation::parse(&raw)?;
+    let snapshot_hash = persist::hex_sha256(raw.as_bytes());
+
+    let log = match std::fs::read(&log_path) {
+        Ok(log) => log,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(error) => return Err(error.to_string()),
+    };
+    let log_len = replay(&mut conversation, &snapshot_hash, &log);
+
+    Ok(Some(Loaded {
+        conversation,
+        log_len,
+        snapshot_len: raw.len() as u64,
+        snapshot_hash,
+    }))
+}
+
+/// Applies intact log entries in order and returns the byte length they cover. Replay stops at
+/// the first line that is unterminated or unparsable, which is what a crash mid-append leaves.
+/// A log whose `Base` doesn't match `snapshot_hash` is ignored as a whole.
+fn replay(conversation: &mut Conversation, snapshot_hash: &str, log: &[u8]) -> u64 {
+    let mut offset = 0;
+
+    while let Some(end) = log[offset..].iter().position(|byte| *byte == b'\n') {
+        let line = &log[offset..offset + end];
+        let entry = match serde_json::from_slice::<LogEntry>(line) {
+            Ok(LogEntry::Base { snapshot }) if offset == 0 && snapshot == snapshot_hash => None,
+            Ok(entry) if offset > 0 && !matches!(entry, LogEntry::Base { .. }) => Some(entry),
+            Ok(_) if offset == 0 => {
+                eprintln!("[rust:conversation-log] ignoring log written against an older snapshot");
+                break;
+            }
+            _ => {
+                eprintln!("[rust:conversation-log] ignoring log from byte {offset}: unreadable entry");
+                break;
+            }
+        };
+
+        if let Some(entry) = entry {
+            apply(&mut conversation.messages, entry);
+        }
+        offset += end + 1;
+    }
+
+    offset as u64
+}
+
+fn apply(messages: &mut Vec<ConversationMessage>, entry: LogEntry) {
+    match entry {
+        LogEntry::Put { index, message } if index < messages.len() => messages[index] = message,
+        LogEntry::Put { index, message } if index == messages.len() => messages.push(message),
+        LogEntry::Put { index, .. } => {
+            eprintln!("[rust:conversation-log] skipping entry for message {index} past the end");
+        }
+        LogEntry::Truncate { len } => messages.truncate(len),
+        LogEntry::Base { .. } => {}
+    }
+}
+
+fn diff(previous: &Conversation, next: &Conversation) -> Vec<LogEntry> {
+    let mut entries = Vec::new();
+
+    if next.messages.len() < previous.messages.len() {
+        entries.push(LogEntry::Truncate {
+            len: next.messages.len(),
+        });
+    }
+
+    for (index, message) in next.messages.iter().enumerate() {
+        if previous.messages.get(index) != Some(message) {
+            entries.push(LogEntry::Put {
+                index,
+                message: message.clone(),
+            });
+        }
+    }
+
+    entries
+}
+
+/// Writes `conversation` as the new snapshot and empties the log. The snapshot is replaced
+/// atomically; a crash before the log is cleared leaves a log whose `Base` no longer matches.
+fn compact(task_folder: &Path, conversation: &Conversation) -> Result<(), String> {
+    persist::write_atomic(
+        &task_folder.join(CONVERSATION_SNAPSHOT_FILE),
+        conversation.to_json()?.as_bytes(),
+    )?;
+
+    let log_path = task_folder.join(CONVERSATION_LOG_FILE);
+    if log_path.exists() {
+        let log = OpenOptions::new()
+            .write(true)
+            .open(&log_path)
+            .map_err(|error| error.to_string())?;
+        log.set_len(0).map_err(|error| error.to_string())?;
+        log.sync_data().map_err(|error| error.to_string())?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversation::{ContentBlock, MessageRole, CONVERSATION_SCHEMA_VERSION};
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    fn temp_dir() -> PathBuf {
+        let suffix = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let counter = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let dir = std::env::temp_dir().join(format!("piwork-conversation-log-{suffix}-{counter}"));
+        std::fs::create_dir_all(&dir).expect("create dir");
+        dir
+    }
+
+    fn conversation(texts: &[&str]) -> Conversation {
+        Conversation {
+            schema_version: CONVERSATION_SCHEMA_VERSION,
+            messages: texts
+                .iter()
+                .map(|text| ConversationMessage {
+                    role: MessageRole::User,
+                    blocks: vec![ContentBlock::Text {
+                        text: (*text).to_string(),
+                        is_streaming: false,
+                    }],
+                    is_streaming: false,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn stale_log_from_interrupted_compaction_is_ignored() {
+        let dir = temp_dir();
+        save(&dir, &conversation(&["a"])).expect("first save");
+        save(&dir, &conversation(&["a", "b"])).expect("append");
+        let stale_log = std::fs::read(dir.join(CONVERSATION_LOG_FILE)).expect("read log");
+
+        compact(&dir, &conversation(&["a", "c"])).expect("compact");
+        std::fs::write(dir.join(CONVERSATION_LOG_FILE), stale_log).expect("restore stale log");
+
+        assert_eq!(load(&dir).expect("load"), Some(conversation(&["a", "c"])));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    fn log_lines(dir: &Path) -> usize {
+        std::fs::read_to_string(dir.join(CONVERSATION_LOG_FILE)).map_or(0, |log| log.lines().count())
+    }
+
+    #[test]
+    fn saves_append_only_changed_messages_and_replay() {
+        let dir = temp_dir();
+
+        save(&dir, &conversation(&["a"])).expect("first save");
+        save(&dir, &conversation(&["a", "b"])).expect("append");
+        save(&dir, &conversation(&["a", "b2"])).expect("update last");
+        save(&dir, &conversation(&["a"])).expect("shrink");
+
+        assert_eq!(log_lines(&dir), 4);
+        assert_eq!(load(&dir).expect("load"), Some(conversation(&["a"])));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn torn_last_line_is_ignored_and_overwritten() {
+        let dir = temp_dir();
+        save(&dir, &conversation(&["a"])).expect("first save");
+        save(&dir, &conversation(&["a", "b"])).expect("append");
+
+        let mut log = OpenOptions::new()
+            .append(true)
+            .open(dir.join(CONVERSATION_LOG_FILE))
+            .expect("open log");
+        log.write_all(br#"{"op":"put","index":2,"mess"#).expect("tear");
+
+        assert_eq!(load(&dir).expect("load"), Some(conversation(&["a", "b"])));
+
+        save(&dir, &conversation(&["a", "b", "c"])).expect("save after tear");
+        assert_eq!(log_lines(&dir), 3);
+        assert_eq!(load(&dir).expect("reload"), Some(conversation(&["a", "b", "c"])));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn large_logs_compact_into_the_snapshot() {
+        let dir = temp_dir();
+        let long = "x".repeat(64 * 1024);
+        let mut texts: Vec<&str> = Vec::new();
+
+        for _ in 0..8 {
+            texts.push(&long);
+            save(&dir, &conversation(&texts)).expect("save");
+        }
+
+        assert!(log_lines(&dir) < 5);
+        assert_eq!(load(&dir).expect("load"), Some(conversation(&texts)));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
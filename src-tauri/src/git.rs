@@ -0,0 +1,208 @@
+use serde::Serialize;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+
+/// Diffs beyond this size are cut off and flagged as truncated.
+const MAX_DIFF_BYTES: usize = 1024 * 1024;
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct GitRepoInfo {
+    pub root: String,
+    /// `None` when HEAD is detached.
+    pub branch: Option<String>,
+    /// `None` for a repository without commits.
+    pub head: Option<String>,
+}
+
+#[derive(Serialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct GitStatusEntry {
+    pub path: String,
+    /// Set for renames and copies.
+    pub original_path: Option<String>,
+    /// Porcelain v1 `X` column (index).
+    pub index: String,
+    /// Porcelain v1 `Y` column (working tree).
+    pub worktree: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitStatusResponse {
+    pub repo: GitRepoInfo,
+    pub entries: Vec<GitStatusEntry>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitDiffResponse {
+    pub diff: String,
+    pub truncated: bool,
+}
+
+pub fn run_git(folder: &Path, args: &[&str]) -> Result<Vec<u8>, String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(folder)
+        .args(args)
+        .output()
+        .map_err(|error| format!("Failed to run git: {error}"))?;
+
+    git_output(args, output)
+}
+
+/// Like [`run_git`], feeding `input` to git's stdin (e.g. a patch for `git apply`).
+pub fn run_git_with_input(folder: &Path, args: &[&str], input: &[u8]) -> Result<Vec<u8>, String> {
+    let mut child = Command::new("git")
+        .arg("-C")
+        .arg(folder)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|error| format!("Failed to run git: {error}"))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(input).map_err(|error| error.to_string())?;
+    }
+
+    let output = child.wait_with_output().map_err(|error| error.to_string())?;
+    git_output(args, output)
+}
+
+fn git_output(args: &[&str], output: Output) -> Result<Vec<u8>, String> {
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("git {} failed: {}", args.join(" "), stderr.trim()));
+    }
+
+    Ok(output.stdout)
+}
+
+fn run_git_line(folder: &Path, args: &[&str]) -> Option<String> {
+    let stdout = run_git(folder, args).ok()?;
+    let line = String::from_utf8_lossy(&stdout).trim().to_string();
+    (!line.is_empty()).then_some(line)
+}
+
+/// Returns repository details when `folder` is inside a git work tree, `None` otherwise
+/// (including when git is not installed).
+pub fn detect(folder: &Path) -> Option<GitRepoInfo> {
+    let root = run_git_line(folder, &["rev-parse", "--show-toplevel"])?;
+    let branch = run_git_line(folder, &["symbolic-ref", "--quiet", "--short", "HEAD"]);
+    let head = run_git_line(folder, &["rev-parse", "--verify", "--quiet", "HEAD"]);
+
+    Some(GitRepoInfo { root, branch, head })
+}
+
+pub fn status(folder: &Path) -> Result<GitStatusResponse, String> {
+    let repo = detect(folder).ok_or_else(|| "Working folder is not a git repository".to_string())?;
+    let stdout = run_git(
+        folder,
+        &["status", "--porcelain=v1", "-z", "--untracked-files=all", "--", "."],
+    )?;
+
+    Ok(GitStatusResponse {
+        repo,
+        entries: parse_porcelain(&stdout),
+    })
+}
+
+/// Working-tree changes against HEAD (or against the index for a repository without commits),
+/// optionally limited to `relative_path`.
+pub fn diff(folder: &Path, relative_path: Option<&str>) -> Result<GitDiffResponse, String> {
+    let repo = detect(folder).ok_or_else(|| "Working folder is not a git repository".to_string())?;
+
+    let mut args = vec!["diff", "--no-color", "--no-ext-diff"];
+    if repo.head.is_some() {
+        args.push("HEAD");
+    }
+    args.push("--");
+    args.push(relative_path.unwrap_or("."));
+
+    let mut stdout = run_git(folder, &args)?;
+    let truncated = stdout.len() > MAX_DIFF_BYTES;
+    stdout.truncate(MAX_DIFF_BYTES);
+
+    Ok(GitDiffResponse {
+        diff: String::from_utf8_lossy(&stdout).to_string(),
+        truncated,
+    })
+}
+
+fn parse_porcelain(stdout: &[u8]) -> Vec<GitStatusEntry> {
+    let mut entries = Vec::new();
+    let mut records = stdout.split(|byte| *byte == 0).filter(|record| !record.is_empty());
+
+    while let Some(record) = records.next() {
+        if record.len() < 4 {
+            continue;
+        }
+
+        let index = char::from(record[0]).to_string();
+        let worktree = char::from(record[1]).to_string();
+        let path = String::from_utf8_lossy(&record[3..]).to_string();
+
+        // With -z, renames and copies are followed by a separate record holding the source path.
+        let original_path = if matches!(record[0], b'R' | b'C') || matches!(record[1], b'R' | b'C') {
+            records.next().map(|source| String::from_utf8_lossy(source).to_string())
+        } else {
+            None
+        };
+
+        entries.push(GitStatusEntry {
+            path,
+            original_path,
+            index,
+            worktree,
+        });
+    }
+
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    fn temp_dir() -> PathBuf {
+        let suffix = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let counter = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+        std::env::temp_dir().join(format!("piwork-git-{suffix}-{counter}"))
+    }
+
+    #[test]
+    fn porcelain_parses_renames_and_untracked() {
+        let stdout = b"R  new.rs\0old.rs\0 M src/lib.rs\0?? notes.md\0";
+
+        let entries = parse_porcelain(stdout);
+
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].path, "new.rs");
+        assert_eq!(entries[0].original_path.as_deref(), Some("old.rs"));
+        assert_eq!(entries[0].index, "R");
+        assert_eq!(entries[1].path, "src/lib.rs");
+        assert_eq!(entries[1].worktree, "M");
+        assert_eq!(entries[2].path, "notes.md");
+        assert_eq!(entries[2].index, "?");
+    }
+
+    #[test]
+    fn plain_folder_is_not_a_repository() {
+        let dir = temp_dir();
+        std::fs::create_dir_all(&dir).expect("create dir");
+
+        assert_eq!(detect(&dir), None);
+        assert!(status(&dir).is_err());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,469 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+
+use crate::change_journal::{self, ChangeRoot};
+use crate::git;
+use crate::persist;
+
+const TASK_BRANCH_PREFIX: &str = "piwork/";
+const MERGE_COMMIT_IDENTITY: [&str; 4] = ["-c", "user.name=piwork", "-c", "user.email=piwork@localhost"];
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum IsolationMode {
+    Worktree,
+    Copy,
+}
+
+/// Where an isolated task actually works, recorded in `task.json` at first bind.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskIsolation {
+    pub mode: IsolationMode,
+    /// The isolated working folder mounted into the VM.
+    pub folder: String,
+    /// The user's original working folder that merge-back writes to.
+    pub source: String,
+    /// Worktree mode: the source repository root, task branch and the commit merged up to.
+    pub repo_root: Option<String>,
+    pub branch: Option<String>,
+    pub base_head: Option<String>,
+}
+
+#[derive(Serialize, Default, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeReport {
+    /// Paths written to the original folder, relative to the repo root (worktree) or working folder (copy).
+    pub applied: Vec<String>,
+    /// Paths removed from the original folder (copy mode; worktree deletions are in `applied`).
+    pub removed: Vec<String>,
+    /// Copy mode: paths that are, or sit under, a symlink on either side and so were not merged.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub skipped: Vec<String>,
+}
+
+/// The isolated workspace lives beside the task folder, not in it, so it is never part of the
+/// task state shared with the guest.
+pub fn task_workspace_dir(tasks_dir: &Path, task_id: &str) -> PathBuf {
+    tasks_dir.join(crate::task_store::TASK_WORKSPACES_DIR).join(task_id)
+}
+
+/// Creates the isolated working folder for `task_id`.
+///
+/// Git repositories with at least one commit get a worktree on a `piwork/<taskId>` branch
+/// (uncommitted changes in the original are not carried over); anything else is copied, using
+/// copy-on-write clones where the host filesystem supports them.
+pub fn create(tasks_dir: &Path, task_id: &str, source: &Path) -> Result<TaskIsolation, String> {
+    let workspace = task_workspace_dir(tasks_dir, task_id);
+    if workspace.exists() {
+        return Err(format!("Isolated workspace already exists: {}", workspace.display()));
+    }
+
+    if let Some(repo) = git::detect(source).filter(|repo| repo.head.is_some()) {
+        let repo_root = PathBuf::from(&repo.root);
+        let relative = source
+            .canonicalize()
+            .ok()
+            .and_then(|canonical| {
+                canonical
+                    .strip_prefix(repo_root.canonicalize().ok()?)
+                    .ok()
+                    .map(Path::to_path_buf)
+            })
+            .unwrap_or_default();
+
+        let branch = format!("{TASK_BRANCH_PREFIX}{task_id}");
+        let workspace_arg = workspace.to_string_lossy();
+        git::run_git(&repo_root, &["worktree", "add", "-b", &branch, &workspace_arg, "HEAD"])?;
+
+        return Ok(TaskIsolation {
+            mode: IsolationMode::Worktree,
+            folder: workspace.join(relative).to_string_lossy().to_string(),
+            source: source.to_string_lossy().to_string(),
+            repo_root: Some(repo.root),
+            branch: Some(branch),
+            base_head: repo.head,
+        });
+    }
+
+    copy_tree(source, &workspace)?;
+
+    Ok(TaskIsolation {
+        mode: IsolationMode::Copy,
+        folder: workspace.to_string_lossy().to_string(),
+        source: source.to_string_lossy().to_string(),
+        repo_root: None,
+        branch: None,
+        base_head: None,
+    })
+}
+
+/// Deletes the isolated workspace, detaching a worktree from its repository and dropping the
+/// task branch first. Best effort: failures are logged, not returned.
+pub fn remove(tasks_dir: &Path, task_id: &str, isolation: &TaskIsolation) {
+    let workspace = task_workspace_dir(tasks_dir, task_id);
+
+    if let (Some(repo_root), IsolationMode::Worktree) = (isolation.repo_root.as_deref(), isolation.mode) {
+        let repo_root = Path::new(repo_root);
+        if let Err(error) = git::run_git(
+            repo_root,
+            &["worktree", "remove", "--force", &workspace.to_string_lossy()],
+        ) {
+            eprintln!("[rust:isolation] failed to remove worktree: {error}");
+            let _ = git::run_git(repo_root, &["worktree", "prune"]);
+        }
+
+        if let Some(branch) = isolation.branch.as_deref() {
+            let _ = git::run_git(repo_root, &["branch", "-D", branch]);
+        }
+    }
+
+    if workspace.exists() {
+        if let Err(error) = std::fs::remove_dir_all(&workspace) {
+            eprintln!("[rust:isolation] failed to remove workspace: {error}");
+        }
+    }
+}
+
+/// Re-points an isolation at its workspace after the task moved from `from_tasks_dir` to
+/// `to_tasks_dir` (archive and restore). Worktrees are repaired so git tracks the new location.
+pub fn relocate(from_tasks_dir: &Path, to_tasks_dir: &Path, task_id: &str, isolation: &TaskIsolation) -> TaskIsolation {
+    let old_workspace = task_workspace_dir(from_tasks_dir, task_id);
+    let new_workspace = task_workspace_dir(to_tasks_dir, task_id);
+    let relative = Path::new(&isolation.folder)
+        .strip_prefix(&old_workspace)
+        .unwrap_or_else(|_| Path::new(""));
+
+    if let (Some(repo_root), IsolationMode::Worktree) = (isolation.repo_root.as_deref(), isolation.mode) {
+        if let Err(error) = git::run_git(
+            Path::new(repo_root),
+            &["worktree", "repair", &new_workspace.to_string_lossy()],
+        ) {
+            eprintln!("[rust:isolation] failed to repair moved worktree: {error}");
+        }
+    }
+
+    TaskIsolation {
+        folder: new_workspace.join(relative).to_string_lossy().to_string(),
+        ..isolation.clone()
+    }
+}
+
+/// Applies the task's changes to the original working folder.
+///
+/// Worktree mode applies one patch of everything since `base_head` (all-or-nothing; fails on
+/// conflicts) and then commits it on the task branch, returning the new `base_head` so the same
+/// changes are not applied twice. Copy mode replays working-folder paths from the change journal.
+pub fn merge_back(
+    tasks_dir: &Path,
+    task_id: &str,
+    isolation: &TaskIsolation,
+) -> Result<(MergeReport, Option<String>), String> {
+    match isolation.mode {
+        IsolationMode::Worktree => merge_worktree(&task_workspace_dir(tasks_dir, task_id), isolation),
+        IsolationMode::Copy => merge_copy(tasks_dir, task_id, isolation).map(|report| (report, None)),
+    }
+}
+
+fn merge_worktree(worktree: &Path, isolation: &TaskIsolation) -> Result<(MergeReport, Option<String>), String> {
+    let repo_root = isolation
+        .repo_root
+        .as_deref()
+        .ok_or_else(|| "Worktree isolation is missing its repository root".to_string())?;
+    let base = isolation
+        .base_head
+        .as_deref()
+        .ok_or_else(|| "Worktree isolation is missing its base commit".to_string())?;
+
+    git::run_git(worktree, &["add", "-A"])?;
+    let names = git::run_git(worktree, &["diff", "--cached", "--name-only", "-z", base])?;
+    let applied: Vec<String> = names
+        .split(|byte| *byte == 0)
+        .filter(|name| !name.is_empty())
+        .map(|name| String::from_utf8_lossy(name).to_string())
+        .collect();
+
+    if applied.is_empty() {
+        return Ok((MergeReport::default(), None));
+    }
+
+    let patch = git::run_git(worktree, &["diff", "--cached", "--binary", base])?;
+    git::run_git_with_input(
+        Path::new(repo_root),
+        &["apply", "--binary", "--whitespace=nowarn"],
+        &patch,
+    )
+    .map_err(|error| format!("Merge back failed; resolve conflicts or merge the task branch manually: {error}"))?;
+
+    let mut commit_args = MERGE_COMMIT_IDENTITY.to_vec();
+    commit_args.extend([
+        "commit",
+        "--quiet",
+        "--no-verify",
+        "--allow-empty",
+        "-m",
+        "piwork: merged back",
+    ]);
+    git::run_git(worktree, &commit_args)?;
+    let head = git::run_git(worktree, &["rev-parse", "HEAD"])?;
+
+    Ok((
+        MergeReport {
+            applied,
+            ..MergeReport::default()
+        },
+        Some(String::from_utf8_lossy(&head).trim().to_string()),
+    ))
+}
+
+fn merge_copy(tasks_dir: &Path, task_id: &str, isolation: &TaskIsolation) -> Result<MergeReport, String> {
+    let folder = Path::new(&isolation.folder);
+    let source = Path::new(&isolation.source);
+    let mut report = MergeReport::default();
+
+    let paths: BTreeSet<String> = change_journal::list_changes(tasks_dir, task_id)?
+        .into_iter()
+        .filter(|change| change.root == ChangeRoot::WorkingFolder)
+        .map(|change| change.path)
+        .collect();
+
+    for relative in paths {
+        let relative_path = Path::new(&relative);
+        // The task can plant symlinks in its copy; following one would read from, or write and
+        // delete in, places outside the two folders.
+        if !relative_path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+            || persist::crosses_symlink(folder, relative_path)
+            || persist::crosses_symlink(source, relative_path)
+        {
+            report.skipped.push(relative);
+            continue;
+        }
+
+        let from = folder.join(relative_path);
+        let to = source.join(relative_path);
+        match std::fs::symlink_metadata(&from) {
+            Ok(metadata) if metadata.is_file() => {
+                if let Some(parent) = to.parent() {
+                    std::fs::create_dir_all(parent).map_err(|error| error.to_string())?;
+                }
+                std::fs::copy(&from, &to).map_err(|error| error.to_string())?;
+                report.applied.push(relative);
+            }
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound && to.is_file() => {
+                std::fs::remove_file(&to).map_err(|error| error.to_string())?;
+                report.removed.push(relative);
+            }
+            _ => {}
+        }
+    }
+
+    Ok(report)
+}
+
+/// Copies `source` to `destination`, which must not exist yet.
+pub fn copy_tree(source: &Path, destination: &Path) -> Result<(), String> {
+    if let Some(parent) = destination.parent() {
+        std::fs::create_dir_all(parent).map_err(|error| error.to_string())?;
+    }
+
+    if clone_tree(source, destination) {
+        return Ok(());
+    }
+
+    let mut stack = vec![(source.to_path_buf(), destination.to_path_buf())];
+    while let Some((from_dir, to_dir)) = stack.pop() {
+        std::fs::create_dir_all(&to_dir).map_err(|error| error.to_string())?;
+
+        for entry in std::fs::read_dir(&from_dir).map_err(|error| error.to_string())? {
+            let entry = entry.map_err(|error| error.to_string())?;
+            let file_type = entry.file_type().map_err(|error| error.to_string())?;
+            let target = to_dir.join(entry.file_name());
+
+            if file_type.is_dir() {
+                stack.push((entry.path(), target));
+            } else if file_type.is_file() {
+                std::fs::copy(entry.path(), &target).map_err(|error| error.to_string())?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Copy-on-write clone via the platform `cp`; returns false so the caller can fall back to a plain copy.
+fn clone_tree(source: &Path, destination: &Path) -> bool {
+    let mut command = Command::new("cp");
+    if cfg!(target_os = "macos") {
+        command.arg("-Rc");
+    } else if cfg!(target_os = "linux") {
+        command.arg("-R").arg("--reflink=auto");
+    } else {
+        return false;
+    }
+
+    let cloned = command
+        .arg(source)
+        .arg(destination)
+        .status()
+        .is_ok_and(|status| status.success());
+
+    if !cloned {
+        let _ = std::fs::remove_dir_all(destination);
+    }
+
+    cloned
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::change_journal::{ChangeKind, FileChange};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    fn temp_dir() -> PathBuf {
+        let suffix = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let counter = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+        std::env::temp_dir().join(format!("piwork-isolation-{suffix}-{counter}"))
+    }
+
+    fn record(tasks_dir: &Path, kind: ChangeKind, path: &str) {
+        let change = FileChange {
+            task_id: "task-1".to_string(),
+            timestamp: 1,
+            kind,
+            root: ChangeRoot::WorkingFolder,
+            path: path.to_string(),
+        };
+        change_journal::append_change(&change_journal::task_changes_file(tasks_dir, "task-1"), &change)
+            .expect("append change");
+    }
+
+    #[test]
+    fn copy_isolation_merges_journaled_changes_back() {
+        let root = temp_dir();
+        let tasks_dir = root.join("tasks");
+        let source = root.join("work");
+        std::fs::create_dir_all(source.join("docs")).expect("create source");
+        std::fs::write(source.join("docs/readme.md"), "original").expect("write");
+        std::fs::write(source.join("old.txt"), "remove me").expect("write");
+
+        let isolation = create(&tasks_dir, "task-1", &source).expect("create");
+        assert_eq!(isolation.mode, IsolationMode::Copy);
+
+        let folder = PathBuf::from(&isolation.folder);
+        std::fs::write(folder.join("docs/readme.md"), "edited").expect("edit");
+        std::fs::remove_file(folder.join("old.txt")).expect("delete");
+        std::fs::write(folder.join("new.txt"), "new").expect("create");
+        assert_eq!(
+            std::fs::read_to_string(source.join("docs/readme.md")).unwrap(),
+            "original"
+        );
+
+        record(&tasks_dir, ChangeKind::Modified, "docs/readme.md");
+        record(&tasks_dir, ChangeKind::Deleted, "old.txt");
+        record(&tasks_dir, ChangeKind::Created, "new.txt");
+
+        let (report, base_head) = merge_back(&tasks_dir, "task-1", &isolation).expect("merge");
+
+        assert_eq!(
+            report.applied,
+            vec!["docs/readme.md".to_string(), "new.txt".to_string()]
+        );
+        assert_eq!(report.removed, vec!["old.txt".to_string()]);
+        assert_eq!(base_head, None);
+        assert_eq!(
+            std::fs::read_to_string(source.join("docs/readme.md")).unwrap(),
+            "edited"
+        );
+        assert!(!source.join("old.txt").exists());
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn copy_merge_skips_symlinks_planted_in_the_copy() {
+        let root = temp_dir();
+        let tasks_dir = root.join("tasks");
+        let source = root.join("work");
+        let secrets = root.join("secrets");
+        std::fs::create_dir_all(&source).expect("create source");
+        std::fs::create_dir_all(&secrets).expect("create secrets");
+        std::fs::write(source.join("kept.txt"), "keep me").expect("write");
+        std::fs::write(secrets.join("id_rsa"), "private").expect("write");
+
+        let isolation = create(&tasks_dir, "task-1", &source).expect("create");
+        let folder = PathBuf::from(&isolation.folder);
+        std::os::unix::fs::symlink(&secrets, folder.join("docs")).expect("dir link");
+        std::fs::remove_file(folder.join("kept.txt")).expect("delete");
+        std::os::unix::fs::symlink(root.join("missing"), folder.join("kept.txt")).expect("dangling link");
+
+        record(&tasks_dir, ChangeKind::Created, "docs/id_rsa");
+        record(&tasks_dir, ChangeKind::Modified, "kept.txt");
+
+        let (report, _) = merge_back(&tasks_dir, "task-1", &isolation).expect("merge");
+
+        assert!(report.applied.is_empty());
+        assert!(report.removed.is_empty());
+        assert_eq!(report.skipped, vec!["docs/id_rsa".to_string(), "kept.txt".to_string()]);
+        assert!(!source.join("docs").exists());
+        assert_eq!(std::fs::read_to_string(source.join("kept.txt")).unwrap(), "keep me");
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn worktree_isolation_applies_patch_once() {
+        let root = temp_dir();
+        let tasks_dir = root.join("tasks");
+        let repo = root.join("repo");
+        std::fs::create_dir_all(repo.join("app")).expect("create repo");
+        std::fs::write(repo.join("app/main.txt"), "v1\n").expect("write");
+        git::run_git(&repo, &["init", "--quiet"]).expect("init");
+        git::run_git(&repo, &["add", "-A"]).expect("add");
+        let mut commit_args = MERGE_COMMIT_IDENTITY.to_vec();
+        commit_args.extend(["commit", "--quiet", "-m", "initial"]);
+        git::run_git(&repo, &commit_args).expect("commit");
+
+        let source = repo.join("app");
+        let isolation = create(&tasks_dir, "task-1", &source).expect("create");
+        assert_eq!(isolation.mode, IsolationMode::Worktree);
+        assert!(isolation.folder.ends_with("app"));
+
+        std::fs::write(Path::new(&isolation.folder).join("main.txt"), "v2\n").expect("edit");
+        let (report, base_head) = merge_back(&tasks_dir, "task-1", &isolation).expect("merge");
+
+        assert_eq!(report.applied, vec!["app/main.txt".to_string()]);
+        assert_eq!(std::fs::read_to_string(source.join("main.txt")).unwrap(), "v2\n");
+
+        let merged = TaskIsolation {
+            base_head,
+            ..isolation.clone()
+        };
+        let (again, _) = merge_back(&tasks_dir, "task-1", &merged).expect("second merge");
+        assert!(again.applied.is_empty());
+
+        remove(&tasks_dir, "task-1", &isolation);
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn create_refuses_existing_workspace() {
+        let root = temp_dir();
+        let tasks_dir = root.join("tasks");
+        let source = root.join("work");
+        std::fs::create_dir_all(&source).expect("create source");
+        std::fs::create_dir_all(task_workspace_dir(&tasks_dir, "task-1")).expect("create workspace");
+
+        assert!(create(&tasks_dir, "task-1", &source).is_err());
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+}
@@ -1,24 +1,41 @@
 use base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine as _};
-use std::collections::HashMap;
 use std::io::{BufRead, BufReader, Write};
 use std::net::TcpListener;
 use std::path::{Path, PathBuf};
 use std::process::Command;
-#[cfg(debug_assertions)]
-use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::{mpsc, Mutex};
-#[cfg(debug_assertions)]
-use std::time::Duration;
-use std::time::UNIX_EPOCH;
+use std::time::{Duration, Instant, UNIX_EPOCH};
 use tauri::{Emitter, Manager};
 
 mod auth_store;
+mod automation;
+mod change_journal;
+pub mod cli;
+mod conversation;
+mod conversation_log;
+mod git;
+mod isolation;
+mod mount;
+mod persist;
+mod pi_session;
+mod rpc_bus;
+mod rpc_replay;
+mod scheduler;
+mod snapshot;
+mod task_bundle;
+mod task_index;
+mod task_service;
+mod task_status;
 mod task_store;
+mod template_store;
 mod vm;
+mod webview_bus;
 
 const RUNTIME_MANIFEST: &str = "manifest.json";
 const RUNTIME_ENV_VAR: &str = "PIWORK_RUNTIME_DIR";
 const WORKSPACE_ROOT_ENV_VAR: &str = "PIWORK_WORKSPACE_ROOT";
+const HOST_VM_READY_TIMEOUT_SECS: u64 = 120;
+/// Longest a scheduled prompt may run before it is recorded as failed.
+const SCHEDULED_RUN_TIMEOUT_SECS: u64 = 60 * 60;
 
 #[derive(Debug, serde::Serialize)]
 #[serde(rename_all = "snake_case")]
@@ -44,6 +61,7 @@ struct WorkingFolderValidation {
     folder: String,
     workspace_root: String,
     relative_path: String,
+    git: Option<git::GitRepoInfo>,
 }
 
 #[derive(serde::Serialize)]
@@ -240,6 +258,7 @@ fn runtime_validate_working_folder(
         folder: canonical_folder.to_string_lossy().to_string(),
         workspace_root: canonical_root.to_string_lossy().to_string(),
         relative_path,
+        git: git::detect(&canonical_folder),
     })
 }
 
@@ -710,12 +729,15 @@ fn task_artifact_read(
 }
 
 fn runtime_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
-    if let Ok(override_dir) = std::env::var(RUNTIME_ENV_VAR) {
-        return Ok(PathBuf::from(override_dir));
-    }
-
     let base_dir = app.path().app_data_dir().map_err(|error| error.to_string())?;
-    Ok(base_dir.join("runtime"))
+    Ok(runtime_dir_in(&base_dir))
+}
+
+fn runtime_dir_in(data_dir: &Path) -> PathBuf {
+    match std::env::var(RUNTIME_ENV_VAR) {
+        Ok(override_dir) => PathBuf::from(override_dir),
+        Err(_) => data_dir.join("runtime"),
+    }
 }
 
 fn tasks_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
@@ -723,6 +745,11 @@ fn tasks_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
     Ok(base_dir.join("tasks"))
 }
 
+fn templates_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let base_dir = app.path().app_data_dir().map_err(|error| error.to_string())?;
+    Ok(base_dir.join("templates"))
+}
+
 fn auth_file(app: &tauri::AppHandle) -> Result<PathBuf, String> {
     let base_dir = app.path().app_data_dir().map_err(|error| error.to_string())?;
     Ok(base_dir.join("auth").join("default").join("auth.json"))
@@ -743,7 +770,20 @@ fn is_valid_task_id(task_id: &str) -> bool {
     !task_id.is_empty() && !task_id.contains('/') && !task_id.contains('\\') && !task_id.contains("..")
 }
 
-#[tauri::command]
+fn task_state_mounts(tasks_path: &Path, task_id: &str) -> Result<vm::TaskStateMounts, String> {
+    task_store::ensure_task_artifact_dirs(tasks_path, task_id)?;
+
+    let session_dir = task_store::task_session_dir(tasks_path, task_id);
+    std::fs::create_dir_all(&session_dir).map_err(|error| error.to_string())?;
+
+    Ok(vm::TaskStateMounts {
+        outputs: task_store::task_outputs_dir(tasks_path, task_id),
+        uploads: task_store::task_uploads_dir(tasks_path, task_id),
+        session: session_dir,
+    })
+}
+
+#[tauri::command(async)]
 #[allow(clippy::needless_pass_by_value)]
 fn vm_start(
     app: tauri::AppHandle,
@@ -751,49 +791,409 @@ fn vm_start(
     working_folder: Option<String>,
     task_id: Option<String>,
 ) -> Result<vm::VmStatusResponse, String> {
-    let runtime_dir = runtime_dir(&app)?;
+    start_task_vm(&app, &state, working_folder.as_deref(), task_id.as_deref())
+}
+
+/// Boots the VM with `task_id`'s state mounted (if given).
+fn start_task_vm(
+    app: &tauri::AppHandle,
+    state: &vm::VmState,
+    working_folder: Option<&str>,
+    task_id: Option<&str>,
+) -> Result<vm::VmStatusResponse, String> {
+    let data_dir = app.path().app_data_dir().map_err(|error| error.to_string())?;
+    let tasks_path = tasks_dir(app)?;
+
+    if matches!(vm::status(state).status, vm::VmStatus::Stopped) {
+        app.state::<task_status::RpcStatusTracker>().reset(task_id);
+    }
+
+    let observer = app.clone();
+    launch_task_vm(
+        app,
+        state,
+        &data_dir,
+        &runtime_dir(app)?,
+        working_folder,
+        task_id,
+        move |line| observe_rpc_line(&observer, &tasks_path, line),
+    )
+}
+
+/// The host folder mounted as the VM's working folder: the workspace root when one is set,
+/// the task's isolated copy, or `working_folder`.
+fn task_vm_folder(
+    data_dir: &Path,
+    working_folder: Option<&str>,
+    task_id: Option<&str>,
+) -> Result<Option<PathBuf>, String> {
     let folder_path = if let Some(workspace_root) = resolve_workspace_root_from_env()? {
         Some(workspace_root)
     } else {
-        working_folder.as_ref().map(std::path::PathBuf::from)
+        working_folder.map(std::path::PathBuf::from)
+    };
+
+    // Isolated tasks work in their own copy of the working folder.
+    let isolated_folder = match task_id {
+        Some(task_id) => task_store::load_task(&data_dir.join("tasks"), task_id)?
+            .and_then(|task| task.isolation)
+            .map(|isolation| PathBuf::from(isolation.folder)),
+        None => None,
     };
 
-    if let Some(task_id) = task_id.as_deref() {
+    Ok(isolated_folder.or(folder_path))
+}
+
+/// Boots the VM with the folder the task works in and its state mounted, for any [`vm::VmHost`].
+///
+/// The working folder is snapshotted and watched before QEMU starts, so the guest can't change
+/// a file before its original is kept.
+fn launch_task_vm(
+    host: &impl vm::VmHost,
+    state: &vm::VmState,
+    data_dir: &Path,
+    runtime_dir: &Path,
+    working_folder: Option<&str>,
+    task_id: Option<&str>,
+    on_rpc_line: impl Fn(&str) + Send + 'static,
+) -> Result<vm::VmStatusResponse, String> {
+    if let Some(task_id) = task_id {
         if !is_valid_task_id(task_id) {
             return Err("Invalid task id".to_string());
         }
     }
 
-    let auth_state_path = app
-        .path()
-        .app_data_dir()
-        .map_err(|error| error.to_string())?
-        .join("auth");
+    let auth_state_path = data_dir.join("auth");
     std::fs::create_dir_all(auth_state_path.join("default")).map_err(|error| error.to_string())?;
 
-    let task_state_path = tasks_dir(&app)?;
-    std::fs::create_dir_all(&task_state_path).map_err(|error| error.to_string())?;
+    let tasks_path = data_dir.join("tasks");
+    let folder_path = task_vm_folder(data_dir, working_folder, task_id)?;
+
+    // A running VM is reused as-is by `vm::start`, so only a call that boots it sets up watching.
+    let booting = matches!(vm::status(state).status, vm::VmStatus::Stopped);
+    if let Some(task_id) = task_id.filter(|_| booting) {
+        prepare_task_files(host, &tasks_path, task_id, folder_path.as_deref())?;
+    }
+
+    let task_state = match task_id {
+        Some(task_id) => Some(task_state_mounts(&tasks_path, task_id)?),
+        None => None,
+    };
 
-    vm::start(
-        &app,
-        &state,
-        &runtime_dir,
+    let started = vm::start(
+        host,
+        state,
+        runtime_dir,
         folder_path.as_deref(),
-        Some(task_state_path.as_path()),
+        task_state.as_ref(),
         Some(auth_state_path.as_path()),
-        task_id.as_deref(),
-    )
+        task_id,
+        on_rpc_line,
+    );
+    if started.is_err() && booting {
+        change_journal::unwatch(host.change_watcher());
+    }
+    started
+}
+
+/// Keeps the pre-run copy of the task's working folder and starts journaling changes to it.
+fn prepare_task_files(
+    host: &impl vm::VmHost,
+    tasks_path: &Path,
+    task_id: &str,
+    folder_path: Option<&Path>,
+) -> Result<(), String> {
+    if let Some(folder) = folder_path {
+        let report = snapshot::capture(tasks_path, task_id, folder)
+            .map_err(|error| format!("Failed to snapshot the working folder: {error}"))?;
+        if let Some(warning) = report.warning() {
+            host.emit_vm_event("warning", warning);
+        }
+    }
+
+    let mut roots = vec![(
+        change_journal::ChangeRoot::Outputs,
+        task_store::task_outputs_dir(tasks_path, task_id),
+    )];
+    if let Some(folder) = folder_path {
+        roots.push((change_journal::ChangeRoot::WorkingFolder, folder.to_path_buf()));
+    }
+
+    let notifier = host.clone();
+    change_journal::watch(host.change_watcher(), tasks_path, task_id, &roots, move |change| {
+        notifier.file_changed(change);
+    })
+}
+
+/// Hands a guest RPC line to host-side listeners (see [`rpc_bus::RpcBus`]) and the status tracker.
+fn observe_rpc_line(app: &tauri::AppHandle, tasks_path: &Path, line: &str) {
+    app.state::<rpc_bus::RpcBus>().publish(line);
+    observe_task_status(app, tasks_path, line);
+}
+
+/// Persists status changes implied by guest RPC traffic and tells the frontend about them.
+fn observe_task_status(app: &tauri::AppHandle, tasks_path: &Path, line: &str) {
+    let Some((task_id, status)) = app.state::<task_status::RpcStatusTracker>().observe_change(line) else {
+        return;
+    };
+
+    match task_store::set_status(tasks_path, &task_id, status) {
+        Ok(Some(task)) => emit_task_event(app, "task_status", Some(&task.id), &task),
+        Ok(None) => {}
+        Err(error) => eprintln!("[rust:task-status] {task_id} -> {}: {error}", status.as_str()),
+    }
+}
+
+/// Runs due scheduled tasks every [`scheduler::TICK_SECS`], one at a time. A fire time that
+/// passes while a run is in progress is picked up by the next tick.
+fn run_scheduler(app: &tauri::AppHandle) {
+    let mut last_tick = task_store::now_ms();
+
+    loop {
+        std::thread::sleep(Duration::from_secs(scheduler::TICK_SECS));
+        let now = task_store::now_ms();
+
+        let tasks_path = match tasks_dir(app) {
+            Ok(tasks_path) => tasks_path,
+            Err(error) => {
+                eprintln!("[rust:scheduler] {error}");
+                continue;
+            }
+        };
+        let tasks = task_store::list_tasks(&tasks_path).unwrap_or_else(|error| {
+            eprintln!("[rust:scheduler] failed to list tasks: {error}");
+            Vec::new()
+        });
+
+        for task in tasks {
+            let Some(due) = task
+                .schedule
+                .as_ref()
+                .and_then(|schedule| scheduler::due_at(schedule, last_tick, now))
+            else {
+                continue;
+            };
+            run_scheduled_task(app, &tasks_path, &task, due);
+        }
+
+        last_tick = now;
+    }
+}
+
+/// Runs one scheduled prompt and records it in the task's `runs.jsonl`.
+fn run_scheduled_task(app: &tauri::AppHandle, tasks_path: &Path, task: &task_store::TaskMetadata, due_ms: i64) {
+    let record = scheduler::RunRecord::started(due_ms);
+    if let Err(error) = scheduler::append_run(tasks_path, &task.id, &record) {
+        eprintln!("[rust:scheduler] failed to record run for {}: {error}", task.id);
+    }
+    emit_task_event(
+        app,
+        "task_run",
+        Some(&task.id),
+        &serde_json::json!({ "taskId": task.id, "run": record }),
+    );
+
+    let finished = record.finish(drive_scheduled_run(app, task));
+    if let Err(error) = scheduler::append_run(tasks_path, &task.id, &finished) {
+        eprintln!("[rust:scheduler] failed to record run for {}: {error}", task.id);
+    }
+    emit_task_event(
+        app,
+        "task_run",
+        Some(&task.id),
+        &serde_json::json!({ "taskId": task.id, "run": finished }),
+    );
+}
+
+/// Boots the VM for the task if nothing is running, sends the scheduled prompt and waits for the
+/// agent to finish. A VM the user has open on another task is left alone, and a VM booted here
+/// keeps running if the user (or the automation API) started using it during the run.
+fn drive_scheduled_run(app: &tauri::AppHandle, task: &task_store::TaskMetadata) -> Result<(), scheduler::RunError> {
+    use scheduler::RunError::{Failed, Skipped};
+
+    let Some(schedule) = task.schedule.as_ref() else {
+        return Err(Skipped("Task has no schedule".to_string()));
+    };
+    if matches!(
+        task.status,
+        task_status::TaskStatus::Running | task_status::TaskStatus::WaitingForApproval
+    ) {
+        return Err(Skipped("Task is already running".to_string()));
+    }
+
+    let state = app.state::<vm::VmState>();
+    let vm_status = vm::status(&state);
+    let start_vm = matches!(vm_status.status, vm::VmStatus::Stopped);
+    if !start_vm && vm_status.task_id.as_deref() != Some(task.id.as_str()) {
+        return Err(Skipped("The VM is in use by another task".to_string()));
+    }
+
+    let service = task_service(app).map_err(Failed)?;
+    if start_vm {
+        start_task_vm(app, &state, task.working_folder.as_deref(), Some(&task.id)).map_err(Failed)?;
+        vm::claim(&state, vm::VmOwner::Background);
+    }
+
+    let result = wait_for_vm_ready(&state)
+        .and_then(|()| service.run_prompt(task, &schedule.prompt, Duration::from_secs(SCHEDULED_RUN_TIMEOUT_SECS)));
+
+    if start_vm && vm::stop_if_owned(&state, vm::VmOwner::Background) {
+        change_journal::unwatch(&app.state::<change_journal::ChangeWatcherState>());
+    }
+    result.map_err(Failed)
+}
+
+fn wait_for_vm_ready(state: &vm::VmState) -> Result<(), String> {
+    let deadline = Instant::now() + Duration::from_secs(HOST_VM_READY_TIMEOUT_SECS);
+
+    while Instant::now() < deadline {
+        match vm::status(state).status {
+            vm::VmStatus::Ready => return Ok(()),
+            vm::VmStatus::Stopped => return Err("VM stopped before it was ready".to_string()),
+            vm::VmStatus::Starting => std::thread::sleep(Duration::from_millis(500)),
+        }
+    }
+
+    Err("VM did not become ready in time".to_string())
+}
+
+fn task_service(app: &tauri::AppHandle) -> Result<task_service::TaskService<'_>, String> {
+    Ok(task_service::TaskService {
+        tasks_dir: tasks_dir(app)?,
+        vm: app.state::<vm::VmState>().inner(),
+        bus: app.state::<rpc_bus::RpcBus>().inner(),
+    })
+}
+
+/// Tells the webview about a task change and publishes it to event subscribers.
+fn emit_task_event(
+    app: &tauri::AppHandle,
+    event: &str,
+    task_id: Option<&str>,
+    payload: &(impl serde::Serialize + Clone),
+) {
+    let _ = app.emit(event, payload.clone());
+    publish_task_event(app, event, task_id, payload);
+}
+
+/// Publishes a task change to event subscribers only, for changes the webview made itself.
+fn publish_task_event(app: &tauri::AppHandle, event: &str, task_id: Option<&str>, payload: &impl serde::Serialize) {
+    let payload = serde_json::to_value(payload).unwrap_or_default();
+    app.state::<automation::Events>()
+        .publish(&automation::HostEvent::task(event, task_id, payload));
+}
+
+fn stop_task_vm(app: &tauri::AppHandle) {
+    vm::stop(&app.state::<vm::VmState>());
+    change_journal::unwatch(&app.state::<change_journal::ChangeWatcherState>());
+}
+
+/// Makes `task_id` the task open in the runtime and tells the UI to follow. The VM is
+/// restarted with the task's state mounted unless it already runs with it (or `restart`).
+fn activate_task(app: &tauri::AppHandle, task_id: &str, restart: bool) -> Result<task_store::TaskMetadata, String> {
+    let service = task_service(app)?;
+    let task = service.load(task_id)?;
+    let state = app.state::<vm::VmState>();
+    vm::claim(&state, vm::VmOwner::Interactive);
+
+    let status = vm::status(&state);
+    if restart || matches!(status.status, vm::VmStatus::Stopped) || status.task_id.as_deref() != Some(task_id) {
+        stop_task_vm(app);
+        start_task_vm(app, &state, task.working_folder.as_deref(), Some(&task.id))?;
+    }
+
+    wait_for_vm_ready(&state)?;
+    service.open(&task)?;
+    emit_task_event(app, "task_activated", Some(task_id), &task);
+    Ok(task)
+}
+
+/// Binds a task's working folder (see [`task_service::TaskService::bind_folder`]). A task open
+/// in the runtime is reopened on the new folder; without a workspace root the folder is only
+/// mounted at boot, so the VM is restarted.
+fn bind_task_folder(app: &tauri::AppHandle, task_id: &str, folder: &str) -> Result<task_store::TaskMetadata, String> {
+    let service = task_service(app)?;
+    let was_bound = service.load(task_id)?.working_folder.is_some();
+    let task = service.bind_folder(task_id, folder)?;
+
+    if was_bound || mounted_task_id(app).as_deref() != Some(task_id) {
+        emit_task_event(app, "task_updated", Some(task_id), &task);
+        return Ok(task);
+    }
+
+    let restart = resolve_workspace_root_from_env()?.is_none();
+    if !restart {
+        service.stop(task_id)?;
+    }
+    activate_task(app, task_id, restart)
+}
+
+/// Sends a prompt to the task open in the runtime and returns its prompt id once taskd
+/// accepts it. The UI shows the message as if it had been typed.
+fn prompt_task(app: &tauri::AppHandle, task_id: &str, message: &str) -> Result<String, String> {
+    vm::claim(&app.state::<vm::VmState>(), vm::VmOwner::Interactive);
+    let prompt_id = task_service(app)?.prompt(task_id, message)?;
+    emit_task_event(
+        app,
+        "task_prompted",
+        Some(task_id),
+        &serde_json::json!({ "taskId": task_id, "message": message, "promptId": prompt_id }),
+    );
+    Ok(prompt_id)
+}
+
+/// Creates a task and opens it in the runtime.
+#[tauri::command(async)]
+#[allow(clippy::needless_pass_by_value)]
+fn task_create(
+    app: tauri::AppHandle,
+    title: String,
+    working_folder: Option<String>,
+) -> Result<task_store::TaskMetadata, String> {
+    let task = task_service(&app)?.create(task_service::new_task(&title, working_folder))?;
+    activate_task(&app, &task.id, false)
+}
+
+#[tauri::command(async)]
+#[allow(clippy::needless_pass_by_value)]
+fn task_activate(app: tauri::AppHandle, task_id: String) -> Result<task_store::TaskMetadata, String> {
+    activate_task(&app, &task_id, false)
+}
+
+#[tauri::command(async)]
+#[allow(clippy::needless_pass_by_value)]
+fn task_bind_folder(
+    app: tauri::AppHandle,
+    task_id: String,
+    folder: String,
+) -> Result<task_store::TaskMetadata, String> {
+    bind_task_folder(&app, &task_id, &folder)
+}
+
+#[tauri::command(async)]
+#[allow(clippy::needless_pass_by_value)]
+fn task_prompt(app: tauri::AppHandle, task_id: String, message: String) -> Result<String, String> {
+    prompt_task(&app, &task_id, &message)
+}
+
+#[tauri::command(async)]
+#[allow(clippy::needless_pass_by_value)]
+fn task_stop(app: tauri::AppHandle, task_id: String) -> Result<(), String> {
+    let service = task_service(&app)?;
+    service.load(&task_id)?;
+    service.stop(&task_id)
 }
 
 #[tauri::command]
 #[allow(clippy::needless_pass_by_value)]
-fn vm_stop(state: tauri::State<vm::VmState>) {
-    vm::stop(&state);
+fn vm_stop(app: tauri::AppHandle) {
+    stop_task_vm(&app);
 }
 
 #[tauri::command]
 #[allow(clippy::needless_pass_by_value)]
 fn rpc_send(state: tauri::State<vm::VmState>, message: String) -> Result<(), String> {
+    vm::claim(&state, vm::VmOwner::Interactive);
     vm::send(&state, &message)
 }
 
@@ -804,25 +1204,254 @@ fn task_store_list(app: tauri::AppHandle) -> Result<Vec<task_store::TaskMetadata
     task_store::list_tasks(&tasks_dir)
 }
 
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+fn task_store_query(
+    app: tauri::AppHandle,
+    query: Option<task_index::TaskQuery>,
+) -> Result<task_index::TaskPage, String> {
+    let tasks_dir = tasks_dir(&app)?;
+    task_index::query(&tasks_dir, &query.unwrap_or_default())
+}
+
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+fn task_search(
+    app: tauri::AppHandle,
+    query: String,
+    limit: Option<u32>,
+) -> Result<Vec<task_index::TaskSearchHit>, String> {
+    let tasks_dir = tasks_dir(&app)?;
+    task_index::search(&tasks_dir, &query, limit)
+}
+
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+fn task_store_reindex(app: tauri::AppHandle) -> Result<(), String> {
+    let tasks_dir = tasks_dir(&app)?;
+    task_index::rebuild(&tasks_dir)
+}
+
+/// Task folders whose `task.json` can't be parsed; they are left out of `task_store_list`.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+fn task_store_quarantined(app: tauri::AppHandle) -> Result<Vec<task_store::QuarantinedTask>, String> {
+    let tasks_dir = tasks_dir(&app)?;
+    Ok(task_store::scan_tasks(&tasks_dir)?.quarantined)
+}
+
 #[tauri::command]
 #[allow(clippy::needless_pass_by_value)]
 fn task_store_upsert(app: tauri::AppHandle, task: task_store::TaskMetadata) -> Result<(), String> {
+    let service = task_service(&app)?;
+    let task_id = task.id.clone();
+    service.save(task)?;
+    publish_task_event(&app, "task_updated", Some(&task_id), &service.load(&task_id)?);
+    Ok(())
+}
+
+/// Forks a task at `message_index` (see [`task_store::fork_task`]). The fork is unbound unless
+/// `working_folder` is given, in which case it is bound like a new task.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+fn task_store_fork(
+    app: tauri::AppHandle,
+    task_id: String,
+    message_index: usize,
+    working_folder: Option<String>,
+) -> Result<task_store::TaskMetadata, String> {
+    if !is_valid_task_id(&task_id) {
+        return Err("Invalid task id".to_string());
+    }
+
+    let service = task_service(&app)?;
+    let tasks_dir = &service.tasks_dir;
+    let mut task = task_store::fork_task(tasks_dir, &task_id, message_index)?;
+    if working_folder.is_some() {
+        task.working_folder = working_folder;
+        if let Err(error) = service.save(task.clone()) {
+            task_store::discard_task(tasks_dir, &task.id);
+            return Err(error);
+        }
+    }
+
+    let task = task_store::load_task(tasks_dir, &task.id)?.ok_or_else(|| "Forked task disappeared".to_string())?;
+    publish_task_event(&app, "task_updated", Some(&task.id), &task);
+    Ok(task)
+}
+
+/// A task's scheduled runs, oldest first.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+fn task_store_runs(app: tauri::AppHandle, task_id: String) -> Result<Vec<scheduler::RunRecord>, String> {
+    if !is_valid_task_id(&task_id) {
+        return Err("Invalid task id".to_string());
+    }
+
     let tasks_dir = tasks_dir(&app)?;
-    task_store::upsert_task(&tasks_dir, &task)
+    scheduler::list_runs(&tasks_dir, &task_id)
+}
+
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+fn template_store_list(app: tauri::AppHandle) -> Result<Vec<template_store::TaskTemplate>, String> {
+    let templates_dir = templates_dir(&app)?;
+    template_store::list_templates(&templates_dir)
+}
+
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+fn template_store_upsert(app: tauri::AppHandle, template: template_store::TaskTemplate) -> Result<(), String> {
+    if !is_valid_task_id(&template.id) {
+        return Err("Invalid template id".to_string());
+    }
+
+    let templates_dir = templates_dir(&app)?;
+    template_store::upsert_template(&templates_dir, &template)
 }
 
 #[tauri::command]
 #[allow(clippy::needless_pass_by_value)]
-fn task_store_delete(app: tauri::AppHandle, task_id: String) -> Result<(), String> {
+fn template_store_delete(app: tauri::AppHandle, template_id: String) -> Result<(), String> {
+    if !is_valid_task_id(&template_id) {
+        return Err("Invalid template id".to_string());
+    }
+
+    let templates_dir = templates_dir(&app)?;
+    template_store::delete_template(&templates_dir, &template_id)
+}
+
+/// Creates and saves a task from a template, bound to `working_folder` if given, with the
+/// template's uploads copied in. The client sends the returned `initialPrompt` to start it.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+fn task_create_from_template(
+    app: tauri::AppHandle,
+    template_id: String,
+    working_folder: Option<String>,
+) -> Result<template_store::TemplateInstance, String> {
+    if !is_valid_task_id(&template_id) {
+        return Err("Invalid template id".to_string());
+    }
+
+    let templates_dir = templates_dir(&app)?;
+    let service = task_service(&app)?;
+    let template =
+        template_store::load_template(&templates_dir, &template_id)?.ok_or_else(|| "Template not found".to_string())?;
+
+    let task = service.create(template_store::new_task(&template, working_folder))?;
+    if let Err(error) = template_store::copy_uploads(&templates_dir, &template_id, &service.tasks_dir, &task.id) {
+        task_store::discard_task(&service.tasks_dir, &task.id);
+        return Err(error);
+    }
+
+    publish_task_event(&app, "task_updated", Some(&task.id), &task);
+    Ok(template_store::TemplateInstance {
+        task,
+        initial_prompt: template.initial_prompt,
+    })
+}
+
+/// The task whose folders are mounted into a running VM; it can't be moved to the archive.
+fn mounted_task_id(app: &tauri::AppHandle) -> Option<String> {
+    let status = vm::status(&app.state::<vm::VmState>());
+    if matches!(status.status, vm::VmStatus::Stopped) {
+        return None;
+    }
+    status.task_id
+}
+
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+fn task_store_archive(app: tauri::AppHandle, task_id: String) -> Result<(), String> {
+    if !is_valid_task_id(&task_id) {
+        return Err("Invalid task id".to_string());
+    }
+    if mounted_task_id(&app).as_deref() == Some(task_id.as_str()) {
+        return Err("Task is mounted in the running VM; stop it before archiving".to_string());
+    }
+
     let tasks_dir = tasks_dir(&app)?;
-    task_store::delete_task(&tasks_dir, task_id)
+    task_store::archive_task(&tasks_dir, &task_id)?;
+    publish_task_event(&app, "task_archived", Some(&task_id), &task_id);
+    Ok(())
 }
 
+/// Archives every task except the one mounted in a running VM.
 #[tauri::command]
 #[allow(clippy::needless_pass_by_value)]
-fn task_store_delete_all(app: tauri::AppHandle) -> Result<(), String> {
+fn task_store_archive_all(app: tauri::AppHandle) -> Result<(), String> {
     let tasks_dir = tasks_dir(&app)?;
-    task_store::delete_all_tasks(&tasks_dir)
+    task_store::archive_all_tasks(&tasks_dir, mounted_task_id(&app).as_deref())?;
+    publish_task_event(&app, "task_list_changed", None, &());
+    Ok(())
+}
+
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+fn task_store_list_archived(app: tauri::AppHandle) -> Result<Vec<task_store::TaskMetadata>, String> {
+    let tasks_dir = tasks_dir(&app)?;
+    Ok(task_store::list_archived_tasks(&tasks_dir)?.tasks)
+}
+
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+fn task_store_restore(app: tauri::AppHandle, task_id: String) -> Result<task_store::TaskMetadata, String> {
+    if !is_valid_task_id(&task_id) {
+        return Err("Invalid task id".to_string());
+    }
+
+    let tasks_dir = tasks_dir(&app)?;
+    let task = task_store::restore_task(&tasks_dir, &task_id)?;
+    publish_task_event(&app, "task_updated", Some(&task_id), &task);
+    Ok(task)
+}
+
+/// Permanently deletes one archived task, or every archived task when `task_id` is omitted.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+fn task_store_purge(app: tauri::AppHandle, task_id: Option<String>) -> Result<(), String> {
+    let tasks_dir = tasks_dir(&app)?;
+    match task_id {
+        Some(task_id) if is_valid_task_id(&task_id) => task_store::purge_archived_task(&tasks_dir, &task_id),
+        Some(_) => Err("Invalid task id".to_string()),
+        None => task_store::purge_all_archived_tasks(&tasks_dir),
+    }
+}
+
+/// Writes a task to `destination` as a shareable bundle, with credentials redacted.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+fn task_export(
+    app: tauri::AppHandle,
+    task_id: String,
+    destination: String,
+    include_session: Option<bool>,
+) -> Result<task_bundle::BundleManifest, String> {
+    if !is_valid_task_id(&task_id) {
+        return Err("Invalid task id".to_string());
+    }
+
+    let tasks_dir = tasks_dir(&app)?;
+    let secrets = auth_store::secret_values(&auth_file(&app)?)?;
+    task_bundle::export(
+        &tasks_dir,
+        &task_id,
+        Path::new(&destination),
+        include_session.unwrap_or(false),
+        &secrets,
+    )
+}
+
+/// Creates a new, unbound task from a bundle written by `task_export`.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+fn task_import(app: tauri::AppHandle, bundle_path: String) -> Result<task_store::TaskMetadata, String> {
+    let tasks_dir = tasks_dir(&app)?;
+    let secrets = auth_store::secret_values(&auth_file(&app)?)?;
+    let task = task_bundle::import(&tasks_dir, Path::new(&bundle_path), &secrets)?;
+    publish_task_event(&app, "task_updated", Some(&task.id), &task);
+    Ok(task)
 }
 
 #[tauri::command]
@@ -843,6 +1472,108 @@ fn task_store_load_conversation(app: tauri::AppHandle, task_id: String) -> Resul
     task_store::load_conversation(&tasks_dir, &task_id)
 }
 
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+fn task_changes_list(app: tauri::AppHandle, task_id: String) -> Result<Vec<change_journal::FileChange>, String> {
+    if !is_valid_task_id(&task_id) {
+        return Err("Invalid task id".to_string());
+    }
+
+    let tasks_dir = tasks_dir(&app)?;
+    change_journal::list_changes(&tasks_dir, &task_id)
+}
+
+fn resolve_task_working_folder(app: &tauri::AppHandle, task_id: &str) -> Result<(PathBuf, PathBuf), String> {
+    if !is_valid_task_id(task_id) {
+        return Err("Invalid task id".to_string());
+    }
+
+    let tasks_path = tasks_dir(app)?;
+    let task = task_store::load_task(&tasks_path, task_id)?.ok_or_else(|| "Task not found".to_string())?;
+    let working_folder = task
+        .isolation
+        .map(|isolation| isolation.folder)
+        .or(task.working_folder)
+        .ok_or_else(|| "Task has no working folder".to_string())?;
+    let validated = runtime_validate_working_folder(working_folder, None)?;
+
+    Ok((tasks_path, PathBuf::from(validated.folder)))
+}
+
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+fn task_restore_file(
+    app: tauri::AppHandle,
+    task_id: String,
+    relative_path: String,
+) -> Result<snapshot::RestoreReport, String> {
+    let (tasks_path, working_folder) = resolve_task_working_folder(&app, &task_id)?;
+    let relative_path = normalize_preview_relative_path(&relative_path)?
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().to_string())
+        .collect::<Vec<String>>()
+        .join("/");
+
+    snapshot::restore_file(&tasks_path, &task_id, &working_folder, &relative_path)
+}
+
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+fn task_restore_all(app: tauri::AppHandle, task_id: String) -> Result<snapshot::RestoreReport, String> {
+    let (tasks_path, working_folder) = resolve_task_working_folder(&app, &task_id)?;
+    snapshot::restore_all(&tasks_path, &task_id, &working_folder)
+}
+
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+fn task_git_status(app: tauri::AppHandle, task_id: String) -> Result<git::GitStatusResponse, String> {
+    let (_, working_folder) = resolve_task_working_folder(&app, &task_id)?;
+    git::status(&working_folder)
+}
+
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+fn task_git_diff(
+    app: tauri::AppHandle,
+    task_id: String,
+    relative_path: Option<String>,
+) -> Result<git::GitDiffResponse, String> {
+    let (_, working_folder) = resolve_task_working_folder(&app, &task_id)?;
+    let relative_path = relative_path
+        .as_deref()
+        .map(normalize_preview_relative_path)
+        .transpose()?;
+
+    git::diff(
+        &working_folder,
+        relative_path.as_deref().map(|path| path.to_string_lossy()).as_deref(),
+    )
+}
+
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+fn task_merge_back(app: tauri::AppHandle, task_id: String) -> Result<isolation::MergeReport, String> {
+    if !is_valid_task_id(&task_id) {
+        return Err("Invalid task id".to_string());
+    }
+
+    let tasks_path = tasks_dir(&app)?;
+    let mut task = task_store::load_task(&tasks_path, &task_id)?.ok_or_else(|| "Task not found".to_string())?;
+    let mut task_isolation = task
+        .isolation
+        .clone()
+        .ok_or_else(|| "Task is not isolated".to_string())?;
+
+    let (report, base_head) = isolation::merge_back(&tasks_path, &task_id, &task_isolation)?;
+    if base_head.is_some() {
+        task_isolation.base_head = base_head;
+        task.isolation = Some(task_isolation);
+        task_store::upsert_task(&tasks_path, &task)?;
+    }
+
+    Ok(report)
+}
+
 #[tauri::command]
 #[allow(clippy::needless_pass_by_value)]
 fn auth_store_list(app: tauri::AppHandle) -> Result<auth_store::AuthStoreSummary, String> {
@@ -879,70 +1610,39 @@ fn auth_store_import_pi(app: tauri::AppHandle) -> Result<auth_store::AuthStoreSu
     auth_store::summary(&auth_path)
 }
 
-#[derive(Default)]
-struct TestStateSnapshotBridge {
-    pending: Mutex<HashMap<String, mpsc::Sender<serde_json::Value>>>,
-}
-
-#[derive(Default)]
-struct TestRuntimeDiagBridge {
-    pending: Mutex<HashMap<String, mpsc::Sender<serde_json::Value>>>,
-}
-
-#[cfg(debug_assertions)]
-static TEST_STATE_SNAPSHOT_COUNTER: AtomicU64 = AtomicU64::new(1);
-
-#[cfg(debug_assertions)]
-static TEST_RUNTIME_DIAG_COUNTER: AtomicU64 = AtomicU64::new(1);
-
+/// Completes a [`webview_bus`] request; `error` fails it instead.
 #[tauri::command]
 #[allow(clippy::needless_pass_by_value)]
-fn test_state_snapshot_reply(
+fn webview_reply(
     request_id: String,
-    snapshot: serde_json::Value,
-    bridge: tauri::State<TestStateSnapshotBridge>,
+    result: Option<serde_json::Value>,
+    error: Option<String>,
+    bus: tauri::State<webview_bus::WebviewBus>,
 ) -> Result<(), String> {
-    let sender = {
-        let mut pending = bridge
-            .pending
-            .lock()
-            .map_err(|_| "Failed to acquire snapshot bridge lock".to_string())?;
-
-        pending.remove(&request_id)
+    let reply = match error {
+        Some(error) => Err(error),
+        None => Ok(result.unwrap_or_default()),
     };
-
-    let Some(sender) = sender else {
-        return Err("Unknown snapshot request".to_string());
-    };
-
-    sender
-        .send(snapshot)
-        .map_err(|_| "Snapshot receiver unavailable".to_string())
+    bus.reply(&request_id, reply)
 }
 
-#[tauri::command]
-#[allow(clippy::needless_pass_by_value)]
-fn test_runtime_diag_reply(
-    request_id: String,
-    diag: serde_json::Value,
-    bridge: tauri::State<TestRuntimeDiagBridge>,
-) -> Result<(), String> {
-    let sender = {
-        let mut pending = bridge
-            .pending
-            .lock()
-            .map_err(|_| "Failed to acquire runtime diag bridge lock".to_string())?;
-
-        pending.remove(&request_id)
-    };
-
-    let Some(sender) = sender else {
-        return Err("Unknown runtime diag request".to_string());
-    };
-
-    sender
-        .send(diag)
-        .map_err(|_| "Runtime diag receiver unavailable".to_string())
+/// Asks the webview for something only it knows (see [`webview_bus::RequestKind`]).
+#[cfg(debug_assertions)]
+fn request_webview(
+    app: &tauri::AppHandle,
+    kind: webview_bus::RequestKind,
+    payload: serde_json::Value,
+    timeout: Option<Duration>,
+) -> Result<serde_json::Value, String> {
+    app.state::<webview_bus::WebviewBus>().request(
+        kind,
+        payload,
+        timeout.unwrap_or_else(|| kind.default_timeout()),
+        |request| {
+            app.emit(webview_bus::REQUEST_EVENT, request)
+                .map_err(|error| error.to_string())
+        },
+    )
 }
 
 #[cfg(debug_assertions)]
@@ -974,21 +1674,241 @@ fn sanitize_test_server_value(value: &serde_json::Value) -> serde_json::Value {
     }
 }
 
+fn automation_enabled() -> bool {
+    std::env::var(automation::AUTOMATION_ENV_VAR).is_ok_and(|value| matches!(value.trim(), "1" | "true"))
+}
+
+fn start_automation_server(app: &tauri::AppHandle) {
+    let data_dir = match app.path().app_data_dir() {
+        Ok(data_dir) => data_dir,
+        Err(error) => {
+            eprintln!("[rust:automation] {error}");
+            return;
+        }
+    };
+
+    let (handle, events) = (app.clone(), app.state::<automation::Events>().inner().clone());
+    match automation::start(&data_dir, events, move |method, params| {
+        automation_dispatch(&handle, method, params)
+    }) {
+        Ok(endpoint) => eprintln!(
+            "[rust:automation] listening on {}:{} ({})",
+            endpoint.host,
+            endpoint.port,
+            data_dir.join(automation::ENDPOINT_FILE).display()
+        ),
+        Err(error) => eprintln!("[rust:automation] failed to start: {error}"),
+    }
+}
+
+/// The automation API's methods (see [`automation`]), on top of the same functions the UI uses.
+#[allow(clippy::too_many_lines)]
+fn automation_dispatch(
+    app: &tauri::AppHandle,
+    method: &str,
+    params: serde_json::Value,
+) -> Result<serde_json::Value, automation::RpcError> {
+    use automation::{params as parse, result, RpcError};
+
+    #[derive(serde::Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct TaskParams {
+        task_id: String,
+    }
+
+    #[derive(serde::Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct CreateParams {
+        title: String,
+        working_folder: Option<String>,
+    }
+
+    #[derive(serde::Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct BindFolderParams {
+        task_id: String,
+        folder: String,
+    }
+
+    #[derive(serde::Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct PromptParams {
+        task_id: String,
+        message: String,
+    }
+
+    #[derive(serde::Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct ArtifactReadParams {
+        task_id: String,
+        source: String,
+        relative_path: String,
+    }
+
+    #[derive(serde::Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct VmStartParams {
+        task_id: Option<String>,
+        working_folder: Option<String>,
+    }
+
+    let app = app.clone();
+    match method {
+        "tasks.list" => result(task_store_list(app).map_err(RpcError::failed)?),
+        "tasks.get" => {
+            let TaskParams { task_id } = parse(params)?;
+            result(automation_task(&app, &task_id)?)
+        }
+        "tasks.create" => {
+            let CreateParams { title, working_folder } = parse(params)?;
+            let task = task_service(&app)
+                .and_then(|service| service.create(task_service::new_task(&title, working_folder)))
+                .map_err(RpcError::failed)?;
+            emit_task_event(&app, "task_updated", Some(&task.id), &task);
+            result(task)
+        }
+        "tasks.open" => {
+            let TaskParams { task_id } = parse(params)?;
+            automation_task(&app, &task_id)?;
+            result(activate_task(&app, &task_id, false).map_err(RpcError::failed)?)
+        }
+        "tasks.bindFolder" => {
+            let BindFolderParams { task_id, folder } = parse(params)?;
+            automation_task(&app, &task_id)?;
+            runtime_validate_working_folder(folder.clone(), None)
+                .map_err(|error| RpcError::new(automation::INVALID_PARAMS, error))?;
+            result(bind_task_folder(&app, &task_id, &folder).map_err(RpcError::failed)?)
+        }
+        "tasks.prompt" => {
+            let PromptParams { task_id, message } = parse(params)?;
+            automation_task(&app, &task_id)?;
+            if message.trim().is_empty() {
+                return Err(RpcError::new(automation::INVALID_PARAMS, "Prompt is empty"));
+            }
+            let prompt_id = prompt_task(&app, &task_id, &message).map_err(RpcError::failed)?;
+            result(serde_json::json!({ "taskId": task_id, "promptId": prompt_id }))
+        }
+        "tasks.stop" => {
+            let TaskParams { task_id } = parse(params)?;
+            automation_task(&app, &task_id)?;
+            result(task_stop(app, task_id).map_err(RpcError::failed)?)
+        }
+        "tasks.archive" => {
+            let TaskParams { task_id } = parse(params)?;
+            automation_task(&app, &task_id)?;
+            task_store_archive(app.clone(), task_id).map_err(RpcError::failed)?;
+            let _ = app.emit("task_list_changed", ());
+            result(())
+        }
+        "tasks.runs" => {
+            let TaskParams { task_id } = parse(params)?;
+            automation_task(&app, &task_id)?;
+            result(task_store_runs(app, task_id).map_err(RpcError::failed)?)
+        }
+        "artifacts.list" => {
+            let TaskParams { task_id } = parse(params)?;
+            automation_task(&app, &task_id)?;
+            result(task_artifact_list(app, task_id).map_err(RpcError::failed)?)
+        }
+        "artifacts.read" => {
+            let ArtifactReadParams {
+                task_id,
+                source,
+                relative_path,
+            } = parse(params)?;
+            automation_task(&app, &task_id)?;
+            let invalid = |error: String| RpcError::new(automation::INVALID_PARAMS, error);
+            let tasks_path = tasks_dir(&app).map_err(RpcError::failed)?;
+            let root = match ArtifactSource::parse(source.trim()).map_err(invalid)? {
+                ArtifactSource::Outputs => task_store::task_outputs_dir(&tasks_path, &task_id),
+                ArtifactSource::Uploads => task_store::task_uploads_dir(&tasks_path, &task_id),
+            };
+            let relative = normalize_preview_relative_path(&relative_path).map_err(invalid)?;
+            if std::fs::symlink_metadata(root.join(relative)).is_err() {
+                return Err(RpcError::new(automation::NOT_FOUND, "File not found"));
+            }
+            result(task_artifact_read(app, task_id, source, relative_path).map_err(RpcError::failed)?)
+        }
+        "auth.summary" => result(auth_store_list(app).map_err(RpcError::failed)?),
+        "vm.status" => result(vm::status(&app.state::<vm::VmState>())),
+        "vm.start" => {
+            let VmStartParams {
+                task_id,
+                working_folder,
+            } = parse(params)?;
+            match task_id {
+                Some(task_id) => {
+                    automation_task(&app, &task_id)?;
+                    activate_task(&app, &task_id, false).map_err(RpcError::failed)?;
+                }
+                None => {
+                    start_task_vm(&app, &app.state::<vm::VmState>(), working_folder.as_deref(), None)
+                        .map_err(RpcError::failed)?;
+                }
+            }
+            result(vm::status(&app.state::<vm::VmState>()))
+        }
+        "vm.stop" => {
+            stop_task_vm(&app);
+            result(vm::status(&app.state::<vm::VmState>()))
+        }
+        _ => Err(RpcError::new(
+            automation::METHOD_NOT_FOUND,
+            format!("Unknown method {method}"),
+        )),
+    }
+}
+
+/// Loads the task an automation method acts on, so a malformed id is reported as invalid
+/// params and an unknown one as not found before the method runs.
+fn automation_task(app: &tauri::AppHandle, task_id: &str) -> Result<task_store::TaskMetadata, automation::RpcError> {
+    use automation::RpcError;
+
+    if !is_valid_task_id(task_id) {
+        return Err(RpcError::new(automation::INVALID_PARAMS, "Invalid task id"));
+    }
+
+    let tasks_path = tasks_dir(app).map_err(RpcError::failed)?;
+    task_store::load_task(&tasks_path, task_id)
+        .map_err(RpcError::failed)?
+        .ok_or_else(|| RpcError::new(automation::NOT_FOUND, format!("Task {task_id} not found")))
+}
+
+/// The `taskId` of a test server command, or the task open in the runtime.
+#[cfg(debug_assertions)]
+fn test_server_task_id(app: &tauri::AppHandle, json: &serde_json::Value) -> Result<String, String> {
+    match json.get("taskId").and_then(|v| v.as_str()) {
+        Some(task_id) => Ok(task_id.to_string()),
+        None => mounted_task_id(app).ok_or_else(|| "No task is open; pass taskId".to_string()),
+    }
+}
+
+#[cfg(debug_assertions)]
+fn write_test_server_result<T: serde::Serialize>(stream: &mut std::net::TcpStream, result: Result<T, String>) {
+    let line = match result {
+        Ok(value) => serde_json::to_string(&value).unwrap_or_else(|_| "{}".to_string()),
+        Err(error) => format!("ERR: {error}"),
+    };
+    let _ = stream.write_all(format!("{line}\n").as_bytes());
+}
+
 /// Test server for automated testing (dev mode only)
 /// Listens on port `19385` and accepts commands:
-/// - `{"cmd":"prompt","message":"..."}` - triggers UI sendPrompt flow
+/// - `{"cmd":"prompt","message":"...","taskId":"..."}` - sends a prompt to the open task (`taskId` defaults to it); returns `{"taskId","promptId"}` once taskd accepts it
 /// - `{"cmd":"inject_message","message":"..."}` - injects a user message into UI conversation (no provider call)
-/// - `{"cmd":"set_folder","folder":"/path"}` - sets working folder (one-time bind for active task)
-/// - `{"cmd":"set_task","taskId":"..."}` - selects active task
+/// - `{"cmd":"set_folder","folder":"/path","taskId":"..."}` - binds the working folder (one-time; `taskId` defaults to the open task); returns the task JSON
+/// - `{"cmd":"set_task","taskId":"..."}` - opens the task in the runtime (restarting the VM if needed); returns the task JSON
+/// - `{"cmd":"stop_task","taskId":"..."}` - stops the task in taskd
+/// - `{"cmd":"subscribe","topics":["vm","rpc","tasks"],"taskId":"...","events":["agent_end"]}` - answers `OK`, then streams matching host events as JSONL until the client disconnects (all filters optional)
 /// - `{"cmd":"send_login"}` - triggers UI /login flow
 /// - `{"cmd":"auth_list"}` - returns auth store summary JSON
 /// - `{"cmd":"auth_set_api_key","provider":"anthropic","key":"..."}` - writes API key to auth store
 /// - `{"cmd":"auth_delete","provider":"anthropic"}` - deletes provider from auth store
 /// - `{"cmd":"auth_import_pi"}` - imports ~/.pi/agent/auth.json into auth store
-/// - `{"cmd":"create_task","title":"...","workingFolder":"/path"}` - creates task
+/// - `{"cmd":"create_task","title":"...","workingFolder":"/path"}` - creates a task and opens it; returns the task JSON
 /// - `{"cmd":"task_list"}` - returns task metadata JSON array
-/// - `{"cmd":"delete_task","taskId":"..."}` - deletes one task
-/// - `{"cmd":"delete_all_tasks"}` - wipes all tasks
+/// - `{"cmd":"delete_task","taskId":"..."}` - archives one task
+/// - `{"cmd":"delete_all_tasks"}` - archives all tasks
 /// - `{"cmd":"dump_state"}` - logs UI state
 /// - `{"cmd":"state_snapshot"}` - returns structured UI/runtime snapshot JSON
 /// - `{"cmd":"runtime_diag"}` - returns runtime taskd diagnostics JSON (forwarded from UI/runtime service)
@@ -1000,6 +1920,9 @@ fn sanitize_test_server_value(value: &serde_json::Value) -> serde_json::Value {
 /// - `{"cmd":"write_working_file","relativePath":"...","content":"..."}` - writes a file via runtime `system_bash` after folder bind settles
 /// - `{"cmd":"open_working_folder","taskId":"..."}` - opens a task working folder via the same `open_path_in_finder` path as the UI action
 /// - `{"cmd":"rpc",...}` - sends raw RPC to VM
+///
+/// `state_snapshot` and `runtime_diag` are answered by the webview (see [`webview_bus`]) and
+/// accept an optional `"timeoutMs"`.
 #[cfg(debug_assertions)]
 #[allow(clippy::too_many_lines)]
 fn start_test_server(app_handle: tauri::AppHandle) {
@@ -1037,13 +1960,25 @@ fn start_test_server(app_handle: tauri::AppHandle) {
 
                     let cmd = json.get("cmd").and_then(|v| v.as_str()).unwrap_or("rpc");
 
+                    // Queries only the webview can answer go over the webview bus.
+                    if let Some(kind) = webview_bus::RequestKind::parse(cmd) {
+                        let timeout = json
+                            .get("timeoutMs")
+                            .and_then(serde_json::Value::as_u64)
+                            .map(Duration::from_millis);
+                        let payload = json.get("payload").cloned().unwrap_or_default();
+                        write_test_server_result(&mut stream, request_webview(&app, kind, payload, timeout));
+                        continue;
+                    }
+
                     match cmd {
                         "prompt" => {
-                            // Emit event to frontend to trigger sendPrompt
                             let message = json.get("message").and_then(|v| v.as_str()).unwrap_or("");
-                            eprintln!("[test-server] emitting test_prompt: {message}");
-                            let _ = app.emit("test_prompt", message);
-                            let _ = stream.write_all(b"OK\n");
+                            let result = test_server_task_id(&app, &json).and_then(|task_id| {
+                                let prompt_id = prompt_task(&app, &task_id, message)?;
+                                Ok(serde_json::json!({ "taskId": task_id, "promptId": prompt_id }))
+                            });
+                            write_test_server_result(&mut stream, result);
                         }
                         "inject_message" => {
                             // Emit event to frontend to inject a user message without provider traffic
@@ -1053,18 +1988,51 @@ fn start_test_server(app_handle: tauri::AppHandle) {
                             let _ = stream.write_all(b"OK\n");
                         }
                         "set_folder" => {
-                            // Emit event to frontend to change working folder
-                            let folder = json.get("folder").and_then(|v| v.as_str());
-                            eprintln!("[test-server] emitting test_set_folder: {folder:?}");
-                            let _ = app.emit("test_set_folder", folder);
-                            let _ = stream.write_all(b"OK\n");
+                            let folder = json.get("folder").and_then(|v| v.as_str()).unwrap_or("");
+                            let result = test_server_task_id(&app, &json)
+                                .and_then(|task_id| bind_task_folder(&app, &task_id, folder));
+                            write_test_server_result(&mut stream, result);
                         }
                         "set_task" => {
-                            // Emit event to frontend to change active task
-                            let task_id = json.get("taskId").and_then(|v| v.as_str());
-                            eprintln!("[test-server] emitting test_set_task: {task_id:?}");
-                            let _ = app.emit("test_set_task", task_id);
+                            let task_id = json.get("taskId").and_then(|v| v.as_str()).unwrap_or("");
+                            write_test_server_result(&mut stream, activate_task(&app, task_id, false));
+                        }
+                        "stop_task" => {
+                            let result =
+                                test_server_task_id(&app, &json).and_then(|task_id| task_service(&app)?.stop(&task_id));
+                            match result {
+                                Ok(()) => {
+                                    let _ = stream.write_all(b"OK\n");
+                                }
+                                Err(error) => {
+                                    let _ = stream.write_all(format!("ERR: {error}\n").as_bytes());
+                                }
+                            }
+                        }
+                        "subscribe" => {
+                            // Streams matching events as JSONL until the client disconnects.
+                            let filter = serde_json::from_value::<automation::Filter>(json.clone())
+                                .map_err(|error| error.to_string())
+                                .and_then(|filter| filter.validate().map(|()| filter));
+                            let filter = match filter {
+                                Ok(filter) => filter,
+                                Err(error) => {
+                                    let _ = stream.write_all(format!("ERR: {error}\n").as_bytes());
+                                    continue;
+                                }
+                            };
+
+                            let events = app.state::<automation::Events>();
+                            let (key, lines) = events.subscribe_keyed();
+                            // Nothing cancels this one; it ends when the client disconnects.
+                            let cancelled = std::sync::atomic::AtomicBool::new(false);
                             let _ = stream.write_all(b"OK\n");
+                            automation::forward(&lines, &filter, &cancelled, |event| {
+                                let line = serde_json::to_string(&event).unwrap_or_default();
+                                stream.write_all(format!("{line}\n").as_bytes()).is_ok()
+                            });
+                            events.unsubscribe(key);
+                            break;
                         }
                         "send_login" => {
                             // Emit event to frontend to trigger /login flow in UI
@@ -1118,16 +2086,14 @@ fn start_test_server(app_handle: tauri::AppHandle) {
                             }
                         },
                         "create_task" => {
-                            // Emit event to frontend to create a new task
-                            let title = json.get("title").and_then(|v| v.as_str());
+                            let title = json.get("title").and_then(|v| v.as_str()).unwrap_or("");
                             let folder = json.get("workingFolder").and_then(|v| v.as_str());
-                            eprintln!("[test-server] emitting test_create_task: {title:?}");
-                            let payload = serde_json::json!({
-                                "title": title,
-                                "workingFolder": folder,
-                            });
-                            let _ = app.emit("test_create_task", payload);
-                            let _ = stream.write_all(b"OK\n");
+                            let result = task_service(&app)
+                                .and_then(|service| {
+                                    service.create(task_service::new_task(title, folder.map(String::from)))
+                                })
+                                .and_then(|task| activate_task(&app, &task.id, false));
+                            write_test_server_result(&mut stream, result);
                         }
                         "task_list" => match task_store_list(app.clone()) {
                             Ok(tasks) => {
@@ -1144,7 +2110,7 @@ fn start_test_server(app_handle: tauri::AppHandle) {
                             if task_id.trim().is_empty() {
                                 let _ = stream.write_all(b"ERR: taskId is required\n");
                             } else {
-                                match task_store_delete(app.clone(), task_id.to_string()) {
+                                match task_store_archive(app.clone(), task_id.to_string()) {
                                     Ok(()) => {
                                         let _ = stream.write_all(b"OK\n");
                                     }
@@ -1154,92 +2120,21 @@ fn start_test_server(app_handle: tauri::AppHandle) {
                                 }
                             }
                         }
-                        "delete_all_tasks" => {
-                            // Emit event to frontend to wipe all tasks
-                            eprintln!("[test-server] emitting test_delete_all_tasks");
-                            let _ = app.emit("test_delete_all_tasks", ());
-                            let _ = stream.write_all(b"OK\n");
-                        }
+                        "delete_all_tasks" => match task_store_archive_all(app.clone()) {
+                            Ok(()) => {
+                                let _ = app.emit("task_list_changed", ());
+                                let _ = stream.write_all(b"OK\n");
+                            }
+                            Err(error) => {
+                                let _ = stream.write_all(format!("ERR: {error}\n").as_bytes());
+                            }
+                        },
                         "dump_state" => {
                             // Emit event to frontend to log current UI state
                             eprintln!("[test-server] emitting test_dump_state");
                             let _ = app.emit("test_dump_state", ());
                             let _ = stream.write_all(b"OK\n");
                         }
-                        "state_snapshot" => {
-                            let request_id = format!(
-                                "state_snapshot_{}",
-                                TEST_STATE_SNAPSHOT_COUNTER.fetch_add(1, Ordering::Relaxed)
-                            );
-                            let bridge: tauri::State<TestStateSnapshotBridge> = app.state();
-                            let (sender, receiver) = mpsc::channel();
-
-                            if let Ok(mut pending) = bridge.pending.lock() {
-                                pending.insert(request_id.clone(), sender);
-                            } else {
-                                let _ = stream.write_all(b"ERR: Failed to acquire snapshot bridge lock\n");
-                                continue;
-                            }
-
-                            let payload = serde_json::json!({
-                                "requestId": request_id,
-                            });
-
-                            if app.emit("test_state_snapshot_request", payload).is_err() {
-                                if let Ok(mut pending) = bridge.pending.lock() {
-                                    pending.remove(&request_id);
-                                }
-                                let _ = stream.write_all(b"ERR: Failed to request state snapshot\n");
-                                continue;
-                            }
-
-                            if let Ok(snapshot) = receiver.recv_timeout(Duration::from_secs(5)) {
-                                let payload = serde_json::to_string(&snapshot).unwrap_or_else(|_| "{}".to_string());
-                                let _ = stream.write_all(format!("{payload}\n").as_bytes());
-                            } else {
-                                if let Ok(mut pending) = bridge.pending.lock() {
-                                    pending.remove(&request_id);
-                                }
-                                let _ = stream.write_all(b"ERR: Timed out waiting for state snapshot\n");
-                            }
-                        }
-                        "runtime_diag" => {
-                            let request_id = format!(
-                                "runtime_diag_{}",
-                                TEST_RUNTIME_DIAG_COUNTER.fetch_add(1, Ordering::Relaxed)
-                            );
-                            let bridge: tauri::State<TestRuntimeDiagBridge> = app.state();
-                            let (sender, receiver) = mpsc::channel();
-
-                            if let Ok(mut pending) = bridge.pending.lock() {
-                                pending.insert(request_id.clone(), sender);
-                            } else {
-                                let _ = stream.write_all(b"ERR: Failed to acquire runtime diag bridge lock\n");
-                                continue;
-                            }
-
-                            let payload = serde_json::json!({
-                                "requestId": request_id,
-                            });
-
-                            if app.emit("test_runtime_diag_request", payload).is_err() {
-                                if let Ok(mut pending) = bridge.pending.lock() {
-                                    pending.remove(&request_id);
-                                }
-                                let _ = stream.write_all(b"ERR: Failed to request runtime diag\n");
-                                continue;
-                            }
-
-                            if let Ok(diag) = receiver.recv_timeout(Duration::from_secs(8)) {
-                                let payload = serde_json::to_string(&diag).unwrap_or_else(|_| "{}".to_string());
-                                let _ = stream.write_all(format!("{payload}\n").as_bytes());
-                            } else {
-                                if let Ok(mut pending) = bridge.pending.lock() {
-                                    pending.remove(&request_id);
-                                }
-                                let _ = stream.write_all(b"ERR: Timed out waiting for runtime diag\n");
-                            }
-                        }
                         "preview_list" => {
                             let task_id = json.get("taskId").and_then(|v| v.as_str()).unwrap_or("");
                             match task_preview_list(app.clone(), task_id.to_string()) {
@@ -1376,6 +2271,7 @@ fn start_test_server(app_handle: tauri::AppHandle) {
                         _ => {
                             // Direct RPC send (bypass UI)
                             let state: tauri::State<vm::VmState> = app.state();
+                            vm::claim(&state, vm::VmOwner::Interactive);
                             match vm::send(&state, &line) {
                                 Ok(()) => {
                                     let _ = stream.write_all(b"OK\n");
@@ -1395,12 +2291,43 @@ fn start_test_server(app_handle: tauri::AppHandle) {
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
+        .on_page_load(|webview, payload| {
+            // A reloading page never answers what the old one was asked.
+            if matches!(payload.event(), tauri::webview::PageLoadEvent::Started) {
+                webview
+                    .state::<webview_bus::WebviewBus>()
+                    .cancel_all("Webview reloaded");
+            }
+        })
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
         .manage(vm::VmState::default())
-        .manage(TestStateSnapshotBridge::default())
-        .manage(TestRuntimeDiagBridge::default())
+        .manage(change_journal::ChangeWatcherState::default())
+        .manage(task_status::RpcStatusTracker::default())
+        .manage(rpc_bus::RpcBus::default())
+        .manage(automation::Events::default())
+        .manage(webview_bus::WebviewBus::default())
         .setup(|app| {
+            let handle = app.handle().clone();
+            std::thread::spawn(move || {
+                let purged = tasks_dir(&handle)
+                    .and_then(|tasks_dir| task_store::purge_expired_archived_tasks(&tasks_dir, task_store::now_ms()));
+                match purged {
+                    Ok(purged) if !purged.is_empty() => {
+                        eprintln!("[rust:task-store] purged {} expired archived task(s)", purged.len());
+                    }
+                    Ok(_) => {}
+                    Err(error) => eprintln!("[rust:task-store] archive purge failed: {error}"),
+                }
+            });
+
+            let handle = app.handle().clone();
+            std::thread::spawn(move || run_scheduler(&handle));
+
+            if automation_enabled() {
+                start_automation_server(app.handle());
+            }
+
             #[cfg(debug_assertions)]
             start_test_server(app.handle().clone());
             Ok(())
@@ -1412,11 +2339,37 @@ pub fn run() {
             runtime_validate_working_folder,
             open_path_in_finder,
             task_store_list,
+            task_store_query,
+            task_store_reindex,
+            task_store_quarantined,
+            task_search,
             task_store_upsert,
-            task_store_delete,
-            task_store_delete_all,
+            task_store_archive,
+            task_store_archive_all,
+            task_store_list_archived,
+            task_store_restore,
+            task_store_purge,
+            task_store_fork,
+            task_store_runs,
+            template_store_list,
+            template_store_upsert,
+            template_store_delete,
+            task_create_from_template,
+            task_create,
+            task_activate,
+            task_bind_folder,
+            task_prompt,
+            task_stop,
+            task_export,
+            task_import,
             task_store_save_conversation,
             task_store_load_conversation,
+            task_changes_list,
+            task_restore_file,
+            task_restore_all,
+            task_git_status,
+            task_git_diff,
+            task_merge_back,
             task_preview_list,
             task_preview_read,
             task_artifact_list,
@@ -1429,8 +2382,7 @@ pub fn run() {
             vm_start,
             vm_stop,
             rpc_send,
-            test_state_snapshot_reply,
-            test_runtime_diag_reply,
+            webview_reply,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,444 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const MOUNT_BACKEND_ENV_VAR: &str = "PIWORK_MOUNT_BACKEND";
+const VIRTIOFS_CAPABILITY: &str = "virtiofs";
+const VIRTIOFSD_FALLBACK_PATHS: [&str; 2] = ["/usr/libexec/virtiofsd", "/usr/lib/qemu/virtiofsd"];
+const VIRTIOFSD_SOCKET_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum MountBackend {
+    #[serde(rename = "9p")]
+    NineP,
+    #[serde(rename = "virtiofs")]
+    Virtiofs,
+}
+
+impl MountBackend {
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw {
+            "9p" => Some(Self::NineP),
+            "virtiofs" => Some(Self::Virtiofs),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::NineP => "9p",
+            Self::Virtiofs => "virtiofs",
+        }
+    }
+}
+
+/// A host directory to expose to the guest under `mount_tag`.
+pub struct MountRequest<'a> {
+    pub id: &'a str,
+    pub mount_tag: &'a str,
+    pub path: &'a Path,
+    pub label: &'a str,
+    pub read_only: bool,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MountInfo {
+    pub tag: String,
+    pub host_path: String,
+    pub backend: MountBackend,
+    pub read_only: bool,
+}
+
+/// Mounts attached to a QEMU command, plus any helper daemons that must live as long as the VM.
+#[derive(Default)]
+pub struct AttachedMounts {
+    pub mounts: Vec<MountInfo>,
+    daemons: Vec<Child>,
+}
+
+impl AttachedMounts {
+    pub fn shutdown(&mut self) {
+        for daemon in &mut self.daemons {
+            daemon.kill().ok();
+            let _ = daemon.wait();
+        }
+        self.daemons.clear();
+    }
+}
+
+/// The backend forced or requested through `PIWORK_MOUNT_BACKEND`, if any.
+pub fn requested_backend_from_env() -> Option<MountBackend> {
+    let raw = std::env::var(MOUNT_BACKEND_ENV_VAR)
+        .ok()
+        .map(|value| value.trim().to_ascii_lowercase())
+        .unwrap_or_default();
+
+    match raw.as_str() {
+        "" => None,
+        value => {
+            let parsed = MountBackend::parse(value);
+            if parsed.is_none() {
+                eprintln!("[rust:vm:mount] Invalid {MOUNT_BACKEND_ENV_VAR}='{raw}', ignoring");
+            }
+            parsed
+        }
+    }
+}
+
+/// Picks the mount backend for this VM.
+///
+/// virtiofs is used only when the runtime pack advertises it, the host is Linux and `virtiofsd`
+/// is available; otherwise 9p. `requested` (from `PIWORK_MOUNT_BACKEND`) can force `9p`, or
+/// request `virtiofs` (still subject to availability).
+pub fn resolve_backend(
+    requested: Option<MountBackend>,
+    capabilities: &[String],
+    virtiofsd: Option<&Path>,
+) -> MountBackend {
+    if requested == Some(MountBackend::NineP) {
+        return MountBackend::NineP;
+    }
+
+    let supports_virtiofs = capabilities.iter().any(|value| value == VIRTIOFS_CAPABILITY);
+    let virtiofs_available = supports_virtiofs && cfg!(target_os = "linux") && virtiofsd.is_some();
+
+    if virtiofs_available {
+        return MountBackend::Virtiofs;
+    }
+
+    if requested == Some(MountBackend::Virtiofs) {
+        eprintln!(
+            "[rust:vm:mount] virtiofs requested but unavailable (capability={supports_virtiofs}, virtiofsd={}); falling back to 9p",
+            virtiofsd.is_some()
+        );
+    }
+
+    MountBackend::NineP
+}
+
+pub fn find_virtiofsd(runtime_dir: &Path, configured: Option<&str>) -> Option<PathBuf> {
+    if let Some(configured) = configured {
+        let candidate = runtime_dir.join(configured);
+        return candidate.is_file().then_some(candidate);
+    }
+
+    crate::vm::find_in_path("virtiofsd").or_else(|| {
+        VIRTIOFSD_FALLBACK_PATHS
+            .iter()
+            .map(PathBuf::from)
+            .find(|candidate| candidate.is_file())
+    })
+}
+
+/// Attaches every request to `command` using `backend`.
+///
+/// virtiofs shares spawn one `virtiofsd` per share (sockets under `socket_dir`) and back guest
+/// RAM with shared memory, so `memory_mb` must match the VM's `-m` size.
+pub fn attach_mounts(
+    command: &mut Command,
+    backend: MountBackend,
+    requests: &[MountRequest],
+    virtiofsd: Option<&Path>,
+    socket_dir: &Path,
+    memory_mb: u32,
+) -> Result<AttachedMounts, String> {
+    let mut attached = AttachedMounts::default();
+
+    for request in requests {
+        if !request.path.is_dir() {
+            eprintln!("[rust:vm] {} not found: {}", request.label, request.path.display());
+            continue;
+        }
+
+        eprintln!(
+            "[rust:vm] mounting {} via {}{}: {}",
+            request.label,
+            backend.as_str(),
+            if request.read_only { " (read-only)" } else { "" },
+            request.path.display()
+        );
+
+        match backend {
+            MountBackend::NineP => attach_9p(command, request),
+            MountBackend::Virtiofs => {
+                let Some(virtiofsd) = virtiofsd else {
+                    attached.shutdown();
+                    return Err("virtiofsd not found".to_string());
+                };
+
+                match spawn_virtiofsd(command, request, virtiofsd, socket_dir) {
+                    Ok(daemon) => attached.daemons.push(daemon),
+                    Err(error) => {
+                        attached.shutdown();
+                        return Err(error);
+                    }
+                }
+            }
+        }
+
+        attached.mounts.push(MountInfo {
+            tag: request.mount_tag.to_string(),
+            host_path: request.path.to_string_lossy().to_string(),
+            backend,
+            read_only: request.read_only,
+        });
+    }
+
+    if backend == MountBackend::Virtiofs && !attached.mounts.is_empty() {
+        // vhost-user-fs needs guest RAM the daemons can map.
+        command
+            .arg("-object")
+            .arg(format!("memory-backend-memfd,id=mem,size={memory_mb}M,share=on"))
+            .arg("-numa")
+            .arg("node,memdev=mem");
+    }
+
+    Ok(attached)
+}
+
+fn attach_9p(command: &mut Command, request: &MountRequest) {
+    let id = request.id;
+    let mut fsdev = format!("local,id={id},path={},security_model=none", request.path.display());
+    if request.read_only {
+        fsdev.push_str(",readonly=on");
+    }
+
+    command
+        .arg("-fsdev")
+        .arg(fsdev)
+        .arg("-device")
+        .arg(format!("virtio-9p-pci,fsdev={id},mount_tag={}", request.mount_tag));
+}
+
+fn spawn_virtiofsd(
+    command: &mut Command,
+    request: &MountRequest,
+    virtiofsd: &Path,
+    socket_dir: &Path,
+) -> Result<Child, String> {
+    let id = request.id;
+    let socket_path = socket_dir.join(format!("virtiofsd-{id}.sock"));
+    let _ = std::fs::remove_file(&socket_path);
+
+    let mut daemon_command = Command::new(virtiofsd);
+    daemon_command
+        .arg(format!("--socket-path={}", socket_path.display()))
+        .arg(format!("--shared-dir={}", request.path.display()))
+        .arg("--sandbox=none")
+        .arg("--cache=auto");
+
+    if request.read_only {
+        daemon_command.arg("--readonly");
+    }
+
+    let mut daemon = daemon_command
+        .stdout(Stdio::null())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .map_err(|error| format!("Failed to spawn virtiofsd for {}: {error}", request.label))?;
+
+    if !wait_for_socket(&socket_path, VIRTIOFSD_SOCKET_TIMEOUT) {
+        daemon.kill().ok();
+        let _ = daemon.wait();
+        return Err(format!(
+            "virtiofsd socket for {} not ready: {}",
+            request.label,
+            socket_path.display()
+        ));
+    }
+
+    command
+        .arg("-chardev")
+        .arg(format!("socket,id={id}-vfs,path={}", socket_path.display()))
+        .arg("-device")
+        .arg(format!("vhost-user-fs-pci,chardev={id}-vfs,tag={}", request.mount_tag));
+
+    Ok(daemon)
+}
+
+fn wait_for_socket(path: &Path, timeout: Duration) -> bool {
+    let start = Instant::now();
+
+    while start.elapsed() < timeout {
+        if path.exists() {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    fn temp_dir() -> PathBuf {
+        let suffix = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let counter = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+        std::env::temp_dir().join(format!("piwork-mount-{suffix}-{counter}"))
+    }
+
+    fn command_args(command: &Command) -> Vec<String> {
+        command
+            .get_args()
+            .map(|value| value.to_string_lossy().to_string())
+            .collect()
+    }
+
+    fn request(path: &Path, read_only: bool) -> MountRequest<'_> {
+        MountRequest {
+            id: "taskuploads",
+            mount_tag: "taskuploads",
+            path,
+            label: "task uploads dir",
+            read_only,
+        }
+    }
+
+    #[test]
+    fn nine_p_read_only_share_sets_readonly_flag() {
+        let root = temp_dir();
+        std::fs::create_dir_all(&root).expect("create dir");
+        let mut command = Command::new("qemu-system-aarch64");
+
+        let attached = attach_mounts(
+            &mut command,
+            MountBackend::NineP,
+            &[request(&root, true)],
+            None,
+            &root,
+            2048,
+        )
+        .expect("attach");
+        let args = command_args(&command);
+
+        assert_eq!(attached.mounts.len(), 1);
+        assert!(attached.mounts[0].read_only);
+        assert_eq!(attached.mounts[0].backend, MountBackend::NineP);
+        assert!(args.iter().any(|arg| arg.ends_with(",readonly=on")));
+        assert!(!args.iter().any(|arg| arg.starts_with("memory-backend")));
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn missing_mount_dir_is_skipped() {
+        let root = temp_dir();
+        let mut command = Command::new("qemu-system-aarch64");
+
+        let attached = attach_mounts(
+            &mut command,
+            MountBackend::NineP,
+            &[request(&root, true)],
+            None,
+            &root,
+            2048,
+        )
+        .expect("attach");
+
+        assert!(attached.mounts.is_empty());
+        assert!(command_args(&command).is_empty());
+    }
+
+    #[test]
+    fn virtiofs_without_daemon_fails() {
+        let root = temp_dir();
+        std::fs::create_dir_all(&root).expect("create dir");
+        let mut command = Command::new("qemu-system-aarch64");
+
+        let result = attach_mounts(
+            &mut command,
+            MountBackend::Virtiofs,
+            &[request(&root, false)],
+            None,
+            &root,
+            2048,
+        );
+
+        assert!(result.is_err());
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn virtiofs_shares_use_vhost_user_and_shared_memory() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = temp_dir();
+        std::fs::create_dir_all(&root).expect("create dir");
+        // Stands in for virtiofsd: creates the socket path it is given, then waits to be killed.
+        let daemon = root.join("virtiofsd");
+        std::fs::write(
+            &daemon,
+            "#!/bin/sh\nfor arg; do case \"$arg\" in --socket-path=*) : > \"${arg#--socket-path=}\";; esac; done\nexec sleep 30\n",
+        )
+        .expect("write daemon");
+        std::fs::set_permissions(&daemon, std::fs::Permissions::from_mode(0o755)).expect("chmod");
+        let mut command = Command::new("qemu-system-aarch64");
+
+        let mut attached = attach_mounts(
+            &mut command,
+            MountBackend::Virtiofs,
+            &[request(&root, true)],
+            Some(&daemon),
+            &root,
+            2048,
+        )
+        .expect("attach");
+        let args = command_args(&command);
+        attached.shutdown();
+
+        assert_eq!(attached.mounts.len(), 1);
+        assert_eq!(attached.mounts[0].backend, MountBackend::Virtiofs);
+        assert!(args.contains(&"vhost-user-fs-pci,chardev=taskuploads-vfs,tag=taskuploads".to_string()));
+        assert!(args.contains(&"memory-backend-memfd,id=mem,size=2048M,share=on".to_string()));
+        assert!(args.contains(&"node,memdev=mem".to_string()));
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn backend_requires_virtiofs_capability() {
+        let virtiofsd = PathBuf::from("/usr/libexec/virtiofsd");
+        let capabilities = ["virtiofs".to_string()];
+
+        assert_eq!(resolve_backend(None, &[], Some(&virtiofsd)), MountBackend::NineP);
+        assert_eq!(resolve_backend(None, &capabilities, None), MountBackend::NineP);
+        assert_eq!(
+            resolve_backend(Some(MountBackend::Virtiofs), &[], Some(&virtiofsd)),
+            MountBackend::NineP
+        );
+        assert_eq!(
+            resolve_backend(Some(MountBackend::NineP), &capabilities, Some(&virtiofsd)),
+            MountBackend::NineP
+        );
+        #[cfg(target_os = "linux")]
+        {
+            assert_eq!(
+                resolve_backend(None, &capabilities, Some(&virtiofsd)),
+                MountBackend::Virtiofs
+            );
+            assert_eq!(
+                resolve_backend(Some(MountBackend::Virtiofs), &capabilities, Some(&virtiofsd)),
+                MountBackend::Virtiofs
+            );
+        }
+    }
+
+    #[test]
+    fn backend_names_round_trip() {
+        for backend in [MountBackend::NineP, MountBackend::Virtiofs] {
+            assert_eq!(MountBackend::parse(backend.as_str()), Some(backend));
+        }
+        assert_eq!(MountBackend::parse("nfs"), None);
+    }
+}
@@ -0,0 +1,238 @@
+//! Crash-safe file writes and advisory locks for host-side state.
+//!
+//! The locks only coordinate the host: commands, the test server, the scheduler and the CLI.
+//! The guest never takes them, and `flock` does not cross the 9p/virtiofs shares anyway, so a
+//! guest writing into a mounted dir (pi refreshing `auth.json`, say) is not serialized against
+//! the host. Atomic renames still keep each side from reading a partial file.
+
+use sha2::{Digest, Sha256};
+use std::fmt::Write as _;
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Exclusive advisory lock on a `<file>.lock` sidecar, released on drop. The sidecar is locked
+/// rather than the file itself because atomic writes replace the file's inode.
+pub struct FileLock {
+    _file: File,
+}
+
+/// Blocks until no other host process or thread holds the lock for `path`. Hold it across a
+/// read-modify-write so concurrent host writers can't drop each other's changes.
+pub fn lock(path: &Path) -> Result<FileLock, String> {
+    let file = open_lock(path)?;
+    file.lock()
+        .map_err(|error| format!("Failed to lock {}: {error}", path.display()))?;
+
+    Ok(FileLock { _file: file })
+}
+
+/// [`lock`] without waiting: `None` while another holder has it.
+pub fn try_lock(path: &Path) -> Result<Option<FileLock>, String> {
+    let file = open_lock(path)?;
+    match file.try_lock() {
+        Ok(()) => Ok(Some(FileLock { _file: file })),
+        Err(TryLockError::WouldBlock) => Ok(None),
+        Err(TryLockError::Error(error)) => Err(format!("Failed to lock {}: {error}", path.display())),
+    }
+}
+
+fn open_lock(path: &Path) -> Result<File, String> {
+    let lock_path = sidecar(path, "lock");
+    if let Some(parent) = lock_path.parent() {
+        std::fs::create_dir_all(parent).map_err(|error| error.to_string())?;
+    }
+
+    OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .map_err(|error| format!("Failed to open lock {}: {error}", lock_path.display()))
+}
+
+/// Replaces `path` with `contents` so readers see either the old or the new file, never a
+/// partial one: write a temp file in the same directory, fsync it, then rename over `path`.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), String> {
+    write_with(path, None, |file| file.write_all(contents))
+}
+
+/// [`write_atomic`] for secrets: the temp file is created owner-only, so the contents are never
+/// readable by others, not even briefly.
+pub fn write_private(path: &Path, contents: &[u8]) -> Result<(), String> {
+    write_with(path, Some(0o600), |file| file.write_all(contents))
+}
+
+/// [`write_atomic`] for contents produced by a writer, e.g. an archive streamed to disk.
+pub fn write_atomic_with(path: &Path, write: impl FnOnce(&mut File) -> std::io::Result<()>) -> Result<(), String> {
+    write_with(path, None, write)
+}
+
+fn write_with(
+    path: &Path,
+    mode: Option<u32>,
+    write: impl FnOnce(&mut File) -> std::io::Result<()>,
+) -> Result<(), String> {
+    let counter = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let temp_path = sidecar(path, &format!("tmp-{}-{counter}", std::process::id()));
+
+    let result = (|| {
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        if let Some(mode) = mode {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(mode);
+        }
+        #[cfg(not(unix))]
+        let _ = mode;
+
+        let mut file = options.open(&temp_path)?;
+        write(&mut file)?;
+        file.sync_all()?;
+        std::fs::rename(&temp_path, path)
+    })();
+
+    if let Err(error) = result {
+        std::fs::remove_file(&temp_path).ok();
+        return Err(format!("Failed to write {}: {error}", path.display()));
+    }
+
+    if let Some(parent) = path.parent() {
+        sync_dir(parent);
+    }
+    Ok(())
+}
+
+/// Persists a rename on filesystems that need the directory flushed too. Best effort.
+pub fn sync_dir(dir: &Path) {
+    #[cfg(unix)]
+    if let Ok(handle) = File::open(dir) {
+        handle.sync_all().ok();
+    }
+    #[cfg(not(unix))]
+    let _ = dir;
+}
+
+/// Whether `relative` under `root` is, or passes through, a symlink. Anything a task could have
+/// planted is checked with this before the host writes or deletes through it, so a link can't
+/// point a restore or merge outside the folder it was meant for.
+pub fn crosses_symlink(root: &Path, relative: &Path) -> bool {
+    let mut path = root.to_path_buf();
+    relative.components().any(|component| {
+        path.push(component);
+        std::fs::symlink_metadata(&path).is_ok_and(|metadata| metadata.file_type().is_symlink())
+    })
+}
+
+/// Lowercase hex of `bytes`.
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .fold(String::with_capacity(bytes.len() * 2), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        })
+}
+
+/// Lowercase hex SHA-256 of `bytes`, as used for content hashes across the task store.
+pub fn hex_sha256(bytes: &[u8]) -> String {
+    to_hex(&Sha256::digest(bytes))
+}
+
+/// `dir/.name.suffix`, hidden so folder listings and the guest don't pick it up.
+fn sidecar(path: &Path, suffix: &str) -> PathBuf {
+    let name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    path.with_file_name(format!(".{name}.{suffix}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn temp_dir() -> PathBuf {
+        let suffix = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let counter = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let dir = std::env::temp_dir().join(format!("piwork-persist-{suffix}-{counter}"));
+        std::fs::create_dir_all(&dir).expect("create dir");
+        dir
+    }
+
+    #[test]
+    fn interrupted_write_keeps_previous_contents() {
+        let dir = temp_dir();
+        let path = dir.join("task.json");
+        write_atomic(&path, b"{\"v\":1}").expect("write");
+
+        let error = write_with(&path, None, |file| {
+            file.write_all(b"{\"v\":")?;
+            Err(std::io::Error::other("simulated crash"))
+        })
+        .expect_err("interrupted");
+
+        assert!(error.contains("simulated crash"));
+        assert_eq!(std::fs::read_to_string(&path).expect("read"), "{\"v\":1}");
+        assert_eq!(std::fs::read_dir(&dir).expect("list").count(), 1);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn locked_read_modify_write_loses_no_updates() {
+        let dir = temp_dir();
+        let path = dir.join("counter");
+        write_atomic(&path, b"0").expect("write");
+
+        let writers: Vec<_> = (0..8)
+            .map(|_| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..25 {
+                        let _lock = lock(&path).expect("lock");
+                        let value: u32 = std::fs::read_to_string(&path).expect("read").parse().expect("number");
+                        write_atomic(&path, (value + 1).to_string().as_bytes()).expect("write");
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().expect("writer");
+        }
+
+        assert_eq!(std::fs::read_to_string(&path).expect("read"), "200");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn try_lock_fails_while_the_lock_is_held() {
+        let dir = temp_dir();
+        let path = dir.join("vm");
+
+        let held = try_lock(&path).expect("try").expect("free");
+        assert!(try_lock(&path).expect("try").is_none());
+        drop(held);
+        assert!(try_lock(&path).expect("try").is_some());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn private_writes_are_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = temp_dir();
+        let path = dir.join("auth.json");
+        write_private(&path, b"{}").expect("write");
+
+        let mode = std::fs::metadata(&path).expect("metadata").permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,79 @@
+use serde_json::Value;
+
+/// The pi session file inside a task's session folder (`/sessions/<taskId>/session.json`).
+pub const SESSION_FILE: &str = "session.json";
+
+/// Cuts a pi session (JSON lines: a `session` header, then `message` and bookkeeping entries)
+/// just before its `user_turns + 1`th user message, so pi resumes a fork after `user_turns`
+/// complete turns. The header's `id` is replaced with `session_id`; unparsable lines are kept
+/// as-is up to the cut.
+pub fn truncate_at_user_turn(raw: &str, user_turns: usize, session_id: &str) -> String {
+    let mut kept = Vec::new();
+    let mut seen_user_turns = 0;
+
+    for line in raw.lines() {
+        let Ok(mut entry) = serde_json::from_str::<Value>(line) else {
+            kept.push(line.to_string());
+            continue;
+        };
+
+        match entry.get("type").and_then(Value::as_str) {
+            Some("session") => {
+                if let Some(object) = entry.as_object_mut() {
+                    object.insert("id".to_string(), Value::String(session_id.to_string()));
+                }
+                kept.push(entry.to_string());
+                continue;
+            }
+            Some("message") if is_user_message(&entry) => {
+                if seen_user_turns == user_turns {
+                    break;
+                }
+                seen_user_turns += 1;
+            }
+            _ => {}
+        }
+
+        kept.push(line.to_string());
+    }
+
+    let mut truncated = kept.join("\n");
+    truncated.push('\n');
+    truncated
+}
+
+fn is_user_message(entry: &Value) -> bool {
+    entry
+        .get("message")
+        .and_then(|message| message.get("role"))
+        .and_then(Value::as_str)
+        == Some("user")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sessions_are_cut_before_the_next_user_turn() {
+        let raw = [
+            r#"{"type":"session","id":"old","cwd":"/work"}"#,
+            r#"{"type":"message","message":{"role":"user","content":"one"}}"#,
+            r#"{"type":"message","message":{"role":"assistant","content":"1"}}"#,
+            r#"{"type":"model_change","model":"m"}"#,
+            r#"{"type":"message","message":{"role":"user","content":"two"}}"#,
+            r#"{"type":"message","message":{"role":"assistant","content":"2"}}"#,
+        ]
+        .join("\n");
+
+        let forked = truncate_at_user_turn(&raw, 1, "new");
+
+        let lines: Vec<&str> = forked.lines().collect();
+        assert_eq!(lines.len(), 4);
+        let header: Value = serde_json::from_str(lines[0]).expect("header");
+        assert_eq!(header["id"], "new");
+        assert_eq!(header["cwd"], "/work");
+        assert_eq!(lines[3], r#"{"type":"model_change","model":"m"}"#);
+        assert_eq!(truncate_at_user_turn(&raw, 0, "new").lines().count(), 1);
+    }
+}
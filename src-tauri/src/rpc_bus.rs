@@ -0,0 +1,160 @@
+use serde_json::Value;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+static REQUEST_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Fans guest RPC lines out to host-side code that drives the runtime itself (e.g. the
+/// scheduler), alongside the copy the webview receives.
+#[derive(Default)]
+pub struct RpcBus {
+    subscribers: Mutex<Vec<(u64, Sender<String>)>>,
+    next_key: AtomicU64,
+}
+
+impl RpcBus {
+    /// Lines published from now on; dropping the receiver unsubscribes.
+    pub fn subscribe(&self) -> Receiver<String> {
+        self.subscribe_keyed().1
+    }
+
+    /// [`subscribe`](Self::subscribe), plus a key for [`unsubscribe`](Self::unsubscribe).
+    pub fn subscribe_keyed(&self) -> (u64, Receiver<String>) {
+        let key = self.next_key.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers.lock().unwrap().push((key, sender));
+        (key, receiver)
+    }
+
+    /// Drops the subscription's sender, so a thread blocked on its receiver wakes up and sees it
+    /// disconnected (after any lines already queued).
+    pub fn unsubscribe(&self, key: u64) {
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain(|(subscriber, _)| *subscriber != key);
+    }
+
+    pub fn publish(&self, line: &str) {
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain(|(_, subscriber)| subscriber.send(line.to_string()).is_ok());
+    }
+}
+
+/// Sends a taskd request through `send` and waits on `lines` for its response. Returns the
+/// `result` of a success response, or the error's code and message.
+pub fn request(
+    lines: &Receiver<String>,
+    send: impl FnOnce(&str) -> Result<(), String>,
+    kind: &str,
+    payload: &Value,
+    timeout: Duration,
+) -> Result<Value, String> {
+    let counter = REQUEST_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let id = format!("host-{}-{counter}", std::process::id());
+    let message = serde_json::json!({ "id": id, "type": kind, "payload": payload });
+    send(&message.to_string())?;
+
+    let response = wait_for(lines, timeout, |line| {
+        line.get("id").and_then(Value::as_str) == Some(&id)
+    })
+    .map_err(|error| format!("{kind}: {error}"))?;
+
+    if response.get("ok").and_then(Value::as_bool) == Some(true) {
+        return Ok(response.get("result").cloned().unwrap_or(Value::Null));
+    }
+
+    let error = response.get("error");
+    let field = |name: &str| {
+        error
+            .and_then(|error| error.get(name))
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string()
+    };
+    Err(format!("{kind} failed: {} {}", field("code"), field("message")))
+}
+
+/// Waits for one of `events` for `task_id` and returns its name and payload.
+pub fn wait_for_event(
+    lines: &Receiver<String>,
+    task_id: &str,
+    events: &[&str],
+    timeout: Duration,
+) -> Result<(String, Value), String> {
+    let line = wait_for(lines, timeout, |line| {
+        line.get("type").and_then(Value::as_str) == Some("event")
+            && line.get("taskId").and_then(Value::as_str) == Some(task_id)
+            && line
+                .get("event")
+                .and_then(Value::as_str)
+                .is_some_and(|event| events.contains(&event))
+    })
+    .map_err(|error| format!("waiting for {}: {error}", events.join("/")))?;
+
+    let event = line
+        .get("event")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string();
+    Ok((event, line.get("payload").cloned().unwrap_or(Value::Null)))
+}
+
+fn wait_for(lines: &Receiver<String>, timeout: Duration, matches: impl Fn(&Value) -> bool) -> Result<Value, String> {
+    let deadline = Instant::now() + timeout;
+
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let line = match lines.recv_timeout(remaining) {
+            Ok(line) => line,
+            Err(RecvTimeoutError::Timeout) => return Err(format!("timed out after {}s", timeout.as_secs())),
+            Err(RecvTimeoutError::Disconnected) => return Err("RPC connection closed".to_string()),
+        };
+
+        if let Ok(value) = serde_json::from_str::<Value>(&line) {
+            if matches(&value) {
+                return Ok(value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_resolve_with_their_own_response() {
+        let bus = RpcBus::default();
+        let lines = bus.subscribe();
+
+        let result = request(
+            &lines,
+            |message| {
+                let id = serde_json::from_str::<Value>(message).unwrap()["id"].clone();
+                bus.publish(r#"{"id":"other","ok":true,"result":{"n":1}}"#);
+                bus.publish(r#"{"type":"event","event":"agent_output","taskId":"t1","payload":{}}"#);
+                bus.publish(&serde_json::json!({ "id": id, "ok": true, "result": { "n": 2 } }).to_string());
+                bus.publish(r#"{"type":"event","event":"agent_end","taskId":"t1","payload":{"done":true}}"#);
+                Ok(())
+            },
+            "prompt",
+            &serde_json::json!({ "message": "hi" }),
+            Duration::from_secs(1),
+        )
+        .expect("response");
+
+        assert_eq!(result, serde_json::json!({ "n": 2 }));
+        assert_eq!(
+            wait_for_event(&lines, "t1", &["agent_end", "task_error"], Duration::from_secs(1)).expect("event"),
+            ("agent_end".to_string(), serde_json::json!({ "done": true }))
+        );
+        assert!(wait_for_event(&lines, "t1", &["agent_end"], Duration::from_millis(10))
+            .expect_err("timeout")
+            .contains("timed out"));
+    }
+}
@@ -0,0 +1,526 @@
+//! Recording and replaying taskd RPC sessions.
+//!
+//! With `PIWORK_RPC_RECORD=<file>`, every line the host sends (`vm::send`) and receives
+//! (`read_rpc_lines`) is appended to a JSONL fixture with its time since the VM started. With
+//! `PIWORK_RPC_REPLAY=<file>`, the VM doesn't boot: a fake taskd serves the fixture on the RPC
+//! port instead, so the UI and host logic run offline against a known session. Both are
+//! development hooks: release builds ignore the variables.
+//!
+//! Replay is driven by the host. Each recorded request is replayed with the guest lines that
+//! followed it (its response matched by id, events by position), when the host sends a request
+//! of the same type. Request ids and prompt ids are rewritten to the live ones. All guest lines
+//! go out through one queue in their recorded order, whatever the replay speed.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+pub const RECORD_ENV_VAR: &str = "PIWORK_RPC_RECORD";
+pub const REPLAY_ENV_VAR: &str = "PIWORK_RPC_REPLAY";
+/// Multiplies replay speed; `0` sends every recorded line without waiting.
+pub const REPLAY_SPEED_ENV_VAR: &str = "PIWORK_RPC_REPLAY_SPEED";
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    /// Sent by the host to taskd.
+    Host,
+    /// Sent by taskd to the host.
+    Guest,
+}
+
+/// One line of a fixture file.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FixtureLine {
+    pub at_ms: u64,
+    pub from: Direction,
+    /// The RPC line, parsed when it is JSON.
+    pub line: Value,
+}
+
+/// Appends RPC lines to a fixture file.
+pub struct Recorder {
+    started: Instant,
+    file: Mutex<File>,
+}
+
+impl Recorder {
+    /// Starts a new recording at `path`, replacing any previous one. The file is owner-only:
+    /// it holds full prompts and responses.
+    pub fn create(path: &Path) -> Result<Self, String> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|error| error.to_string())?;
+        }
+        // Removed rather than truncated, so an old file's wider mode isn't kept.
+        match std::fs::remove_file(path) {
+            Err(error) if error.kind() != std::io::ErrorKind::NotFound => {
+                return Err(format!("Failed to replace RPC recording {}: {error}", path.display()));
+            }
+            _ => {}
+        }
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let file = options
+            .open(path)
+            .map_err(|error| format!("Failed to open RPC recording {}: {error}", path.display()))?;
+
+        Ok(Self {
+            started: Instant::now(),
+            file: Mutex::new(file),
+        })
+    }
+
+    pub fn record(&self, from: Direction, line: &str) {
+        let entry = FixtureLine {
+            at_ms: u64::try_from(self.started.elapsed().as_millis()).unwrap_or(u64::MAX),
+            from,
+            line: serde_json::from_str(line).unwrap_or_else(|_| Value::String(line.to_string())),
+        };
+        let Ok(json) = serde_json::to_string(&entry) else {
+            return;
+        };
+
+        if let Err(error) = writeln!(self.file.lock().unwrap(), "{json}") {
+            eprintln!("[rust:rpc-replay] failed to record RPC line: {error}");
+        }
+    }
+}
+
+/// The recorder requested by `PIWORK_RPC_RECORD`, if any.
+pub fn recorder_from_env() -> Result<Option<Arc<Recorder>>, String> {
+    match env_path(RECORD_ENV_VAR) {
+        Some(path) => Ok(Some(Arc::new(Recorder::create(Path::new(&path))?))),
+        None => Ok(None),
+    }
+}
+
+/// The fixture requested by `PIWORK_RPC_REPLAY`, if any.
+pub fn fixture_from_env() -> Result<Option<Fixture>, String> {
+    env_path(REPLAY_ENV_VAR)
+        .map(|path| Fixture::load(Path::new(&path)))
+        .transpose()
+}
+
+pub fn speed_from_env() -> f64 {
+    std::env::var(REPLAY_SPEED_ENV_VAR)
+        .ok()
+        .and_then(|value| value.trim().parse::<f64>().ok())
+        .filter(|speed| speed.is_finite() && *speed >= 0.0)
+        .unwrap_or(1.0)
+}
+
+/// Always `None` in release builds, so a shipped app can't be made to log every prompt to disk
+/// or to talk to a fake guest through its environment.
+fn env_path(name: &str) -> Option<String> {
+    if !cfg!(debug_assertions) {
+        return None;
+    }
+    std::env::var(name).ok().filter(|value| !value.trim().is_empty())
+}
+
+#[derive(Clone, Debug)]
+struct Reply {
+    /// Position in the fixture; replies are sent in this order.
+    seq: usize,
+    delay_ms: u64,
+    line: Value,
+}
+
+/// A recorded request and the guest lines replayed for it.
+#[derive(Clone, Debug)]
+struct Exchange {
+    request: Value,
+    replies: Vec<Reply>,
+}
+
+impl Exchange {
+    fn is_response(&self, reply: &Reply) -> bool {
+        let id = self.request.get("id");
+        id.is_some() && reply.line.get("id") == id
+    }
+}
+
+/// A recorded session, indexed for replay.
+#[derive(Debug, Default)]
+pub struct Fixture {
+    /// Guest lines sent before the host's first request, e.g. startup events.
+    opening: Vec<Reply>,
+    /// Recorded requests by type, in the order they were sent.
+    exchanges: HashMap<String, VecDeque<Exchange>>,
+}
+
+impl Fixture {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|error| format!("Failed to read RPC fixture {}: {error}", path.display()))?;
+        let lines = content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .enumerate()
+            .map(|(index, line)| {
+                serde_json::from_str(line).map_err(|error| format!("Invalid RPC fixture line {}: {error}", index + 1))
+            })
+            .collect::<Result<Vec<FixtureLine>, String>>()?;
+
+        Ok(Self::from_lines(&lines))
+    }
+
+    pub fn from_lines(lines: &[FixtureLine]) -> Self {
+        let opening_at = lines.first().map_or(0, |line| line.at_ms);
+        let mut opening = Vec::new();
+        // (type, sent at, exchange) in recorded order.
+        let mut recorded: Vec<(String, u64, Exchange)> = Vec::new();
+        let mut by_id: HashMap<String, usize> = HashMap::new();
+
+        for (seq, entry) in lines.iter().enumerate() {
+            match entry.from {
+                Direction::Host => {
+                    if let Some(id) = entry.line.get("id").and_then(Value::as_str) {
+                        by_id.insert(id.to_string(), recorded.len());
+                    }
+                    let kind = string_field(&entry.line, "type").unwrap_or_default();
+                    recorded.push((
+                        kind,
+                        entry.at_ms,
+                        Exchange {
+                            request: entry.line.clone(),
+                            replies: Vec::new(),
+                        },
+                    ));
+                }
+                Direction::Guest => {
+                    let index = entry
+                        .line
+                        .get("id")
+                        .and_then(Value::as_str)
+                        .and_then(|id| by_id.get(id).copied())
+                        .or_else(|| recorded.len().checked_sub(1));
+                    let Some(index) = index else {
+                        opening.push(Reply {
+                            seq,
+                            delay_ms: entry.at_ms.saturating_sub(opening_at),
+                            line: entry.line.clone(),
+                        });
+                        continue;
+                    };
+
+                    let (_, sent_at, exchange) = &mut recorded[index];
+                    exchange.replies.push(Reply {
+                        seq,
+                        delay_ms: entry.at_ms.saturating_sub(*sent_at),
+                        line: entry.line.clone(),
+                    });
+                }
+            }
+        }
+
+        let mut exchanges: HashMap<String, VecDeque<Exchange>> = HashMap::new();
+        for (kind, _, exchange) in recorded {
+            exchanges.entry(kind).or_default().push_back(exchange);
+        }
+
+        Self { opening, exchanges }
+    }
+}
+
+/// Plays taskd for one host connection on `listener`, until the host disconnects.
+///
+/// A request type used more often than recorded gets the last recorded response again (polls),
+/// without its events. A type never recorded gets a `REPLAY_MISS` error response.
+pub fn serve(listener: &TcpListener, mut fixture: Fixture, speed: f64) -> Result<(), String> {
+    let (stream, _) = listener.accept().map_err(|error| error.to_string())?;
+    let outgoing = spawn_sender(stream.try_clone().map_err(|error| error.to_string())?);
+
+    queue_replies(&outgoing, std::mem::take(&mut fixture.opening), &HashMap::new(), speed);
+
+    let mut last: HashMap<String, Exchange> = HashMap::new();
+    for line in BufReader::new(stream).lines().map_while(Result::ok) {
+        let Ok(request) = serde_json::from_str::<Value>(&line) else {
+            continue;
+        };
+        let kind = string_field(&request, "type").unwrap_or_default();
+
+        let next = fixture.exchanges.get_mut(&kind).and_then(VecDeque::pop_front);
+        let (exchange, with_events) = match (next, last.get(&kind)) {
+            (Some(exchange), _) => (exchange, true),
+            (None, Some(exchange)) => (exchange.clone(), false),
+            (None, None) => {
+                eprintln!("[rust:rpc-replay] no recorded {kind} request");
+                let miss = serde_json::json!({
+                    "id": request.get("id"),
+                    "ok": false,
+                    "error": { "code": "REPLAY_MISS", "message": format!("No recorded {kind} request") },
+                });
+                // Not part of the recording, so it goes ahead of anything still pending.
+                let _ = outgoing.send(Outgoing {
+                    seq: 0,
+                    due: Instant::now(),
+                    line: miss,
+                });
+                continue;
+            }
+        };
+
+        let mut substitutions = HashMap::new();
+        for path in ["/id", "/payload/promptId"] {
+            let recorded = exchange.request.pointer(path).and_then(Value::as_str);
+            let live = request.pointer(path).and_then(Value::as_str);
+            if let (Some(recorded), Some(live)) = (recorded, live) {
+                substitutions.insert(recorded.to_string(), live.to_string());
+            }
+        }
+
+        let replies = exchange
+            .replies
+            .iter()
+            .filter(|reply| with_events || exchange.is_response(reply))
+            .cloned()
+            .collect();
+        queue_replies(&outgoing, replies, &substitutions, speed);
+        last.insert(kind, exchange);
+    }
+
+    Ok(())
+}
+
+/// A guest line waiting in the send queue.
+struct Outgoing {
+    seq: usize,
+    due: Instant,
+    line: Value,
+}
+
+/// Schedules `replies` for sending, each after its recorded delay from now.
+fn queue_replies(
+    outgoing: &mpsc::Sender<Outgoing>,
+    replies: Vec<Reply>,
+    substitutions: &HashMap<String, String>,
+    speed: f64,
+) {
+    let queued_at = Instant::now();
+    for mut reply in replies {
+        let delay = if speed > 0.0 {
+            Duration::from_millis(reply.delay_ms).div_f64(speed)
+        } else {
+            Duration::ZERO
+        };
+        substitute(&mut reply.line, substitutions);
+        let _ = outgoing.send(Outgoing {
+            seq: reply.seq,
+            due: queued_at + delay,
+            line: reply.line,
+        });
+    }
+}
+
+/// Starts the one thread that writes guest lines to `stream`. Lines go out in recorded order:
+/// the earliest-recorded pending line is sent once it is due, and nothing recorded after it
+/// overtakes it, so interleaved exchanges replay the way they were recorded at any speed.
+fn spawn_sender(mut stream: TcpStream) -> mpsc::Sender<Outgoing> {
+    let (sender, receiver) = mpsc::channel::<Outgoing>();
+
+    std::thread::spawn(move || {
+        // Keyed by recorded position, then arrival, since polls replay the same lines again.
+        let mut pending: BTreeMap<(usize, u64), (Instant, Value)> = BTreeMap::new();
+        let mut arrivals = 0u64;
+
+        loop {
+            let next_due = pending.first_key_value().map(|(_, (due, _))| *due);
+            let next = match next_due {
+                None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+                Some(due) if due <= Instant::now() => {
+                    let (_, (_, line)) = pending.pop_first().expect("pending line");
+                    if writeln!(stream, "{line}").is_err() {
+                        return;
+                    }
+                    continue;
+                }
+                Some(due) => receiver.recv_timeout(due.saturating_duration_since(Instant::now())),
+            };
+
+            match next {
+                Ok(outgoing) => {
+                    pending.insert((outgoing.seq, arrivals), (outgoing.due, outgoing.line));
+                    arrivals += 1;
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+    });
+
+    sender
+}
+
+/// Replaces every string in `value` that is a key of `substitutions`.
+fn substitute(value: &mut Value, substitutions: &HashMap<String, String>) {
+    match value {
+        Value::String(text) => {
+            if let Some(replacement) = substitutions.get(text.as_str()) {
+                text.clone_from(replacement);
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|item| substitute(item, substitutions)),
+        Value::Object(fields) => fields.values_mut().for_each(|item| substitute(item, substitutions)),
+        _ => {}
+    }
+}
+
+fn string_field(value: &Value, name: &str) -> Option<String> {
+    value.get(name).and_then(Value::as_str).map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc_bus::{self, RpcBus};
+    use serde_json::json;
+
+    fn line(at_ms: u64, from: Direction, line: Value) -> FixtureLine {
+        FixtureLine { at_ms, from, line }
+    }
+
+    fn sample_session() -> Vec<FixtureLine> {
+        vec![
+            line(
+                900,
+                Direction::Guest,
+                json!({ "type": "event", "event": "runtime_ready" }),
+            ),
+            line(
+                1_000,
+                Direction::Host,
+                json!({ "id": "rec-1", "type": "create_or_open_task", "payload": { "taskId": "t1" } }),
+            ),
+            line(
+                1_020,
+                Direction::Guest,
+                json!({ "id": "rec-1", "ok": true, "result": { "taskId": "t1" } }),
+            ),
+            line(
+                1_100,
+                Direction::Host,
+                json!({ "id": "rec-2", "type": "prompt", "payload": { "message": "hi", "promptId": "rec-p" } }),
+            ),
+            line(
+                1_110,
+                Direction::Guest,
+                json!({ "id": "rec-2", "ok": true, "result": null }),
+            ),
+            line(
+                1_500,
+                Direction::Guest,
+                json!({ "type": "event", "event": "agent_end", "taskId": "t1", "payload": { "promptId": "rec-p" } }),
+            ),
+        ]
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn recordings_are_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+        use std::time::{SystemTime, UNIX_EPOCH};
+
+        let suffix = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let dir = std::env::temp_dir().join(format!("piwork-rpc-replay-{suffix}"));
+        let path = dir.join("session.jsonl");
+        std::fs::create_dir_all(&dir).expect("dir");
+        std::fs::write(&path, "old\n").expect("old recording");
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).expect("chmod");
+
+        let recorder = Recorder::create(&path).expect("create");
+        recorder.record(Direction::Host, r#"{"type":"prompt"}"#);
+
+        let mode = std::fs::metadata(&path).expect("metadata").permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        let contents = std::fs::read_to_string(&path).expect("read");
+        assert!(!contents.contains("old") && contents.contains("prompt"));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn fixtures_group_guest_lines_under_their_request() {
+        let fixture = Fixture::from_lines(&sample_session());
+
+        assert_eq!(fixture.opening.len(), 1);
+        assert_eq!(fixture.opening[0].delay_ms, 0);
+        let prompt = &fixture.exchanges["prompt"][0];
+        assert_eq!(prompt.replies.len(), 2);
+        assert!(prompt.is_response(&prompt.replies[0]));
+        assert_eq!(prompt.replies[1].delay_ms, 400);
+        assert_eq!(fixture.exchanges["create_or_open_task"][0].replies.len(), 1);
+    }
+
+    #[test]
+    fn replay_answers_live_requests_with_recorded_lines() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let fixture = Fixture::from_lines(&sample_session());
+        std::thread::spawn(move || serve(&listener, fixture, 0.0));
+
+        let stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        let writer = Mutex::new(stream.try_clone().unwrap());
+        let bus = Arc::new(RpcBus::default());
+        let lines = bus.subscribe();
+        let reader_bus = bus.clone();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stream).lines().map_while(Result::ok) {
+                reader_bus.publish(&line);
+            }
+        });
+        let send = |message: &str| writeln!(writer.lock().unwrap(), "{message}").map_err(|error| error.to_string());
+        let timeout = Duration::from_secs(5);
+
+        let opened = rpc_bus::request(&lines, send, "create_or_open_task", &json!({ "taskId": "t1" }), timeout);
+        assert_eq!(opened, Ok(json!({ "taskId": "t1" })));
+        rpc_bus::request(
+            &lines,
+            send,
+            "prompt",
+            &json!({ "message": "hi", "promptId": "live-p" }),
+            timeout,
+        )
+        .expect("prompt");
+        let (_, payload) = rpc_bus::wait_for_event(&lines, "t1", &["agent_end"], timeout).expect("agent_end");
+        assert_eq!(payload["promptId"], "live-p");
+
+        let missed = rpc_bus::request(&lines, send, "stop_task", &json!({ "taskId": "t1" }), timeout);
+        assert!(missed.is_err_and(|error| error.contains("REPLAY_MISS")));
+    }
+
+    #[test]
+    fn queued_lines_keep_their_recorded_order() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        let outgoing = spawn_sender(server);
+
+        let now = Instant::now();
+        for (seq, due, line) in [(1, now + Duration::from_millis(100), "first"), (2, now, "second")] {
+            outgoing
+                .send(Outgoing {
+                    seq,
+                    due,
+                    line: json!(line),
+                })
+                .unwrap();
+        }
+
+        let received: Vec<String> = BufReader::new(client).lines().take(2).map_while(Result::ok).collect();
+        assert_eq!(received, vec![r#""first""#.to_string(), r#""second""#.to_string()]);
+    }
+}
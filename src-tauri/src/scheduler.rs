@@ -0,0 +1,360 @@
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+
+use crate::task_index;
+use crate::task_store;
+
+/// Run history of a scheduled task, one JSON record per line, in the task folder.
+pub const RUNS_FILE: &str = "runs.jsonl";
+/// How often the scheduler checks for due tasks.
+pub const TICK_SECS: u64 = 30;
+/// Leap days only come around every four years, so that is as far as a search has to look.
+const MAX_SEARCH_DAYS: i64 = 366 * 4 + 1;
+
+/// A recurring prompt, stored on the task.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskSchedule {
+    /// Five-field cron expression (`minute hour day-of-month month day-of-week`) or one of
+    /// `@hourly`, `@daily`, `@weekly`, `@monthly`.
+    pub cron: String,
+    /// Sent to the task on every run.
+    pub prompt: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Minutes to add to UTC to get the clock `cron` is written against (e.g. 60 for UTC+1).
+    #[serde(default)]
+    pub utc_offset_minutes: i32,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RunOutcome {
+    Running,
+    Completed,
+    Failed,
+    /// The run was due but couldn't start, e.g. the VM was busy with another task.
+    Skipped,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RunRecord {
+    pub run_id: String,
+    /// The cron time the run was due at, ISO 8601 UTC.
+    pub scheduled_for: String,
+    pub started_at: String,
+    pub finished_at: Option<String>,
+    pub outcome: RunOutcome,
+    pub error: Option<String>,
+}
+
+impl RunRecord {
+    pub fn started(scheduled_for_ms: i64) -> Self {
+        Self {
+            run_id: scheduled_for_ms.to_string(),
+            scheduled_for: task_index::format_timestamp_ms(scheduled_for_ms),
+            started_at: task_index::format_timestamp_ms(task_store::now_ms()),
+            finished_at: None,
+            outcome: RunOutcome::Running,
+            error: None,
+        }
+    }
+
+    pub fn finish(&self, result: Result<(), RunError>) -> Self {
+        let (outcome, error) = match result {
+            Ok(()) => (RunOutcome::Completed, None),
+            Err(RunError::Skipped(reason)) => (RunOutcome::Skipped, Some(reason)),
+            Err(RunError::Failed(error)) => (RunOutcome::Failed, Some(error)),
+        };
+
+        Self {
+            finished_at: Some(task_index::format_timestamp_ms(task_store::now_ms())),
+            outcome,
+            error,
+            ..self.clone()
+        }
+    }
+}
+
+pub enum RunError {
+    Skipped(String),
+    Failed(String),
+}
+
+/// A parsed cron expression. Each field is a bit set of the values it matches.
+#[derive(Debug, PartialEq, Eq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    /// Like cron: when both day fields are restricted, a day matching either one is enough.
+    day_fields_restricted: (bool, bool),
+}
+
+impl CronSchedule {
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        let expression = match raw.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            other => other,
+        };
+
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minute, hour, day_of_month, month, day_of_week] = fields[..] else {
+            return Err(format!("Invalid cron expression {raw:?}: expected 5 fields"));
+        };
+
+        let mut weekdays = parse_field(day_of_week, 0, 7).map_err(|error| format!("{raw:?}: {error}"))?;
+        // Both 0 and 7 mean Sunday.
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+
+        Ok(Self {
+            minutes: parse_field(minute, 0, 59).map_err(|error| format!("{raw:?}: {error}"))?,
+            hours: parse_field(hour, 0, 23).map_err(|error| format!("{raw:?}: {error}"))?,
+            days_of_month: parse_field(day_of_month, 1, 31).map_err(|error| format!("{raw:?}: {error}"))?,
+            months: parse_field(month, 1, 12).map_err(|error| format!("{raw:?}: {error}"))?,
+            days_of_week: weekdays,
+            day_fields_restricted: (!day_of_month.starts_with('*'), !day_of_week.starts_with('*')),
+        })
+    }
+
+    /// The first matching minute strictly after `after_ms`, in Unix milliseconds. Fields are
+    /// matched against UTC shifted by `utc_offset_minutes`.
+    pub fn next_after(&self, after_ms: i64, utc_offset_minutes: i32) -> Option<i64> {
+        let offset_ms = i64::from(utc_offset_minutes) * 60_000;
+        let first_minute = (after_ms + offset_ms).div_euclid(60_000) + 1;
+        let first_day = first_minute.div_euclid(1_440);
+
+        for day in first_day..first_day + MAX_SEARCH_DAYS {
+            if !self.matches_day(day) {
+                continue;
+            }
+
+            for hour in (0..24).filter(|hour| self.hours & (1 << hour) != 0) {
+                for minute in (0..60).filter(|minute| self.minutes & (1 << minute) != 0) {
+                    let candidate = day * 1_440 + hour * 60 + minute;
+                    if candidate >= first_minute {
+                        return Some(candidate * 60_000 - offset_ms);
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    fn matches_day(&self, day: i64) -> bool {
+        let (_, month, day_of_month) = task_index::civil_from_days(day);
+        // 1970-01-01 was a Thursday.
+        let day_of_week = (day + 4).rem_euclid(7);
+
+        let month_matches = self.months & (1 << month) != 0;
+        let day_of_month_matches = self.days_of_month & (1 << day_of_month) != 0;
+        let day_of_week_matches = self.days_of_week & (1 << day_of_week) != 0;
+
+        month_matches
+            && match self.day_fields_restricted {
+                (true, true) => day_of_month_matches || day_of_week_matches,
+                _ => day_of_month_matches && day_of_week_matches,
+            }
+    }
+}
+
+/// Parses one cron field (`*`, `5`, `1-5`, `*/15`, `0-30/10`, or a comma list of those).
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64, String> {
+    let mut bits = 0u64;
+
+    for item in field.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => (
+                range,
+                step.parse::<u32>()
+                    .ok()
+                    .filter(|step| *step > 0)
+                    .ok_or_else(|| format!("invalid step in {item:?}"))?,
+            ),
+            None => (item, 1),
+        };
+
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (parse_value(start, min, max)?, parse_value(end, min, max)?)
+        } else {
+            let value = parse_value(range, min, max)?;
+            (value, if step > 1 { max } else { value })
+        };
+
+        if start > end {
+            return Err(format!("invalid range {range:?}"));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+
+    Ok(bits)
+}
+
+fn parse_value(raw: &str, min: u32, max: u32) -> Result<u32, String> {
+    raw.parse::<u32>()
+        .ok()
+        .filter(|value| (min..=max).contains(value))
+        .ok_or_else(|| format!("{raw:?} is not between {min} and {max}"))
+}
+
+/// When `schedule` is next due after `last_tick_ms`, if that is no later than `now_ms`.
+/// Runs missed while the app was closed are not caught up.
+pub fn due_at(schedule: &TaskSchedule, last_tick_ms: i64, now_ms: i64) -> Option<i64> {
+    if !schedule.enabled {
+        return None;
+    }
+
+    CronSchedule::parse(&schedule.cron)
+        .ok()?
+        .next_after(last_tick_ms, schedule.utc_offset_minutes)
+        .filter(|due| *due <= now_ms)
+}
+
+pub fn append_run(tasks_dir: &Path, task_id: &str, record: &RunRecord) -> Result<(), String> {
+    let mut line = serde_json::to_string(record).map_err(|error| error.to_string())?;
+    line.push('\n');
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(task_store::task_dir(tasks_dir, task_id).join(RUNS_FILE))
+        .map_err(|error| error.to_string())?;
+    file.write_all(line.as_bytes()).map_err(|error| error.to_string())?;
+    file.sync_data().map_err(|error| error.to_string())
+}
+
+/// A task's runs, oldest first. A run is recorded when it starts and again when it ends; the
+/// latest record wins. Unreadable lines (e.g. a torn final write) are skipped.
+pub fn list_runs(tasks_dir: &Path, task_id: &str) -> Result<Vec<RunRecord>, String> {
+    let path = task_store::task_dir(tasks_dir, task_id).join(RUNS_FILE);
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(error.to_string()),
+    };
+
+    let mut runs: Vec<RunRecord> = Vec::new();
+    for record in content
+        .lines()
+        .filter_map(|line| serde_json::from_str::<RunRecord>(line).ok())
+    {
+        match runs.iter_mut().find(|run| run.run_id == record.run_id) {
+            Some(run) => *run = record,
+            None => runs.push(record),
+        }
+    }
+
+    Ok(runs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    fn temp_dir() -> PathBuf {
+        let suffix = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let counter = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+        std::env::temp_dir().join(format!("piwork-scheduler-{suffix}-{counter}"))
+    }
+
+    // 2026-02-04T10:30:00Z, a Wednesday.
+    const WEDNESDAY: i64 = 1_770_201_000_000;
+
+    fn next(cron: &str, after_ms: i64, offset: i32) -> String {
+        let schedule = CronSchedule::parse(cron).expect("parse");
+        task_index::format_timestamp_ms(schedule.next_after(after_ms, offset).expect("next"))
+    }
+
+    #[test]
+    fn cron_expressions_find_the_next_matching_minute() {
+        assert_eq!(next("*/15 * * * *", WEDNESDAY, 0), "2026-02-04T10:45:00.000Z");
+        assert_eq!(next("0 9 * * 1", WEDNESDAY, 0), "2026-02-09T09:00:00.000Z");
+        assert_eq!(next("0 9 * * 1", WEDNESDAY, 60), "2026-02-09T08:00:00.000Z");
+        assert_eq!(next("@monthly", WEDNESDAY, 0), "2026-03-01T00:00:00.000Z");
+        assert_eq!(next("0 0 29 2 *", WEDNESDAY, 0), "2028-02-29T00:00:00.000Z");
+        // Either restricted day field matches: the 13th, or any Friday.
+        assert_eq!(next("0 12 13 * 5", WEDNESDAY, 0), "2026-02-06T12:00:00.000Z");
+        assert_eq!(next("30 10 * * 3,7", WEDNESDAY - 1, 0), "2026-02-04T10:30:00.000Z");
+
+        assert!(CronSchedule::parse("* * *").is_err());
+        assert!(CronSchedule::parse("60 * * * *").is_err());
+        assert!(CronSchedule::parse("*/0 * * * *").is_err());
+        assert_eq!(
+            CronSchedule::parse("0 0 31 2 *")
+                .expect("parse")
+                .next_after(WEDNESDAY, 0),
+            None
+        );
+    }
+
+    #[test]
+    fn runs_are_due_once_per_fire_time() {
+        let schedule = TaskSchedule {
+            cron: "30 10 * * *".to_string(),
+            prompt: "Summarize".to_string(),
+            enabled: true,
+            utc_offset_minutes: 0,
+        };
+
+        assert_eq!(
+            due_at(&schedule, WEDNESDAY - 30_000, WEDNESDAY + 5_000),
+            Some(WEDNESDAY)
+        );
+        assert_eq!(due_at(&schedule, WEDNESDAY + 5_000, WEDNESDAY + 35_000), None);
+        assert_eq!(
+            due_at(
+                &TaskSchedule {
+                    enabled: false,
+                    ..schedule
+                },
+                WEDNESDAY - 30_000,
+                WEDNESDAY + 5_000
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn run_history_keeps_the_latest_record_per_run() {
+        let dir = temp_dir();
+        std::fs::create_dir_all(task_store::task_dir(&dir, "task-1")).expect("task dir");
+
+        let first = RunRecord::started(WEDNESDAY);
+        append_run(&dir, "task-1", &first).expect("start");
+        append_run(&dir, "task-1", &first.finish(Ok(()))).expect("finish");
+        let second = RunRecord::started(WEDNESDAY + 86_400_000);
+        append_run(&dir, "task-1", &second).expect("start second");
+
+        let runs = list_runs(&dir, "task-1").expect("list");
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0].outcome, RunOutcome::Completed);
+        assert!(runs[0].finished_at.is_some());
+        assert_eq!(runs[1].outcome, RunOutcome::Running);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
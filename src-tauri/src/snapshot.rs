@@ -0,0 +1,630 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Component, Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::change_journal::{self, ChangeKind, ChangeRoot};
+use crate::persist;
+
+pub const TASK_SNAPSHOTS_DIR: &str = "snapshots";
+const SNAPSHOT_INDEX_FILE: &str = "index.json";
+const SNAPSHOT_OBJECTS_DIR: &str = "objects";
+
+/// Files larger than this are indexed but not copied, so they cannot be restored.
+pub const MAX_SNAPSHOT_FILE_BYTES: u64 = 16 * 1024 * 1024;
+/// Upper bound on stored snapshot content per task.
+pub const MAX_SNAPSHOT_TOTAL_BYTES: u64 = 512 * 1024 * 1024;
+/// Upper bound on indexed files per task; deeper trees are truncated.
+pub const MAX_SNAPSHOT_FILES: usize = 20_000;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+struct SnapshotEntry {
+    /// sha256 of the stored copy; `None` when the file exceeded a retention limit.
+    hash: Option<String>,
+    size: u64,
+    modified_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct SnapshotIndex {
+    files: BTreeMap<String, SnapshotEntry>,
+    /// Size of the distinct objects the entries point to.
+    stored_bytes: u64,
+    truncated: bool,
+    /// Files that existed but weren't indexed (past [`MAX_SNAPSHOT_FILES`]), so a restore can
+    /// tell them from files the task created and never deletes them.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    skipped: BTreeSet<String>,
+}
+
+/// What a capture protected.
+#[derive(Default, Debug, PartialEq, Eq)]
+pub struct CaptureReport {
+    pub files: usize,
+    pub stored_bytes: u64,
+    /// Files indexed without a copy because they exceeded a retention limit.
+    pub unprotected: usize,
+    /// The folder has more than [`MAX_SNAPSHOT_FILES`] files; the rest were not indexed.
+    pub truncated: bool,
+}
+
+impl CaptureReport {
+    /// Why some files can't be restored, if any can't.
+    pub fn warning(&self) -> Option<String> {
+        let mut reasons = Vec::new();
+        if self.unprotected > 0 {
+            reasons.push(format!(
+                "{} file(s) exceed the snapshot size limits and can't be restored",
+                self.unprotected
+            ));
+        }
+        if self.truncated {
+            reasons.push(format!(
+                "the folder has more than {MAX_SNAPSHOT_FILES} files; the rest aren't snapshotted"
+            ));
+        }
+
+        (!reasons.is_empty()).then(|| format!("Working folder snapshot is incomplete: {}", reasons.join(", ")))
+    }
+}
+
+#[derive(Serialize, Default, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreReport {
+    /// Files written back from their pre-task copy.
+    pub restored: Vec<String>,
+    /// Files the task created, removed again.
+    pub removed: Vec<String>,
+    /// Files that changed but have no stored copy (over a retention limit, or not snapshotted),
+    /// or that are or sit behind a symlink.
+    pub skipped: Vec<String>,
+}
+
+pub fn task_snapshots_dir(tasks_dir: &Path, task_id: &str) -> PathBuf {
+    crate::task_store::task_dir(tasks_dir, task_id).join(TASK_SNAPSHOTS_DIR)
+}
+
+/// Records the pre-task state of `working_folder` before the agent can touch it.
+///
+/// Runs before every task run boots the VM. A watcher only reports a change after it happened,
+/// so copies are taken up front rather than on first change; later runs only copy files whose
+/// size or mtime moved, and content already in `objects/` is never written twice. Files the task
+/// has already changed keep their original copy, so edits the user made between runs are not
+/// rolled back by a later restore.
+pub fn capture(tasks_dir: &Path, task_id: &str, working_folder: &Path) -> Result<CaptureReport, String> {
+    capture_limited(tasks_dir, task_id, working_folder, MAX_SNAPSHOT_FILES)
+}
+
+fn capture_limited(
+    tasks_dir: &Path,
+    task_id: &str,
+    working_folder: &Path,
+    max_files: usize,
+) -> Result<CaptureReport, String> {
+    let snapshots_dir = task_snapshots_dir(tasks_dir, task_id);
+    let objects_dir = snapshots_dir.join(SNAPSHOT_OBJECTS_DIR);
+    std::fs::create_dir_all(&objects_dir).map_err(|error| error.to_string())?;
+
+    let touched = touched_paths(tasks_dir, task_id)?;
+    let mut index = load_index(&snapshots_dir)?;
+    let mut files = BTreeMap::new();
+    let mut skipped = BTreeSet::new();
+    let mut stored = ObjectTally::default();
+    let mut unprotected = 0;
+    let mut truncated = false;
+
+    for (relative, path, size, modified_at) in walk_files(working_folder) {
+        if files.len() >= max_files {
+            truncated = true;
+            skipped.insert(relative);
+            continue;
+        }
+
+        let previous = index.files.remove(&relative);
+        let entry = match previous {
+            Some(entry) if touched.contains(&relative) => entry,
+            Some(entry) if entry.size == size && entry.modified_at == modified_at => entry,
+            // Unindexed when the task first touched it, so its content now isn't the original.
+            None if touched.contains(&relative) && index.skipped.contains(&relative) => {
+                skipped.insert(relative);
+                continue;
+            }
+            // Created by an earlier run of this task: restoring means removing it, not keeping this copy.
+            None if touched.contains(&relative) => continue,
+            _ => {
+                let within_budget = stored.bytes + size <= MAX_SNAPSHOT_TOTAL_BYTES;
+                let hash = if size <= MAX_SNAPSHOT_FILE_BYTES && within_budget {
+                    store_object(&objects_dir, &path)?
+                } else {
+                    None
+                };
+                SnapshotEntry {
+                    hash,
+                    size,
+                    modified_at,
+                }
+            }
+        };
+
+        if !stored.add(&entry) {
+            unprotected += 1;
+        }
+        files.insert(relative, entry);
+    }
+
+    // Keep copies of touched files the task has since deleted, and touched files that were never
+    // indexed marked as such, deleted or not.
+    for (relative, entry) in index.files {
+        if touched.contains(&relative) {
+            stored.add(&entry);
+            files.insert(relative, entry);
+        }
+    }
+    skipped.extend(index.skipped.into_iter().filter(|relative| touched.contains(relative)));
+
+    let stored_bytes = stored.bytes;
+    let index = SnapshotIndex {
+        files,
+        stored_bytes,
+        truncated,
+        skipped,
+    };
+    save_index(&snapshots_dir, &index)?;
+    prune_objects(&objects_dir, &index)?;
+
+    let report = CaptureReport {
+        files: index.files.len(),
+        stored_bytes,
+        unprotected,
+        truncated,
+    };
+    eprintln!(
+        "[rust:snapshot] captured {} file(s) for task {task_id} ({stored_bytes} bytes stored)",
+        report.files
+    );
+    if let Some(warning) = report.warning() {
+        eprintln!("[rust:snapshot] WARNING task {task_id}: {warning}");
+    }
+
+    Ok(report)
+}
+
+/// Rolls `relative_path` back to its pre-task state.
+pub fn restore_file(
+    tasks_dir: &Path,
+    task_id: &str,
+    working_folder: &Path,
+    relative_path: &str,
+) -> Result<RestoreReport, String> {
+    let snapshots_dir = task_snapshots_dir(tasks_dir, task_id);
+    let index = load_index(&snapshots_dir)?;
+    let created = created_paths(tasks_dir, task_id)?;
+    let mut report = RestoreReport::default();
+    restore_path(
+        &snapshots_dir,
+        &index,
+        &created,
+        working_folder,
+        relative_path,
+        &mut report,
+    )?;
+    Ok(report)
+}
+
+/// Rolls back every working-folder file in the task's change journal.
+pub fn restore_all(tasks_dir: &Path, task_id: &str, working_folder: &Path) -> Result<RestoreReport, String> {
+    let snapshots_dir = task_snapshots_dir(tasks_dir, task_id);
+    let index = load_index(&snapshots_dir)?;
+    let created = created_paths(tasks_dir, task_id)?;
+    let mut report = RestoreReport::default();
+
+    for relative_path in touched_paths(tasks_dir, task_id)? {
+        restore_path(
+            &snapshots_dir,
+            &index,
+            &created,
+            working_folder,
+            &relative_path,
+            &mut report,
+        )?;
+    }
+
+    Ok(report)
+}
+
+fn restore_path(
+    snapshots_dir: &Path,
+    index: &SnapshotIndex,
+    created: &BTreeSet<String>,
+    working_folder: &Path,
+    relative_path: &str,
+    report: &mut RestoreReport,
+) -> Result<(), String> {
+    let relative = checked_relative_path(relative_path)?;
+    let target = working_folder.join(&relative);
+    // Symlinks (the guest can create real ones on the share) are never followed.
+    if persist::crosses_symlink(working_folder, &relative) || target.is_dir() {
+        report.skipped.push(relative_path.to_string());
+        return Ok(());
+    }
+
+    match index.files.get(relative_path) {
+        Some(SnapshotEntry { hash: Some(hash), .. }) => {
+            let object = snapshots_dir.join(SNAPSHOT_OBJECTS_DIR).join(hash);
+            if !object.is_file() {
+                report.skipped.push(relative_path.to_string());
+                return Ok(());
+            }
+
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent).map_err(|error| error.to_string())?;
+            }
+            std::fs::copy(&object, &target).map_err(|error| error.to_string())?;
+            report.restored.push(relative_path.to_string());
+        }
+        None if created.contains(relative_path) && !index.skipped.contains(relative_path) => {
+            if target.is_file() {
+                std::fs::remove_file(&target).map_err(|error| error.to_string())?;
+                report.removed.push(relative_path.to_string());
+            }
+        }
+        // Over a retention limit, or existed before the task but wasn't indexed: never delete it.
+        Some(SnapshotEntry { hash: None, .. }) | None => report.skipped.push(relative_path.to_string()),
+    }
+
+    Ok(())
+}
+
+fn checked_relative_path(relative_path: &str) -> Result<PathBuf, String> {
+    let path = Path::new(relative_path);
+    if relative_path.is_empty()
+        || !path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return Err(format!("Invalid snapshot path: {relative_path}"));
+    }
+    Ok(path.to_path_buf())
+}
+
+fn touched_paths(tasks_dir: &Path, task_id: &str) -> Result<BTreeSet<String>, String> {
+    Ok(change_journal::list_changes(tasks_dir, task_id)?
+        .into_iter()
+        .filter(|change| change.root == ChangeRoot::WorkingFolder)
+        .map(|change| change.path)
+        .collect())
+}
+
+/// Working-folder paths whose first journaled change is a creation, i.e. that the task added.
+fn created_paths(tasks_dir: &Path, task_id: &str) -> Result<BTreeSet<String>, String> {
+    let mut first_kinds = BTreeMap::new();
+    for change in change_journal::list_changes(tasks_dir, task_id)? {
+        if change.root == ChangeRoot::WorkingFolder {
+            first_kinds.entry(change.path).or_insert(change.kind);
+        }
+    }
+
+    Ok(first_kinds
+        .into_iter()
+        .filter(|(_, kind)| *kind == ChangeKind::Created)
+        .map(|(path, _)| path)
+        .collect())
+}
+
+fn load_index(snapshots_dir: &Path) -> Result<SnapshotIndex, String> {
+    let index_path = snapshots_dir.join(SNAPSHOT_INDEX_FILE);
+    if !index_path.exists() {
+        return Ok(SnapshotIndex::default());
+    }
+
+    let content = std::fs::read_to_string(&index_path).map_err(|error| error.to_string())?;
+    serde_json::from_str(&content).map_err(|error| error.to_string())
+}
+
+fn save_index(snapshots_dir: &Path, index: &SnapshotIndex) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(index).map_err(|error| error.to_string())?;
+    persist::write_atomic(&snapshots_dir.join(SNAPSHOT_INDEX_FILE), content.as_bytes())
+}
+
+/// Distinct objects referenced by an index, so duplicate content counts against the budget once.
+#[derive(Default)]
+struct ObjectTally {
+    hashes: BTreeSet<String>,
+    bytes: u64,
+}
+
+impl ObjectTally {
+    /// Counts `entry`'s object; false when it has none.
+    fn add(&mut self, entry: &SnapshotEntry) -> bool {
+        let Some(hash) = &entry.hash else {
+            return false;
+        };
+        if self.hashes.insert(hash.clone()) {
+            self.bytes += entry.size;
+        }
+        true
+    }
+}
+
+/// Stores `path` under its content hash. Content already in `objects_dir` (the file's copy from
+/// an earlier run, or a duplicate elsewhere in the folder) is not written again.
+fn store_object(objects_dir: &Path, path: &Path) -> Result<Option<String>, String> {
+    let Ok(bytes) = std::fs::read(path) else {
+        // Unreadable or vanished mid-walk; index it as not restorable rather than failing the run.
+        return Ok(None);
+    };
+
+    let hash = persist::hex_sha256(&bytes);
+
+    let object = objects_dir.join(&hash);
+    if !object.is_file() {
+        std::fs::write(&object, &bytes).map_err(|error| error.to_string())?;
+    }
+
+    Ok(Some(hash))
+}
+
+fn prune_objects(objects_dir: &Path, index: &SnapshotIndex) -> Result<(), String> {
+    let referenced: BTreeSet<&str> = index.files.values().filter_map(|entry| entry.hash.as_deref()).collect();
+
+    for entry in std::fs::read_dir(objects_dir).map_err(|error| error.to_string())? {
+        let entry = entry.map_err(|error| error.to_string())?;
+        let name = entry.file_name();
+        if !referenced.contains(name.to_string_lossy().as_ref()) {
+            std::fs::remove_file(entry.path()).ok();
+        }
+    }
+
+    Ok(())
+}
+
+/// Regular files under `root` as `(relative, path, size, modified_at_ms)`, skipping symlinks and
+/// the same components the change journal ignores.
+fn walk_files(root: &Path) -> Vec<(String, PathBuf, u64, u64)> {
+    let mut files = Vec::new();
+    let mut stack = vec![root.to_path_buf()];
+
+    while let Some(dir) = stack.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Ok(metadata) = std::fs::symlink_metadata(&path) else {
+                continue;
+            };
+
+            if metadata.file_type().is_symlink() {
+                continue;
+            }
+
+            let name = entry.file_name();
+            if change_journal::IGNORED_COMPONENTS.contains(&name.to_string_lossy().as_ref()) {
+                continue;
+            }
+
+            if metadata.is_dir() {
+                stack.push(path);
+                continue;
+            }
+
+            if !metadata.is_file() {
+                continue;
+            }
+
+            let Ok(relative) = path.strip_prefix(root) else {
+                continue;
+            };
+
+            let relative = relative
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy().to_string())
+                .collect::<Vec<String>>()
+                .join("/");
+
+            let modified_at = metadata
+                .modified()
+                .ok()
+                .and_then(|value| value.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |value| u64::try_from(value.as_millis()).unwrap_or(u64::MAX));
+
+            files.push((relative, path, metadata.len(), modified_at));
+        }
+    }
+
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::change_journal::FileChange;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::SystemTime;
+
+    static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    fn temp_dir() -> PathBuf {
+        let suffix = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let counter = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+        std::env::temp_dir().join(format!("piwork-snapshot-{suffix}-{counter}"))
+    }
+
+    fn record(tasks_dir: &Path, kind: ChangeKind, path: &str) {
+        let change = FileChange {
+            task_id: "task-1".to_string(),
+            timestamp: 1,
+            kind,
+            root: ChangeRoot::WorkingFolder,
+            path: path.to_string(),
+        };
+        change_journal::append_change(&change_journal::task_changes_file(tasks_dir, "task-1"), &change)
+            .expect("append change");
+    }
+
+    #[test]
+    fn restore_all_rolls_back_modified_deleted_and_created_files() {
+        let root = temp_dir();
+        let tasks_dir = root.join("tasks");
+        let work = root.join("work");
+        std::fs::create_dir_all(work.join("src")).expect("create work");
+        std::fs::write(work.join("src/main.rs"), "original").expect("write");
+        std::fs::write(work.join("notes.md"), "keep me").expect("write");
+
+        capture(&tasks_dir, "task-1", &work).expect("capture");
+
+        std::fs::write(work.join("src/main.rs"), "agent edit").expect("edit");
+        std::fs::remove_file(work.join("notes.md")).expect("delete");
+        std::fs::write(work.join("generated.txt"), "new").expect("create");
+        record(&tasks_dir, ChangeKind::Modified, "src/main.rs");
+        record(&tasks_dir, ChangeKind::Deleted, "notes.md");
+        record(&tasks_dir, ChangeKind::Created, "generated.txt");
+
+        let report = restore_all(&tasks_dir, "task-1", &work).expect("restore");
+
+        assert_eq!(report.restored, vec!["notes.md".to_string(), "src/main.rs".to_string()]);
+        assert_eq!(report.removed, vec!["generated.txt".to_string()]);
+        assert_eq!(std::fs::read_to_string(work.join("src/main.rs")).unwrap(), "original");
+        assert_eq!(std::fs::read_to_string(work.join("notes.md")).unwrap(), "keep me");
+        assert!(!work.join("generated.txt").exists());
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn later_capture_keeps_the_first_copy_of_touched_files() {
+        let root = temp_dir();
+        let tasks_dir = root.join("tasks");
+        let work = root.join("work");
+        std::fs::create_dir_all(&work).expect("create work");
+        std::fs::write(work.join("a.txt"), "v1").expect("write");
+
+        capture(&tasks_dir, "task-1", &work).expect("capture");
+        std::fs::write(work.join("a.txt"), "agent v2").expect("edit");
+        record(&tasks_dir, ChangeKind::Modified, "a.txt");
+        capture(&tasks_dir, "task-1", &work).expect("second capture");
+
+        let report = restore_file(&tasks_dir, "task-1", &work, "a.txt").expect("restore");
+
+        assert_eq!(report.restored, vec!["a.txt".to_string()]);
+        assert_eq!(std::fs::read_to_string(work.join("a.txt")).unwrap(), "v1");
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn files_missing_from_the_snapshot_are_only_removed_when_the_task_created_them() {
+        let root = temp_dir();
+        let tasks_dir = root.join("tasks");
+        let work = root.join("work");
+        std::fs::create_dir_all(&work).expect("create work");
+
+        let report = capture(&tasks_dir, "task-1", &work).expect("capture");
+        assert_eq!(report.warning(), None);
+
+        // e.g. a file past the snapshot's file limit, edited by the agent.
+        std::fs::write(work.join("unindexed.txt"), "agent edit").expect("write");
+        record(&tasks_dir, ChangeKind::Modified, "unindexed.txt");
+
+        let report = restore_all(&tasks_dir, "task-1", &work).expect("restore");
+
+        assert_eq!(report.skipped, vec!["unindexed.txt".to_string()]);
+        assert!(work.join("unindexed.txt").exists());
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn duplicate_content_is_stored_once() {
+        let root = temp_dir();
+        let tasks_dir = root.join("tasks");
+        let work = root.join("work");
+        std::fs::create_dir_all(work.join("copy")).expect("create work");
+        std::fs::write(work.join("a.txt"), "same bytes").expect("write");
+        std::fs::write(work.join("copy/a.txt"), "same bytes").expect("write");
+
+        let report = capture(&tasks_dir, "task-1", &work).expect("capture");
+
+        assert_eq!(report.files, 2);
+        assert_eq!(report.stored_bytes, 10);
+        let objects = task_snapshots_dir(&tasks_dir, "task-1").join(SNAPSHOT_OBJECTS_DIR);
+        assert_eq!(std::fs::read_dir(&objects).expect("objects").count(), 1);
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn files_past_the_file_limit_are_never_treated_as_created() {
+        let root = temp_dir();
+        let tasks_dir = root.join("tasks");
+        let work = root.join("work");
+        std::fs::create_dir_all(&work).expect("create work");
+        std::fs::write(work.join("a.txt"), "indexed").expect("write");
+        std::fs::write(work.join("b.txt"), "past the limit").expect("write");
+
+        let report = capture_limited(&tasks_dir, "task-1", &work, 1).expect("capture");
+        assert!(report.truncated);
+
+        // Editors that save by rename make an edit look like a creation.
+        std::fs::write(work.join("b.txt"), "agent edit").expect("edit");
+        std::fs::write(work.join("c.txt"), "new").expect("create");
+        record(&tasks_dir, ChangeKind::Created, "b.txt");
+        record(&tasks_dir, ChangeKind::Created, "c.txt");
+        // A later run with room to spare must not take the edited file as its original.
+        capture(&tasks_dir, "task-1", &work).expect("second capture");
+
+        let report = restore_all(&tasks_dir, "task-1", &work).expect("restore");
+
+        assert_eq!(report.skipped, vec!["b.txt".to_string()]);
+        assert_eq!(report.removed, vec!["c.txt".to_string()]);
+        assert_eq!(std::fs::read_to_string(work.join("b.txt")).unwrap(), "agent edit");
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn restore_never_writes_through_planted_symlinks() {
+        let root = temp_dir();
+        let tasks_dir = root.join("tasks");
+        let work = root.join("work");
+        let outside = root.join("outside");
+        std::fs::create_dir_all(work.join("docs")).expect("create work");
+        std::fs::create_dir_all(&outside).expect("create outside");
+        std::fs::write(work.join("a.txt"), "original a").expect("write");
+        std::fs::write(work.join("docs/b.txt"), "original b").expect("write");
+        std::fs::write(outside.join("victim.txt"), "victim").expect("write");
+        std::fs::write(outside.join("b.txt"), "outside b").expect("write");
+
+        capture(&tasks_dir, "task-1", &work).expect("capture");
+
+        std::fs::remove_file(work.join("a.txt")).expect("remove");
+        std::os::unix::fs::symlink(outside.join("victim.txt"), work.join("a.txt")).expect("file link");
+        std::fs::remove_dir_all(work.join("docs")).expect("remove dir");
+        std::os::unix::fs::symlink(&outside, work.join("docs")).expect("dir link");
+        record(&tasks_dir, ChangeKind::Modified, "a.txt");
+        record(&tasks_dir, ChangeKind::Modified, "docs/b.txt");
+
+        let report = restore_all(&tasks_dir, "task-1", &work).expect("restore");
+
+        assert_eq!(report.skipped, vec!["a.txt".to_string(), "docs/b.txt".to_string()]);
+        assert!(report.restored.is_empty());
+        assert_eq!(std::fs::read_to_string(outside.join("victim.txt")).unwrap(), "victim");
+        assert_eq!(std::fs::read_to_string(outside.join("b.txt")).unwrap(), "outside b");
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn restore_rejects_paths_outside_the_working_folder() {
+        let root = temp_dir();
+
+        let error = restore_file(&root, "task-1", &root, "../escape.txt").expect_err("must reject");
+
+        assert!(error.contains("Invalid snapshot path"));
+    }
+}
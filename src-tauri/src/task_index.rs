@@ -0,0 +1,627 @@
+use rusqlite::{params, params_from_iter, Connection};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::conversation::Conversation;
+use crate::task_store::{self, TaskMetadata};
+
+pub const TASK_INDEX_FILE: &str = "index.sqlite3";
+
+/// Bump when the schema changes; a mismatched index is dropped and rebuilt from `task.json` files.
+const INDEX_VERSION: i64 = 3;
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+const DEFAULT_PAGE_SIZE: u32 = 100;
+const MAX_PAGE_SIZE: u32 = 1000;
+const DEFAULT_SEARCH_LIMIT: u32 = 20;
+const SNIPPET_TOKENS: i32 = 16;
+// Private-use sentinels mark matches until the snippet is HTML-escaped.
+const MATCH_START: char = '\u{E000}';
+const MATCH_END: char = '\u{E001}';
+
+const SCHEMA: &str = "
+CREATE TABLE tasks (
+    id TEXT PRIMARY KEY,
+    title TEXT NOT NULL,
+    status TEXT NOT NULL,
+    provider TEXT,
+    model TEXT,
+    working_folder TEXT,
+    created_at_ms INTEGER NOT NULL,
+    updated_at_ms INTEGER NOT NULL,
+    metadata TEXT NOT NULL
+);
+CREATE INDEX tasks_updated_at ON tasks (updated_at_ms);
+CREATE INDEX tasks_created_at ON tasks (created_at_ms);
+CREATE INDEX tasks_status ON tasks (status);
+CREATE VIRTUAL TABLE task_search USING fts5(
+    task_id UNINDEXED,
+    title,
+    content,
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+";
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum TaskSort {
+    #[default]
+    UpdatedDesc,
+    UpdatedAsc,
+    CreatedDesc,
+    CreatedAsc,
+}
+
+impl TaskSort {
+    fn order_by(self) -> &'static str {
+        match self {
+            Self::UpdatedDesc => "updated_at_ms DESC, id ASC",
+            Self::UpdatedAsc => "updated_at_ms ASC, id ASC",
+            Self::CreatedDesc => "created_at_ms DESC, id ASC",
+            Self::CreatedAsc => "created_at_ms ASC, id ASC",
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TaskQuery {
+    pub status: Option<String>,
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    pub working_folder: Option<String>,
+    pub sort: TaskSort,
+    /// Page size; defaults to 100, capped at 1000.
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskPage {
+    pub tasks: Vec<TaskMetadata>,
+    /// Matching tasks across all pages.
+    pub total: u64,
+}
+
+pub fn task_index_file(tasks_dir: &Path) -> PathBuf {
+    tasks_dir.join(TASK_INDEX_FILE)
+}
+
+/// Opens the index, creating and filling it from disk when missing or from an older schema.
+fn open(tasks_dir: &Path) -> Result<Connection, String> {
+    let mut connection = open_raw(tasks_dir)?;
+    let version: i64 = connection
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .map_err(|error| error.to_string())?;
+
+    if version != INDEX_VERSION {
+        rebuild_with(&mut connection, tasks_dir)?;
+    }
+
+    Ok(connection)
+}
+
+/// Opens the index file as is, without checking its schema version.
+fn open_raw(tasks_dir: &Path) -> Result<Connection, String> {
+    std::fs::create_dir_all(tasks_dir).map_err(|error| error.to_string())?;
+
+    let connection = Connection::open(task_index_file(tasks_dir)).map_err(|error| error.to_string())?;
+    connection
+        .busy_timeout(BUSY_TIMEOUT)
+        .map_err(|error| error.to_string())?;
+    Ok(connection)
+}
+
+fn rebuild_with(connection: &mut Connection, tasks_dir: &Path) -> Result<(), String> {
+    let scan = task_store::scan_tasks(tasks_dir)?;
+    for quarantined in &scan.quarantined {
+        eprintln!(
+            "[rust:task-index] skipping unreadable task {}: {}",
+            quarantined.task_id, quarantined.error
+        );
+    }
+    let tasks = scan.tasks;
+
+    let transaction = connection.transaction().map_err(|error| error.to_string())?;
+    transaction
+        .execute_batch("DROP TABLE IF EXISTS tasks; DROP TABLE IF EXISTS task_search;")
+        .map_err(|error| error.to_string())?;
+    transaction.execute_batch(SCHEMA).map_err(|error| error.to_string())?;
+    for task in &tasks {
+        insert(&transaction, task)?;
+        let conversation =
+            task_store::load_conversation(tasks_dir, &task.id)?.and_then(|raw| Conversation::parse(&raw).ok());
+        if let Some(conversation) = conversation {
+            set_search_content(&transaction, &task.id, &conversation.text())?;
+        }
+    }
+    transaction
+        .pragma_update(None, "user_version", INDEX_VERSION)
+        .map_err(|error| error.to_string())?;
+    transaction.commit().map_err(|error| error.to_string())?;
+
+    eprintln!("[rust:task-index] rebuilt index with {} task(s)", tasks.len());
+    Ok(())
+}
+
+fn insert(connection: &Connection, task: &TaskMetadata) -> Result<(), String> {
+    let metadata = serde_json::to_string(task).map_err(|error| error.to_string())?;
+
+    connection
+        .execute(
+            "INSERT INTO tasks (id, title, status, provider, model, working_folder, created_at_ms, updated_at_ms, metadata)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+             ON CONFLICT(id) DO UPDATE SET
+                title = excluded.title,
+                status = excluded.status,
+                provider = excluded.provider,
+                model = excluded.model,
+                working_folder = excluded.working_folder,
+                created_at_ms = excluded.created_at_ms,
+                updated_at_ms = excluded.updated_at_ms,
+                metadata = excluded.metadata",
+            params![
+                task.id,
+                task.title,
+                task.status.as_str(),
+                task.provider,
+                task.model,
+                task.working_folder,
+                parse_timestamp_ms(&task.created_at).unwrap_or(0),
+                parse_timestamp_ms(&task.updated_at).unwrap_or(0),
+                metadata,
+            ],
+        )
+        .map_err(|error| error.to_string())?;
+
+    let updated = connection
+        .execute(
+            "UPDATE task_search SET title = ?2 WHERE task_id = ?1",
+            params![task.id, task.title],
+        )
+        .map_err(|error| error.to_string())?;
+    if updated == 0 {
+        connection
+            .execute(
+                "INSERT INTO task_search (task_id, title, content) VALUES (?1, ?2, '')",
+                params![task.id, task.title],
+            )
+            .map_err(|error| error.to_string())?;
+    }
+
+    Ok(())
+}
+
+fn set_search_content(connection: &Connection, task_id: &str, content: &str) -> Result<(), String> {
+    let title: String = connection
+        .query_row("SELECT title FROM tasks WHERE id = ?1", params![task_id], |row| {
+            row.get(0)
+        })
+        .unwrap_or_default();
+
+    connection
+        .execute("DELETE FROM task_search WHERE task_id = ?1", params![task_id])
+        .map_err(|error| error.to_string())?;
+    connection
+        .execute(
+            "INSERT INTO task_search (task_id, title, content) VALUES (?1, ?2, ?3)",
+            params![task_id, title, content],
+        )
+        .map_err(|error| error.to_string())?;
+
+    Ok(())
+}
+
+/// Drops the index so the next read rebuilds it from disk.
+pub fn invalidate(tasks_dir: &Path) {
+    let _ = std::fs::remove_file(task_index_file(tasks_dir));
+}
+
+pub fn rebuild(tasks_dir: &Path) -> Result<(), String> {
+    // `open` would already rebuild an outdated index; this always rebuilds exactly once.
+    let mut connection = open_raw(tasks_dir)?;
+    rebuild_with(&mut connection, tasks_dir)
+}
+
+pub fn upsert(tasks_dir: &Path, task: &TaskMetadata) -> Result<(), String> {
+    insert(&open(tasks_dir)?, task)
+}
+
+pub fn remove(tasks_dir: &Path, task_id: &str) -> Result<(), String> {
+    let connection = open(tasks_dir)?;
+    connection
+        .execute("DELETE FROM tasks WHERE id = ?1", params![task_id])
+        .map_err(|error| error.to_string())?;
+    connection
+        .execute("DELETE FROM task_search WHERE task_id = ?1", params![task_id])
+        .map_err(|error| error.to_string())?;
+    Ok(())
+}
+
+/// Re-indexes the searchable text of a task's conversation.
+pub fn index_conversation(tasks_dir: &Path, task_id: &str, conversation: &Conversation) -> Result<(), String> {
+    set_search_content(&open(tasks_dir)?, task_id, &conversation.text())
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskSearchHit {
+    pub task_id: String,
+    /// HTML-escaped title with matches wrapped in `<mark>`.
+    pub title: String,
+    /// HTML-escaped conversation excerpt with matches wrapped in `<mark>`; empty for title-only hits.
+    pub snippet: String,
+    /// bm25 score; lower is more relevant. Title matches weigh more than conversation matches.
+    pub rank: f64,
+}
+
+/// Full-text search over task titles and conversation text, best matches first.
+///
+/// Every whitespace-separated term must match; the last one also matches as a prefix.
+pub fn search(tasks_dir: &Path, text: &str, limit: Option<u32>) -> Result<Vec<TaskSearchHit>, String> {
+    let Some(match_query) = fts_query(text) else {
+        return Ok(Vec::new());
+    };
+
+    let connection = open(tasks_dir)?;
+    let limit = limit.unwrap_or(DEFAULT_SEARCH_LIMIT).min(MAX_PAGE_SIZE);
+    let mut statement = connection
+        .prepare(
+            "SELECT task_id,
+                    highlight(task_search, 1, ?2, ?3),
+                    snippet(task_search, 2, ?2, ?3, '…', ?4),
+                    bm25(task_search, 0.0, 5.0, 1.0) AS rank
+             FROM task_search
+             WHERE task_search MATCH ?1
+             ORDER BY rank
+             LIMIT ?5",
+        )
+        .map_err(|error| error.to_string())?;
+
+    let rows = statement
+        .query_map(
+            params![
+                match_query,
+                MATCH_START.to_string(),
+                MATCH_END.to_string(),
+                SNIPPET_TOKENS,
+                limit
+            ],
+            |row| {
+                Ok(TaskSearchHit {
+                    task_id: row.get(0)?,
+                    title: marked_html(&row.get::<_, String>(1)?),
+                    snippet: marked_html(&row.get::<_, String>(2)?),
+                    rank: row.get(3)?,
+                })
+            },
+        )
+        .map_err(|error| error.to_string())?;
+
+    rows.map(|row| row.map_err(|error| error.to_string())).collect()
+}
+
+/// Turns free text into an FTS5 query of quoted terms so user input can't hit query syntax.
+fn fts_query(text: &str) -> Option<String> {
+    let terms: Vec<String> = text
+        .split_whitespace()
+        .map(|term| term.replace('"', ""))
+        .filter(|term| !term.is_empty())
+        .map(|term| format!("\"{term}\""))
+        .collect();
+
+    let (last, rest) = terms.split_last()?;
+    let mut query = rest.join(" ");
+    if !query.is_empty() {
+        query.push(' ');
+    }
+    query.push_str(last);
+    query.push('*');
+    Some(query)
+}
+
+fn marked_html(raw: &str) -> String {
+    let mut html = String::with_capacity(raw.len());
+    for character in raw.chars() {
+        match character {
+            MATCH_START => html.push_str("<mark>"),
+            MATCH_END => html.push_str("</mark>"),
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            '\'' => html.push_str("&#39;"),
+            other => html.push(other),
+        }
+    }
+    html
+}
+
+pub fn list_all(tasks_dir: &Path) -> Result<Vec<TaskMetadata>, String> {
+    let connection = open(tasks_dir)?;
+    let mut statement = connection
+        .prepare(&format!(
+            "SELECT metadata FROM tasks ORDER BY {}",
+            TaskSort::UpdatedDesc.order_by()
+        ))
+        .map_err(|error| error.to_string())?;
+    let rows = statement
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|error| error.to_string())?;
+
+    collect_tasks(rows)
+}
+
+fn collect_tasks(rows: impl Iterator<Item = rusqlite::Result<String>>) -> Result<Vec<TaskMetadata>, String> {
+    let mut tasks = Vec::new();
+    for row in rows {
+        let metadata = row.map_err(|error| error.to_string())?;
+        tasks.push(serde_json::from_str(&metadata).map_err(|error| error.to_string())?);
+    }
+    Ok(tasks)
+}
+
+pub fn query(tasks_dir: &Path, query: &TaskQuery) -> Result<TaskPage, String> {
+    let connection = open(tasks_dir)?;
+
+    let mut conditions = Vec::new();
+    let mut values = Vec::new();
+    for (column, value) in [
+        ("status", &query.status),
+        ("provider", &query.provider),
+        ("model", &query.model),
+        ("working_folder", &query.working_folder),
+    ] {
+        if let Some(value) = value {
+            values.push(value.clone());
+            conditions.push(format!("{column} = ?{}", values.len()));
+        }
+    }
+
+    let where_clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    };
+
+    let total: u64 = connection
+        .query_row(
+            &format!("SELECT COUNT(*) FROM tasks {where_clause}"),
+            params_from_iter(values.iter()),
+            |row| row.get(0),
+        )
+        .map_err(|error| error.to_string())?;
+
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE);
+    let offset = query.offset.unwrap_or(0);
+    let sql = format!(
+        "SELECT metadata FROM tasks {where_clause} ORDER BY {} LIMIT {limit} OFFSET {offset}",
+        query.sort.order_by()
+    );
+
+    let mut statement = connection.prepare(&sql).map_err(|error| error.to_string())?;
+    let rows = statement
+        .query_map(params_from_iter(values.iter()), |row| row.get::<_, String>(0))
+        .map_err(|error| error.to_string())?;
+
+    Ok(TaskPage {
+        tasks: collect_tasks(rows)?,
+        total,
+    })
+}
+
+/// Parses the RFC 3339 timestamps the frontend writes (`2026-02-04T00:00:00.000Z`, or with a
+/// `+HH:MM` offset) into Unix milliseconds.
+pub fn parse_timestamp_ms(raw: &str) -> Option<i64> {
+    let raw = raw.trim();
+    let (date, time) = raw.split_once(['T', 't', ' '])?;
+
+    let mut date_parts = date.splitn(3, '-');
+    let year: i64 = date_parts.next()?.parse().ok()?;
+    let month: i64 = date_parts.next()?.parse().ok()?;
+    let day: i64 = date_parts.next()?.parse().ok()?;
+
+    let (clock, offset_ms) = if let Some(clock) = time.strip_suffix(['Z', 'z']) {
+        (clock, 0)
+    } else {
+        let split = time.rfind(['+', '-'])?;
+        let (clock, offset) = time.split_at(split);
+        let sign = if offset.starts_with('-') { -1 } else { 1 };
+        let (hours, minutes) = offset[1..].split_once(':')?;
+        let offset_ms = (hours.parse::<i64>().ok()? * 60 + minutes.parse::<i64>().ok()?) * 60_000;
+        (clock, sign * offset_ms)
+    };
+
+    let (hms, fraction) = clock.split_once('.').unwrap_or((clock, ""));
+    let mut clock_parts = hms.splitn(3, ':');
+    let hour: i64 = clock_parts.next()?.parse().ok()?;
+    let minute: i64 = clock_parts.next()?.parse().ok()?;
+    let second: i64 = clock_parts.next()?.parse().ok()?;
+
+    let millis: i64 = if fraction.is_empty() {
+        0
+    } else {
+        format!("{fraction:0<3}")[..3].parse().ok()?
+    };
+
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    let seconds = days_from_civil(year, month, day) * 86_400 + hour * 3_600 + minute * 60 + second;
+    Some(seconds * 1_000 + millis - offset_ms)
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date (Howard Hinnant's algorithm).
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_index = (month + 9) % 12;
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Formats Unix milliseconds the way the frontend's `Date.toISOString()` does
+/// (`2026-02-04T00:00:00.000Z`).
+pub fn format_timestamp_ms(ms: i64) -> String {
+    let days = ms.div_euclid(86_400_000);
+    let ms_of_day = ms.rem_euclid(86_400_000);
+    let (year, month, day) = civil_from_days(days);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+        ms_of_day / 3_600_000,
+        ms_of_day / 60_000 % 60,
+        ms_of_day / 1_000 % 60,
+        ms_of_day % 1_000
+    )
+}
+
+/// Inverse of [`days_from_civil`].
+pub fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    fn temp_dir() -> PathBuf {
+        let suffix = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let counter = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+        std::env::temp_dir().join(format!("piwork-task-index-{suffix}-{counter}"))
+    }
+
+    fn task(id: &str, status: &str, updated_at: &str) -> TaskMetadata {
+        let mut task: TaskMetadata = serde_json::from_value(serde_json::json!({
+            "id": id,
+            "title": "Test",
+            "status": status,
+            "createdAt": "2026-02-04T00:00:00.000Z",
+            "updatedAt": updated_at,
+        }))
+        .expect("task");
+        task.provider = Some("anthropic".to_string());
+        task
+    }
+
+    #[test]
+    fn timestamps_parse_to_unix_millis() {
+        assert_eq!(parse_timestamp_ms("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(parse_timestamp_ms("2026-02-04T00:00:01.5Z"), Some(1_770_163_201_500));
+        assert_eq!(
+            parse_timestamp_ms("2026-02-04T02:00:01+02:00"),
+            parse_timestamp_ms("2026-02-04T00:00:01Z")
+        );
+        assert_eq!(parse_timestamp_ms("not a date"), None);
+        assert_eq!(format_timestamp_ms(1_770_163_201_500), "2026-02-04T00:00:01.500Z");
+        assert_eq!(format_timestamp_ms(951_782_400_000), "2000-02-29T00:00:00.000Z");
+    }
+
+    #[test]
+    fn query_filters_sorts_by_real_time_and_paginates() {
+        let dir = temp_dir();
+        // String order disagrees with time order: the offset timestamp is the latest.
+        task_store::upsert_task(&dir, &task("a", "idle", "2026-02-04T10:00:00Z")).expect("upsert");
+        task_store::upsert_task(&dir, &task("b", "idle", "2026-02-04T09:00:00-05:00")).expect("upsert");
+        task_store::upsert_task(&dir, &task("c", "completed", "2026-02-04T11:00:00Z")).expect("upsert");
+
+        let idle = query(
+            &dir,
+            &TaskQuery {
+                status: Some("idle".to_string()),
+                ..TaskQuery::default()
+            },
+        )
+        .expect("query");
+        let ids: Vec<&str> = idle.tasks.iter().map(|task| task.id.as_str()).collect();
+        assert_eq!(ids, vec!["b", "a"]);
+        assert_eq!(idle.total, 2);
+
+        let page = query(
+            &dir,
+            &TaskQuery {
+                limit: Some(1),
+                offset: Some(1),
+                ..TaskQuery::default()
+            },
+        )
+        .expect("query");
+        assert_eq!(page.total, 3);
+        assert_eq!(page.tasks.len(), 1);
+        assert_eq!(page.tasks[0].id, "c");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    fn conversation(text: &str) -> String {
+        serde_json::json!({
+            "messages": [
+                { "role": "user", "blocks": [{ "type": "text", "text": text }] },
+                { "role": "assistant", "blocks": [{ "type": "thinking", "text": "private spreadsheet musings" }] }
+            ]
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn search_ranks_title_and_conversation_matches_with_safe_snippets() {
+        let dir = temp_dir();
+        let mut titled = task("a", "idle", "2026-02-04T10:00:00Z");
+        titled.title = "Q3 spreadsheet cleanup".to_string();
+        task_store::upsert_task(&dir, &titled).expect("upsert");
+        task_store::upsert_task(&dir, &task("b", "idle", "2026-02-04T10:00:00Z")).expect("upsert");
+        task_store::upsert_task(&dir, &task("c", "idle", "2026-02-04T10:00:00Z")).expect("upsert");
+        task_store::save_conversation(&dir, "b", &conversation("please clean the <Q3> spreadsheet")).expect("save");
+
+        let hits = search(&dir, "q3 spread", None).expect("search");
+        let ids: Vec<&str> = hits.iter().map(|hit| hit.task_id.as_str()).collect();
+
+        assert_eq!(ids, vec!["a", "b"]);
+        assert_eq!(hits[0].title, "<mark>Q3</mark> <mark>spreadsheet</mark> cleanup");
+        assert!(hits[1]
+            .snippet
+            .contains("&lt;<mark>Q3</mark>&gt; <mark>spreadsheet</mark>"));
+        assert!(search(&dir, "musings", None).expect("search").is_empty());
+        assert!(search(&dir, "\"(", None).expect("search").is_empty());
+
+        invalidate(&dir);
+        assert_eq!(search(&dir, "spreadsheet", None).expect("search").len(), 2);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn missing_index_is_rebuilt_from_disk() {
+        let dir = temp_dir();
+        task_store::upsert_task(&dir, &task("a", "idle", "2026-02-04T10:00:00Z")).expect("upsert");
+        invalidate(&dir);
+
+        let page = query(&dir, &TaskQuery::default()).expect("query");
+
+        assert_eq!(page.total, 1);
+        assert_eq!(page.tasks[0].id, "a");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
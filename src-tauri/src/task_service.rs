@@ -0,0 +1,343 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Receiver;
+use std::time::Duration;
+
+use crate::git;
+use crate::isolation;
+use crate::rpc_bus::{self, RpcBus};
+use crate::task_index;
+use crate::task_status::TaskStatus;
+use crate::task_store::{self, TaskMetadata};
+use crate::vm::{self, VmState};
+
+/// Longest a single taskd request (or a task switch) may take.
+pub const REQUEST_TIMEOUT_SECS: u64 = 60;
+
+/// The task lifecycle as the host drives it: saving and binding tasks, then opening, prompting
+/// and stopping them in taskd. Tauri commands, the test server and the CLI all go through this,
+/// so each gets a real result instead of a request the UI may or may not carry out.
+///
+/// Booting the VM is left to the caller; opening a task needs the VM ready with that task's
+/// state mounted.
+pub struct TaskService<'a> {
+    pub tasks_dir: PathBuf,
+    pub vm: &'a VmState,
+    pub bus: &'a RpcBus,
+}
+
+/// A new idle, unsaved task.
+pub fn new_task(title: &str, working_folder: Option<String>) -> TaskMetadata {
+    let now = task_index::format_timestamp_ms(task_store::now_ms());
+    let title = title.trim();
+
+    TaskMetadata {
+        schema_version: task_store::TASK_SCHEMA_VERSION,
+        id: task_store::new_task_id(title),
+        title: if title.is_empty() { "New Task" } else { title }.to_string(),
+        status: TaskStatus::Idle,
+        status_history: Vec::new(),
+        created_at: now.clone(),
+        updated_at: now,
+        session_file: None,
+        working_folder,
+        mounts: None,
+        provider: None,
+        model: None,
+        thinking_level: None,
+        connectors_enabled: None,
+        git_branch: None,
+        git_head: None,
+        isolated: None,
+        isolation: None,
+        schedule: None,
+    }
+}
+
+impl TaskService<'_> {
+    pub fn load(&self, task_id: &str) -> Result<TaskMetadata, String> {
+        if !crate::is_valid_task_id(task_id) {
+            return Err("Invalid task id".to_string());
+        }
+
+        task_store::load_task(&self.tasks_dir, task_id)?.ok_or_else(|| format!("Task {task_id} not found"))
+    }
+
+    /// Saves `task` and returns it as stored.
+    pub fn create(&self, task: TaskMetadata) -> Result<TaskMetadata, String> {
+        let task_id = task.id.clone();
+        self.save(task)?;
+        self.load(&task_id)
+    }
+
+    /// Saves a task, validating its working folder and recording git details (and creating its
+    /// isolated copy) the first time it is bound to one.
+    pub fn save(&self, mut task: TaskMetadata) -> Result<(), String> {
+        let existing = task_store::load_task(&self.tasks_dir, &task.id)?;
+        let first_bind = existing.is_none_or(|existing_task| existing_task.working_folder.is_none());
+        if first_bind && task.isolated == Some(true) && crate::resolve_workspace_root_from_env()?.is_some() {
+            return Err(format!(
+                "Task isolation is not supported when {} is set",
+                crate::WORKSPACE_ROOT_ENV_VAR
+            ));
+        }
+
+        if first_bind {
+            if let Some(folder) = task.working_folder.take() {
+                // The folder is fixed from here on, so store it resolved and checked the way
+                // `bind_folder` does, not as given (e.g. a CLI-relative `.`).
+                task.working_folder = Some(crate::runtime_validate_working_folder(folder, None)?.folder);
+            }
+            if let Some(repo) = task
+                .working_folder
+                .as_deref()
+                .and_then(|folder| git::detect(Path::new(folder)))
+            {
+                task.git_branch = repo.branch;
+                task.git_head = repo.head;
+            }
+        }
+
+        let created = match task.working_folder.as_deref() {
+            Some(folder) if first_bind && task.isolated == Some(true) && task.isolation.is_none() => {
+                let created = isolation::create(&self.tasks_dir, &task.id, Path::new(folder))?;
+                task.isolation = Some(created.clone());
+                Some(created)
+            }
+            _ => None,
+        };
+
+        let result = task_store::upsert_task(&self.tasks_dir, &task);
+        if let (Err(_), Some(created)) = (&result, &created) {
+            // Nothing records the workspace yet, so drop it (and its branch) rather than leak it.
+            isolation::remove(&self.tasks_dir, &task.id, created);
+        }
+        result
+    }
+
+    /// Binds a task to `folder`. The folder is set once; binding the same folder again is a
+    /// no-op and a different one is refused.
+    pub fn bind_folder(&self, task_id: &str, folder: &str) -> Result<TaskMetadata, String> {
+        let mut task = self.load(task_id)?;
+        let validated = crate::runtime_validate_working_folder(folder.to_string(), None)?;
+
+        match task.working_folder.as_deref() {
+            Some(current) if current == validated.folder => return Ok(task),
+            Some(_) => {
+                return Err(
+                    "Working folder is locked for this task. Create a new task to use a different folder.".to_string(),
+                )
+            }
+            None => {}
+        }
+
+        task.working_folder = Some(validated.folder);
+        self.save(task)?;
+        self.load(task_id)
+    }
+
+    /// Opens the task in taskd and makes it the active one, once it reports `task_ready`.
+    pub fn open(&self, task: &TaskMetadata) -> Result<(), String> {
+        self.ensure_mounted(&task.id)?;
+        let lines = self.bus.subscribe();
+        self.open_with(&lines, task)
+    }
+
+    /// Sends `message` to the active task and returns its prompt id once taskd accepts it.
+    /// Completion is reported by the `agent_end` or `task_error` event.
+    pub fn prompt(&self, task_id: &str, message: &str) -> Result<String, String> {
+        self.ensure_mounted(task_id)?;
+        let lines = self.bus.subscribe();
+        self.send_prompt(&lines, message)
+    }
+
+    /// Opens the task, sends `message` and waits for the agent to finish. Fails on
+    /// `task_error` or after `run_timeout`.
+    pub fn run_prompt(&self, task: &TaskMetadata, message: &str, run_timeout: Duration) -> Result<(), String> {
+        self.ensure_mounted(&task.id)?;
+        // Subscribe before anything is sent so no response or event can be missed.
+        let lines = self.bus.subscribe();
+        self.open_with(&lines, task)?;
+        self.send_prompt(&lines, message)?;
+
+        let (event, payload) = rpc_bus::wait_for_event(&lines, &task.id, &["agent_end", "task_error"], run_timeout)?;
+        if event == "task_error" {
+            return Err(format!("Run failed: {payload}"));
+        }
+
+        Ok(())
+    }
+
+    /// Stops the task's agent in taskd. A task taskd has never opened counts as stopped.
+    pub fn stop(&self, task_id: &str) -> Result<(), String> {
+        let lines = self.bus.subscribe();
+        match self.request(&lines, "stop_task", &serde_json::json!({ "taskId": task_id })) {
+            Err(error) if error.contains("TASK_NOT_FOUND") => Ok(()),
+            result => result.map(|_| ()),
+        }
+    }
+
+    fn open_with(&self, lines: &Receiver<String>, task: &TaskMetadata) -> Result<(), String> {
+        let mut payload = serde_json::json!({ "taskId": task.id });
+        if let Some(folder) = task.working_folder.clone() {
+            let validated = crate::runtime_validate_working_folder(folder, None)?;
+            payload["workingFolder"] = validated.folder.into();
+            payload["workingFolderRelative"] = validated.relative_path.into();
+        }
+        for (key, value) in [
+            ("provider", &task.provider),
+            ("model", &task.model),
+            ("thinkingLevel", &task.thinking_level),
+        ] {
+            if let Some(value) = value {
+                payload[key] = value.clone().into();
+            }
+        }
+
+        self.request(lines, "create_or_open_task", &payload)?;
+        self.request(lines, "switch_task", &serde_json::json!({ "taskId": task.id }))?;
+        let (event, payload) = rpc_bus::wait_for_event(
+            lines,
+            &task.id,
+            &["task_ready", "task_error"],
+            Duration::from_secs(REQUEST_TIMEOUT_SECS),
+        )?;
+        if event == "task_error" {
+            return Err(format!("Task failed to start: {payload}"));
+        }
+
+        Ok(())
+    }
+
+    fn send_prompt(&self, lines: &Receiver<String>, message: &str) -> Result<String, String> {
+        if message.trim().is_empty() {
+            return Err("Prompt is empty".to_string());
+        }
+
+        let prompt_id = format!("host-{}", task_store::now_ms());
+        self.request(
+            lines,
+            "prompt",
+            &serde_json::json!({ "message": message, "promptId": prompt_id }),
+        )?;
+        Ok(prompt_id)
+    }
+
+    fn request(
+        &self,
+        lines: &Receiver<String>,
+        kind: &str,
+        payload: &serde_json::Value,
+    ) -> Result<serde_json::Value, String> {
+        rpc_bus::request(
+            lines,
+            |message| vm::send(self.vm, message),
+            kind,
+            payload,
+            Duration::from_secs(REQUEST_TIMEOUT_SECS),
+        )
+    }
+
+    /// taskd only sees the state of the task mounted at boot, so that is the only one it can open.
+    fn ensure_mounted(&self, task_id: &str) -> Result<(), String> {
+        let status = vm::status(self.vm);
+        if !matches!(status.status, vm::VmStatus::Ready) {
+            return Err("VM is not running".to_string());
+        }
+        if status.task_id.as_deref() != Some(task_id) {
+            return Err(format!("Task {task_id} is not mounted in the running VM"));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scheduler::TaskSchedule;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    fn temp_dir() -> PathBuf {
+        let suffix = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let counter = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+        std::env::temp_dir().join(format!("piwork-task-service-{suffix}-{counter}"))
+    }
+
+    #[test]
+    fn folders_bind_once_and_runtime_calls_need_a_mounted_task() {
+        let dir = temp_dir();
+        let folder = dir.join("project");
+        std::fs::create_dir_all(&folder).expect("folder");
+        let (vm, bus) = (VmState::default(), RpcBus::default());
+        let service = TaskService {
+            tasks_dir: dir.join("tasks"),
+            vm: &vm,
+            bus: &bus,
+        };
+
+        let task = service.create(new_task("  ", None)).expect("create");
+        assert_eq!(task.title, "New Task");
+
+        let bound = service.bind_folder(&task.id, folder.to_str().unwrap()).expect("bind");
+        let canonical = std::fs::canonicalize(&folder).unwrap();
+        assert_eq!(bound.working_folder.as_deref(), canonical.to_str());
+        service
+            .bind_folder(&task.id, canonical.to_str().unwrap())
+            .expect("same folder again");
+        let Err(error) = service.bind_folder(&task.id, dir.to_str().unwrap()) else {
+            panic!("binding another folder should fail");
+        };
+        assert!(error.contains("locked"));
+
+        assert_eq!(service.prompt(&task.id, "hi").expect_err("no vm"), "VM is not running");
+
+        let created = service
+            .create(new_task("Relative", Some(format!("{}/../project", folder.display()))))
+            .expect("create bound");
+        assert_eq!(created.working_folder.as_deref(), canonical.to_str());
+        let missing = new_task("Missing", Some(dir.join("missing").to_string_lossy().to_string()));
+        assert!(service.create(missing).is_err());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn isolated_workspaces_sit_outside_the_task_dir_and_are_dropped_when_the_save_fails() {
+        let dir = temp_dir();
+        let folder = dir.join("project");
+        std::fs::create_dir_all(&folder).expect("folder");
+        std::fs::write(folder.join("notes.txt"), "hello").expect("write");
+        let (vm, bus) = (VmState::default(), RpcBus::default());
+        let service = TaskService {
+            tasks_dir: dir.join("tasks"),
+            vm: &vm,
+            bus: &bus,
+        };
+
+        let mut task = service.create(new_task("Isolated", None)).expect("create");
+        task.isolated = Some(true);
+        task.working_folder = Some(folder.to_string_lossy().to_string());
+        task.schedule = Some(TaskSchedule {
+            cron: "whenever".to_string(),
+            prompt: "hi".to_string(),
+            enabled: true,
+            utc_offset_minutes: 0,
+        });
+        let workspace = isolation::task_workspace_dir(&service.tasks_dir, &task.id);
+
+        service.save(task.clone()).expect_err("invalid schedule");
+        assert!(!workspace.exists());
+
+        task.schedule = None;
+        service.save(task.clone()).expect("bind");
+        let stored = service.load(&task.id).expect("load").isolation.expect("isolation");
+        assert_eq!(Path::new(&stored.folder), workspace);
+        assert!(!workspace.starts_with(task_store::task_dir(&service.tasks_dir, &task.id)));
+        assert_eq!(std::fs::read_to_string(workspace.join("notes.txt")).unwrap(), "hello");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,194 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Keeps `task.json` bounded for long-lived tasks; older transitions are dropped first.
+pub const MAX_STATUS_HISTORY: usize = 100;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskStatus {
+    Draft,
+    Idle,
+    Running,
+    WaitingForApproval,
+    Completed,
+    Failed,
+    Archived,
+}
+
+impl TaskStatus {
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw {
+            "draft" => Some(Self::Draft),
+            "idle" => Some(Self::Idle),
+            "running" => Some(Self::Running),
+            "waiting_for_approval" => Some(Self::WaitingForApproval),
+            "completed" => Some(Self::Completed),
+            "failed" => Some(Self::Failed),
+            "archived" => Some(Self::Archived),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Draft => "draft",
+            Self::Idle => "idle",
+            Self::Running => "running",
+            Self::WaitingForApproval => "waiting_for_approval",
+            Self::Completed => "completed",
+            Self::Failed => "failed",
+            Self::Archived => "archived",
+        }
+    }
+
+    /// Whether a task may move from `self` to `next`. Staying in the same status is always allowed,
+    /// and any task can be archived (archiving replaces deletion).
+    pub fn can_transition_to(self, next: Self) -> bool {
+        use TaskStatus::{Archived, Completed, Draft, Failed, Idle, Running, WaitingForApproval};
+
+        self == next
+            || next == Archived
+            || match self {
+                Draft => matches!(next, Idle | Running),
+                Idle | Completed | Failed => matches!(next, Idle | Running | Completed | Failed),
+                Running => matches!(next, Idle | WaitingForApproval | Completed | Failed),
+                WaitingForApproval => matches!(next, Running | Idle | Completed | Failed),
+                Archived => next == Idle,
+            }
+    }
+}
+
+/// One status change, recorded by the host when it is persisted.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusTransition {
+    /// `None` for the status a task was created with.
+    pub from: Option<TaskStatus>,
+    pub to: TaskStatus,
+    /// ISO 8601 UTC.
+    pub at: String,
+}
+
+/// Maps guest RPC traffic to status changes. Raw agent output carries no task id, so the
+/// tracker remembers which task the guest has switched to.
+#[derive(Default)]
+pub struct RpcStatusTracker {
+    active_task: Mutex<Option<String>>,
+    reported: Mutex<HashMap<String, TaskStatus>>,
+}
+
+impl RpcStatusTracker {
+    pub fn reset(&self, task_id: Option<&str>) {
+        *self.active_task.lock().unwrap() = task_id.map(str::to_string);
+        self.reported.lock().unwrap().clear();
+    }
+
+    /// The task and status an RPC line implies, if any.
+    pub fn observe(&self, line: &str) -> Option<(String, TaskStatus)> {
+        let payload: Value = serde_json::from_str(line).ok()?;
+        let kind = payload.get("type").and_then(Value::as_str)?;
+        let mut active_task = self.active_task.lock().unwrap();
+
+        if kind == "event" {
+            let task_id = payload.get("taskId").and_then(Value::as_str)?;
+            let status = match payload.get("event").and_then(Value::as_str)? {
+                "task_switch_started" | "task_ready" => {
+                    *active_task = Some(task_id.to_string());
+                    return None;
+                }
+                "agent_output" => TaskStatus::Running,
+                "agent_end" => TaskStatus::Completed,
+                "task_error" => TaskStatus::Failed,
+                _ => return None,
+            };
+            return Some((task_id.to_string(), status));
+        }
+
+        let status = match kind {
+            "agent_start" => TaskStatus::Running,
+            "extension_ui_request" if is_blocking_ui_request(&payload) => TaskStatus::WaitingForApproval,
+            _ => return None,
+        };
+        active_task.clone().map(|task_id| (task_id, status))
+    }
+
+    /// Like [`Self::observe`], but only reports a status that differs from the last one reported
+    /// for the task, so streaming output doesn't rewrite `task.json` per chunk.
+    pub fn observe_change(&self, line: &str) -> Option<(String, TaskStatus)> {
+        let (task_id, status) = self.observe(line)?;
+        let mut reported = self.reported.lock().unwrap();
+        if reported.get(&task_id) == Some(&status) {
+            return None;
+        }
+        reported.insert(task_id.clone(), status);
+        Some((task_id, status))
+    }
+}
+
+/// Dialog requests wait for the user; fire-and-forget ones (e.g. `notify`) don't.
+fn is_blocking_ui_request(payload: &Value) -> bool {
+    matches!(
+        payload.get("method").and_then(Value::as_str),
+        Some("select" | "confirm" | "input" | "editor")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transitions_follow_the_task_lifecycle() {
+        assert!(TaskStatus::Draft.can_transition_to(TaskStatus::Running));
+        assert!(TaskStatus::Running.can_transition_to(TaskStatus::WaitingForApproval));
+        assert!(TaskStatus::Completed.can_transition_to(TaskStatus::Running));
+        assert!(TaskStatus::Archived.can_transition_to(TaskStatus::Idle));
+        assert!(TaskStatus::Running.can_transition_to(TaskStatus::Archived));
+        assert!(!TaskStatus::Draft.can_transition_to(TaskStatus::Completed));
+        assert!(!TaskStatus::Archived.can_transition_to(TaskStatus::Running));
+        assert!(!TaskStatus::Completed.can_transition_to(TaskStatus::Draft));
+        assert_eq!(
+            TaskStatus::parse("waiting_for_approval"),
+            Some(TaskStatus::WaitingForApproval)
+        );
+        assert_eq!(TaskStatus::parse("done"), None);
+    }
+
+    #[test]
+    fn rpc_lines_drive_statuses_for_the_active_task() {
+        let tracker = RpcStatusTracker::default();
+
+        assert_eq!(tracker.observe(r#"{"type":"agent_start"}"#), None);
+        assert_eq!(
+            tracker.observe(r#"{"type":"event","event":"task_ready","taskId":"t1","payload":{}}"#),
+            None
+        );
+        assert_eq!(
+            tracker.observe(r#"{"type":"agent_start"}"#),
+            Some(("t1".to_string(), TaskStatus::Running))
+        );
+        assert_eq!(
+            tracker.observe(r#"{"type":"extension_ui_request","id":"1","method":"confirm"}"#),
+            Some(("t1".to_string(), TaskStatus::WaitingForApproval))
+        );
+        assert_eq!(
+            tracker.observe(r#"{"type":"extension_ui_request","id":"2","method":"notify"}"#),
+            None
+        );
+        assert_eq!(
+            tracker.observe(r#"{"type":"event","event":"task_error","taskId":"t2","payload":{}}"#),
+            Some(("t2".to_string(), TaskStatus::Failed))
+        );
+        assert_eq!(tracker.observe("not json"), None);
+
+        let output = r#"{"type":"event","event":"agent_output","taskId":"t1","payload":{"chunk":"a"}}"#;
+        assert_eq!(
+            tracker.observe_change(output),
+            Some(("t1".to_string(), TaskStatus::Running))
+        );
+        assert_eq!(tracker.observe_change(output), None);
+    }
+}
@@ -1,19 +1,40 @@
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::path::{Path, PathBuf};
-
-#[derive(Serialize, Deserialize, Clone)]
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::conversation::{Conversation, MessageRole};
+use crate::conversation_log;
+use crate::isolation::{self, TaskIsolation};
+use crate::persist;
+use crate::pi_session;
+use crate::scheduler::{CronSchedule, TaskSchedule};
+use crate::task_index;
+use crate::task_status::{StatusTransition, TaskStatus, MAX_STATUS_HISTORY};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct TaskMount {
     pub path: String,
     pub mode: String,
 }
 
+/// Version written by this build. Files without `schemaVersion` are version 0.
+pub const TASK_SCHEMA_VERSION: u64 = 2;
+
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct TaskMetadata {
+    /// Stamped by the host on save; clients may leave it out.
+    #[serde(default)]
+    pub schema_version: u64,
     pub id: String,
     pub title: String,
-    pub status: String,
+    pub status: TaskStatus,
+    /// Recorded by the host whenever `status` changes, oldest first; clients can't rewrite it.
+    #[serde(default)]
+    pub status_history: Vec<StatusTransition>,
     pub created_at: String,
     pub updated_at: String,
     pub session_file: Option<String>,
@@ -23,10 +44,44 @@ pub struct TaskMetadata {
     pub model: Option<String>,
     pub thinking_level: Option<String>,
     pub connectors_enabled: Option<Vec<String>>,
+    /// Branch checked out in the working folder when it was bound (git repos only).
+    pub git_branch: Option<String>,
+    /// HEAD commit of the working folder when it was bound (git repos only).
+    pub git_head: Option<String>,
+    /// Requested by the client: run the task against an isolated copy of the working folder.
+    pub isolated: Option<bool>,
+    /// Set by the host at first bind when `isolated` is requested.
+    pub isolation: Option<TaskIsolation>,
+    /// Recurring prompt run by the host scheduler.
+    #[serde(default)]
+    pub schedule: Option<TaskSchedule>,
+}
+
+/// A task folder whose `task.json` can't be read, reported instead of failing the whole listing.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct QuarantinedTask {
+    pub task_id: String,
+    pub path: String,
+    pub error: String,
+}
+
+pub struct TaskScan {
+    pub tasks: Vec<TaskMetadata>,
+    pub quarantined: Vec<QuarantinedTask>,
 }
 
 pub const TASK_OUTPUTS_DIR: &str = "outputs";
 pub const TASK_UPLOADS_DIR: &str = "uploads";
+pub const TASK_SESSIONS_DIR: &str = "sessions";
+/// Isolated working folders, one per task; see [`isolation::task_workspace_dir`].
+pub const TASK_WORKSPACES_DIR: &str = "workspaces";
+/// Archived tasks, laid out like the tasks dir itself (task folders plus `sessions/` and `workspaces/`).
+pub const TASK_ARCHIVE_DIR: &str = ".archive";
+/// Archived tasks are purged automatically once they have been archived this long.
+pub const ARCHIVE_RETENTION_DAYS: i64 = 30;
+
+static ID_COUNTER: AtomicUsize = AtomicUsize::new(0);
 
 pub fn task_dir(tasks_dir: &Path, task_id: &str) -> PathBuf {
     tasks_dir.join(task_id)
@@ -40,6 +95,14 @@ pub fn task_uploads_dir(tasks_dir: &Path, task_id: &str) -> PathBuf {
     task_dir(tasks_dir, task_id).join(TASK_UPLOADS_DIR)
 }
 
+pub fn task_session_dir(tasks_dir: &Path, task_id: &str) -> PathBuf {
+    tasks_dir.join(TASK_SESSIONS_DIR).join(task_id)
+}
+
+pub fn archive_dir(tasks_dir: &Path) -> PathBuf {
+    tasks_dir.join(TASK_ARCHIVE_DIR)
+}
+
 pub fn ensure_task_artifact_dirs(tasks_dir: &Path, task_id: &str) -> Result<(), String> {
     std::fs::create_dir_all(task_outputs_dir(tasks_dir, task_id)).map_err(|error| error.to_string())?;
     std::fs::create_dir_all(task_uploads_dir(tasks_dir, task_id)).map_err(|error| error.to_string())?;
@@ -55,14 +118,78 @@ pub fn load_task(tasks_dir: &Path, task_id: &str) -> Result<Option<TaskMetadata>
     }
 
     let content = std::fs::read_to_string(&task_path).map_err(|error| error.to_string())?;
-    let task: TaskMetadata = serde_json::from_str(&content).map_err(|error| error.to_string())?;
-    Ok(Some(task))
+    parse_task(&content).map(Some)
+}
+
+/// Parses a `task.json`, upgrading older schema versions in memory. The upgraded form is
+/// written back by the next save.
+pub fn parse_task(raw: &str) -> Result<TaskMetadata, String> {
+    let value: Value = serde_json::from_str(raw).map_err(|error| format!("Invalid task JSON: {error}"))?;
+    let value = migrate_task(value)?;
+    serde_json::from_value(value).map_err(|error| format!("Invalid task: {error}"))
 }
 
+/// Upgrades raw task metadata to [`TASK_SCHEMA_VERSION`], one version at a time.
+fn migrate_task(mut value: Value) -> Result<Value, String> {
+    let Some(object) = value.as_object_mut() else {
+        return Err("Invalid task: expected an object".to_string());
+    };
+
+    let mut version = match object.get("schemaVersion") {
+        None => 0,
+        Some(raw) => raw
+            .as_u64()
+            .ok_or_else(|| "Invalid task: schemaVersion must be a number".to_string())?,
+    };
+
+    if version > TASK_SCHEMA_VERSION {
+        return Err(format!(
+            "Task schemaVersion {version} is newer than supported ({TASK_SCHEMA_VERSION})"
+        ));
+    }
+
+    while version < TASK_SCHEMA_VERSION {
+        match version {
+            // v0 predates versioning; v1 only adds the version field.
+            0 => {}
+            // v2 types `status`; free-form values from older clients map onto the closest state.
+            1 => {
+                let legacy = object.get("status").and_then(Value::as_str).unwrap_or_default();
+                let status = TaskStatus::parse(legacy).unwrap_or(match legacy {
+                    "blocked" => TaskStatus::WaitingForApproval,
+                    "done" => TaskStatus::Completed,
+                    _ => TaskStatus::Idle,
+                });
+                object.insert("status".to_string(), Value::from(status.as_str()));
+            }
+            _ => unreachable!("missing task migration from v{version}"),
+        }
+        version += 1;
+    }
+
+    object.insert("schemaVersion".to_string(), Value::from(version));
+    Ok(value)
+}
+
+/// All tasks, most recently updated first, served from the task index.
 pub fn list_tasks(tasks_dir: &Path) -> Result<Vec<TaskMetadata>, String> {
+    match task_index::list_all(tasks_dir) {
+        Ok(tasks) => Ok(tasks),
+        Err(error) => {
+            eprintln!("[rust:task-index] list failed, scanning disk: {error}");
+            task_index::invalidate(tasks_dir);
+            scan_tasks(tasks_dir).map(|scan| scan.tasks)
+        }
+    }
+}
+
+/// Reads every `task.json` under `tasks_dir`. Source of truth for rebuilding the index.
+/// Unreadable tasks are set aside in `quarantined` rather than failing the scan.
+pub fn scan_tasks(tasks_dir: &Path) -> Result<TaskScan, String> {
     std::fs::create_dir_all(tasks_dir).map_err(|error| error.to_string())?;
 
     let mut tasks = Vec::new();
+    let mut quarantined = Vec::new();
 
     for entry in std::fs::read_dir(tasks_dir).map_err(|error| error.to_string())? {
         let entry = entry.map_err(|error| error.to_string())?;
@@ -75,20 +202,53 @@ pub fn list_tasks(tasks_dir: &Path) -> Result<Vec<TaskMetadata>, String> {
             continue;
         };
 
-        if let Some(task) = load_task(tasks_dir, task_id)? {
-            tasks.push(task);
+        match load_task(tasks_dir, task_id) {
+            Ok(Some(task)) => tasks.push(task),
+            Ok(None) => {}
+            Err(error) => quarantined.push(QuarantinedTask {
+                task_id: task_id.to_string(),
+                path: path.join("task.json").to_string_lossy().to_string(),
+                error,
+            }),
         }
     }
 
     tasks.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+    quarantined.sort_by(|a, b| a.task_id.cmp(&b.task_id));
 
-    Ok(tasks)
+    Ok(TaskScan { tasks, quarantined })
 }
 
+/// Creates or updates a task. An existing task keeps its stored status: only [`set_status`],
+/// [`archive_task`] and [`restore_task`] move it, so a stale client copy can't undo them.
 pub fn upsert_task(tasks_dir: &Path, task: &TaskMetadata) -> Result<(), String> {
     std::fs::create_dir_all(tasks_dir).map_err(|error| error.to_string())?;
 
+    let task_folder = task_dir(tasks_dir, &task.id);
+    let task_path = task_folder.join("task.json");
+    let _lock = persist::lock(&task_path)?;
+
+    if let Some(schedule) = &task.schedule {
+        CronSchedule::parse(&schedule.cron)?;
+    }
+
+    let mut task = task.clone();
+    task.schema_version = TASK_SCHEMA_VERSION;
     let existing = load_task(tasks_dir, &task.id)?;
+    match &existing {
+        Some(existing_task) => {
+            task.status = existing_task.status;
+            task.status_history.clone_from(&existing_task.status_history);
+        }
+        None if task.status == TaskStatus::Archived => {
+            return Err(format!("Task {} can't be created archived", task.id));
+        }
+        None => {
+            task.status_history = Vec::new();
+            record_transition(&mut task, None)?;
+        }
+    }
+
     if let Some(existing_task) = existing {
         if let Some(existing_folder) = existing_task.working_folder.as_deref() {
             if task.working_folder.as_deref() != Some(existing_folder) {
@@ -97,69 +257,436 @@ pub fn upsert_task(tasks_dir: &Path, task: &TaskMetadata) -> Result<(), String>
                 );
             }
         }
+
+        // Git details and isolation are recorded once at bind time; clients that don't know them must not clear them.
+        if task.git_head.is_none() && task.git_branch.is_none() {
+            task.git_branch = existing_task.git_branch;
+            task.git_head = existing_task.git_head;
+        }
+        if task.isolation.is_none() {
+            task.isolation = existing_task.isolation;
+        }
     }
 
-    let task_folder = task_dir(tasks_dir, &task.id);
-    std::fs::create_dir_all(&task_folder).map_err(|error| error.to_string())?;
     ensure_task_artifact_dirs(tasks_dir, &task.id)?;
+    write_task(tasks_dir, &task_path, &task)
+}
 
-    let task_path = task_folder.join("task.json");
+/// Moves an existing task to `status`, as driven by the runtime. Returns the updated task, or
+/// `None` when the task doesn't exist or already has that status.
+pub fn set_status(tasks_dir: &Path, task_id: &str, status: TaskStatus) -> Result<Option<TaskMetadata>, String> {
+    let task_path = task_dir(tasks_dir, task_id).join("task.json");
+    let _lock = persist::lock(&task_path)?;
+
+    let Some(mut task) = load_task(tasks_dir, task_id)? else {
+        return Ok(None);
+    };
+    if task.status == status {
+        return Ok(None);
+    }
+
+    let previous = task.status;
+    task.status = status;
+    record_transition(&mut task, Some(previous))?;
+    task.updated_at
+        .clone_from(&task.status_history[task.status_history.len() - 1].at);
+
+    write_task(tasks_dir, &task_path, &task)?;
+    Ok(Some(task))
+}
+
+/// Rejects transitions the lifecycle doesn't allow and appends allowed changes to the history.
+fn record_transition(task: &mut TaskMetadata, previous: Option<TaskStatus>) -> Result<(), String> {
+    if let Some(previous) = previous {
+        if previous == task.status {
+            return Ok(());
+        }
+        if !previous.can_transition_to(task.status) {
+            return Err(format!(
+                "Task {} can't move from {} to {}",
+                task.id,
+                previous.as_str(),
+                task.status.as_str()
+            ));
+        }
+    }
+
+    task.status_history.push(StatusTransition {
+        from: previous,
+        to: task.status,
+        at: now_timestamp(),
+    });
+    let overflow = task.status_history.len().saturating_sub(MAX_STATUS_HISTORY);
+    task.status_history.drain(..overflow);
+    Ok(())
+}
+
+pub fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| i64::try_from(elapsed.as_millis()).unwrap_or(i64::MAX))
+}
+
+fn now_timestamp() -> String {
+    task_index::format_timestamp_ms(now_ms())
+}
+
+fn write_task(tasks_dir: &Path, task_path: &Path, task: &TaskMetadata) -> Result<(), String> {
     let content = serde_json::to_string_pretty(task).map_err(|error| error.to_string())?;
-    std::fs::write(&task_path, content).map_err(|error| error.to_string())?;
+    persist::write_atomic(task_path, content.as_bytes())?;
 
+    sync_index(tasks_dir, task_index::upsert(tasks_dir, task));
     Ok(())
 }
 
-pub fn delete_task(tasks_dir: &Path, task_id: String) -> Result<(), String> {
-    let task_folder = tasks_dir.join(task_id);
+/// `task.json` is the source of truth; an index that failed to update is dropped and rebuilt on next read.
+fn sync_index(tasks_dir: &Path, result: Result<(), String>) {
+    if let Err(error) = result {
+        eprintln!("[rust:task-index] update failed, invalidating index: {error}");
+        task_index::invalidate(tasks_dir);
+    }
+}
 
+/// Moves a task out of the task list into [`archive_dir`], marking it archived. Outputs,
+/// uploads, conversation and the guest session move with it so [`restore_task`] is lossless.
+/// Re-archiving an id replaces its previous archive.
+pub fn archive_task(tasks_dir: &Path, task_id: &str) -> Result<(), String> {
+    let task_folder = task_dir(tasks_dir, task_id);
     if task_folder.exists() {
-        std::fs::remove_dir_all(task_folder).map_err(|error| error.to_string())?;
+        let archive = archive_dir(tasks_dir);
+        if task_dir(&archive, task_id).exists() {
+            purge_archived_task(tasks_dir, task_id)?;
+        }
+
+        move_task(tasks_dir, &archive, task_id)?;
+        // A quarantined task is archived as-is; it just can't record the transition.
+        if let Err(error) = update_moved_task(tasks_dir, &archive, task_id, TaskStatus::Archived) {
+            eprintln!("[rust:task-store] archived {task_id} without updating task.json: {error}");
+        }
     }
 
+    sync_index(tasks_dir, task_index::remove(tasks_dir, task_id));
     Ok(())
 }
 
-pub fn delete_all_tasks(tasks_dir: &Path) -> Result<(), String> {
+/// Archives every task except `keep` (the one mounted in a running VM, if any).
+pub fn archive_all_tasks(tasks_dir: &Path, keep: Option<&str>) -> Result<(), String> {
     if !tasks_dir.exists() {
         return Ok(());
     }
 
     for entry in std::fs::read_dir(tasks_dir).map_err(|error| error.to_string())? {
         let entry = entry.map_err(|error| error.to_string())?;
-        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !entry.path().is_dir()
+            || name.starts_with('.')
+            || name == TASK_SESSIONS_DIR
+            || name == TASK_WORKSPACES_DIR
+            || Some(name.as_str()) == keep
+        {
+            continue;
+        }
+
+        archive_task(tasks_dir, &name)?;
+    }
+
+    Ok(())
+}
+
+/// Archived tasks, most recently archived first. Unreadable ones are listed as quarantined.
+pub fn list_archived_tasks(tasks_dir: &Path) -> Result<TaskScan, String> {
+    scan_tasks(&archive_dir(tasks_dir))
+}
+
+/// Moves an archived task back into the task list as idle.
+pub fn restore_task(tasks_dir: &Path, task_id: &str) -> Result<TaskMetadata, String> {
+    let archive = archive_dir(tasks_dir);
+    if !task_dir(&archive, task_id).exists() {
+        return Err(format!("Archived task not found: {task_id}"));
+    }
+    if task_dir(tasks_dir, task_id).exists() {
+        return Err(format!("A task with id {task_id} already exists"));
+    }
+
+    move_task(&archive, tasks_dir, task_id)?;
+    let task = update_moved_task(&archive, tasks_dir, task_id, TaskStatus::Idle)?;
+
+    sync_index(tasks_dir, task_index::upsert(tasks_dir, &task));
+    Ok(task)
+}
+
+/// Permanently deletes an archived task, including its worktree and task branch.
+pub fn purge_archived_task(tasks_dir: &Path, task_id: &str) -> Result<(), String> {
+    let archive = archive_dir(tasks_dir);
+
+    // A quarantined task must stay purgeable, so an unreadable task.json isn't an error here.
+    if let Some(isolation) = load_task(&archive, task_id)
+        .ok()
+        .flatten()
+        .and_then(|task| task.isolation)
+    {
+        isolation::remove(&archive, task_id, &isolation);
+    }
+
+    for folder in [
+        task_dir(&archive, task_id),
+        task_session_dir(&archive, task_id),
+        isolation::task_workspace_dir(&archive, task_id),
+    ] {
+        if folder.exists() {
+            std::fs::remove_dir_all(folder).map_err(|error| error.to_string())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Permanently deletes every archived task.
+pub fn purge_all_archived_tasks(tasks_dir: &Path) -> Result<(), String> {
+    for task_id in archived_task_ids(tasks_dir)? {
+        purge_archived_task(tasks_dir, &task_id)?;
+    }
+
+    Ok(())
+}
+
+/// Purges tasks archived more than [`ARCHIVE_RETENTION_DAYS`] before `now_ms`, returning their ids.
+/// Quarantined archives carry no archive time and are left for an explicit purge.
+pub fn purge_expired_archived_tasks(tasks_dir: &Path, now_ms: i64) -> Result<Vec<String>, String> {
+    let cutoff = now_ms - ARCHIVE_RETENTION_DAYS * 86_400_000;
+    let mut purged = Vec::new();
+
+    for task in list_archived_tasks(tasks_dir)?.tasks {
+        let archived_at = task
+            .status_history
+            .iter()
+            .rev()
+            .find(|transition| transition.to == TaskStatus::Archived)
+            .map_or(task.updated_at.as_str(), |transition| transition.at.as_str());
+
+        if task_index::parse_timestamp_ms(archived_at).is_some_and(|archived_at| archived_at < cutoff) {
+            purge_archived_task(tasks_dir, &task.id)?;
+            purged.push(task.id);
+        }
+    }
+
+    Ok(purged)
+}
 
-        if path.is_dir() {
-            std::fs::remove_dir_all(&path).map_err(|error| error.to_string())?;
-        } else {
-            std::fs::remove_file(&path).map_err(|error| error.to_string())?;
+fn archived_task_ids(tasks_dir: &Path) -> Result<Vec<String>, String> {
+    let archive = archive_dir(tasks_dir);
+    if !archive.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut task_ids = Vec::new();
+    for entry in std::fs::read_dir(&archive).map_err(|error| error.to_string())? {
+        let entry = entry.map_err(|error| error.to_string())?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if entry.path().is_dir() && name != TASK_SESSIONS_DIR && name != TASK_WORKSPACES_DIR {
+            task_ids.push(name);
         }
     }
 
+    Ok(task_ids)
+}
+
+/// Moves a task folder and its guest session between two tasks-dir-shaped roots.
+fn move_task(from_tasks_dir: &Path, to_tasks_dir: &Path, task_id: &str) -> Result<(), String> {
+    std::fs::create_dir_all(to_tasks_dir.join(TASK_SESSIONS_DIR)).map_err(|error| error.to_string())?;
+    std::fs::rename(task_dir(from_tasks_dir, task_id), task_dir(to_tasks_dir, task_id))
+        .map_err(|error| format!("Failed to move task {task_id}: {error}"))?;
+
+    let session = task_session_dir(from_tasks_dir, task_id);
+    if session.exists() {
+        std::fs::rename(&session, task_session_dir(to_tasks_dir, task_id))
+            .map_err(|error| format!("Failed to move session for task {task_id}: {error}"))?;
+    }
+
+    let workspace = isolation::task_workspace_dir(from_tasks_dir, task_id);
+    if workspace.exists() {
+        std::fs::create_dir_all(to_tasks_dir.join(TASK_WORKSPACES_DIR)).map_err(|error| error.to_string())?;
+        std::fs::rename(&workspace, isolation::task_workspace_dir(to_tasks_dir, task_id))
+            .map_err(|error| format!("Failed to move workspace for task {task_id}: {error}"))?;
+    }
+
     Ok(())
 }
 
+/// Records the status change of a task that just moved and points its isolation at the new place.
+fn update_moved_task(
+    from_tasks_dir: &Path,
+    to_tasks_dir: &Path,
+    task_id: &str,
+    status: TaskStatus,
+) -> Result<TaskMetadata, String> {
+    let task_path = task_dir(to_tasks_dir, task_id).join("task.json");
+    let _lock = persist::lock(&task_path)?;
+
+    let mut task = load_task(to_tasks_dir, task_id)?.ok_or_else(|| format!("Task not found: {task_id}"))?;
+    if task.status != status {
+        let previous = task.status;
+        task.status = status;
+        record_transition(&mut task, Some(previous))?;
+        task.updated_at
+            .clone_from(&task.status_history[task.status_history.len() - 1].at);
+    }
+    task.isolation = task
+        .isolation
+        .map(|isolation| isolation::relocate(from_tasks_dir, to_tasks_dir, task_id, &isolation));
+
+    let content = serde_json::to_string_pretty(&task).map_err(|error| error.to_string())?;
+    persist::write_atomic(&task_path, content.as_bytes())?;
+    Ok(task)
+}
+
+/// Creates a copy of `task_id` whose conversation ends before `message_index`, so a task that
+/// went the wrong way can be retried from that point. Outputs and uploads are copied as they are
+/// now, and the pi session is cut at the same turn so the runtime resumes from there.
+///
+/// The fork starts idle with no working folder; the caller binds one if it wants to.
+pub fn fork_task(tasks_dir: &Path, task_id: &str, message_index: usize) -> Result<TaskMetadata, String> {
+    let source = load_task(tasks_dir, task_id)?.ok_or_else(|| format!("Task not found: {task_id}"))?;
+    let mut conversation = conversation_log::load(&task_dir(tasks_dir, task_id))?;
+    let message_count = conversation
+        .as_ref()
+        .map_or(0, |conversation| conversation.messages.len());
+    if message_index > message_count {
+        return Err(format!(
+            "Message index {message_index} is past the end of the conversation ({message_count} messages)"
+        ));
+    }
+    if let Some(conversation) = &mut conversation {
+        conversation.messages.truncate(message_index);
+    }
+
+    let id = new_task_id(task_id);
+    let source_session = task_session_dir(tasks_dir, task_id).join(pi_session::SESSION_FILE);
+    let forks_session = source_session.exists();
+    let now = now_timestamp();
+    let task = TaskMetadata {
+        id: id.clone(),
+        title: format!("{} (fork)", source.title),
+        status: TaskStatus::Idle,
+        status_history: Vec::new(),
+        created_at: now.clone(),
+        updated_at: now,
+        session_file: source
+            .session_file
+            .as_deref()
+            .filter(|_| forks_session)
+            .map(|session_file| session_file.replace(task_id, &id)),
+        working_folder: None,
+        git_branch: None,
+        git_head: None,
+        isolation: None,
+        // A fork is a one-off branch; it shouldn't repeat its source's scheduled runs.
+        schedule: None,
+        ..source
+    };
+    upsert_task(tasks_dir, &task)?;
+
+    let result = (|| {
+        if let Some(conversation) = &conversation {
+            save_conversation(tasks_dir, &id, &conversation.to_json()?)?;
+        }
+
+        for (from, to) in [
+            (task_outputs_dir(tasks_dir, task_id), task_outputs_dir(tasks_dir, &id)),
+            (task_uploads_dir(tasks_dir, task_id), task_uploads_dir(tasks_dir, &id)),
+        ] {
+            if from.exists() {
+                // copy_tree creates the destination itself.
+                std::fs::remove_dir(&to).map_err(|error| error.to_string())?;
+                isolation::copy_tree(&from, &to)?;
+            }
+        }
+
+        if forks_session {
+            let raw = std::fs::read_to_string(&source_session).map_err(|error| error.to_string())?;
+            let user_turns = conversation
+                .iter()
+                .flat_map(|conversation| &conversation.messages)
+                .filter(|message| message.role == MessageRole::User)
+                .count();
+            let session_dir = task_session_dir(tasks_dir, &id);
+            std::fs::create_dir_all(&session_dir).map_err(|error| error.to_string())?;
+            persist::write_atomic(
+                &session_dir.join(pi_session::SESSION_FILE),
+                pi_session::truncate_at_user_turn(&raw, user_turns, &id).as_bytes(),
+            )?;
+        }
+
+        Ok(())
+    })();
+
+    if let Err(error) = result {
+        discard_task(tasks_dir, &id);
+        return Err(error);
+    }
+
+    Ok(task)
+}
+
+/// A fresh task id in UUID form, derived from `seed` (e.g. the task it was copied from) plus the
+/// clock, process and a counter, so repeated copies never collide.
+pub fn new_task_id(seed: &str) -> String {
+    let counter = ID_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos());
+    let hash = persist::hex_sha256(format!("{seed}:{nanos}:{}:{counter}", std::process::id()).as_bytes());
+
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hash[0..8],
+        &hash[8..12],
+        &hash[12..16],
+        &hash[16..20],
+        &hash[20..32]
+    )
+}
+
+/// Removes a task that failed to be created part-way (import, fork) so it doesn't linger in the list.
+pub fn discard_task(tasks_dir: &Path, task_id: &str) {
+    if let Err(error) = archive_task(tasks_dir, task_id).and_then(|()| purge_archived_task(tasks_dir, task_id)) {
+        eprintln!("[rust:task-store] failed to discard partial task {task_id}: {error}");
+    }
+}
+
 pub fn save_conversation(tasks_dir: &Path, task_id: &str, conversation_json: &str) -> Result<(), String> {
     let task_folder = tasks_dir.join(task_id);
     if !task_folder.exists() {
         return Err(format!("Task folder does not exist: {task_id}"));
     }
 
-    let conv_path = task_folder.join("conversation.json");
-    std::fs::write(&conv_path, conversation_json).map_err(|e| e.to_string())?;
+    let conversation = Conversation::parse(conversation_json)?;
+    {
+        let _lock = persist::lock(&task_folder.join(conversation_log::CONVERSATION_SNAPSHOT_FILE))?;
+        conversation_log::save(&task_folder, &conversation)?;
+    }
+
+    sync_index(
+        tasks_dir,
+        task_index::index_conversation(tasks_dir, task_id, &conversation),
+    );
     Ok(())
 }
 
 pub fn load_conversation(tasks_dir: &Path, task_id: &str) -> Result<Option<String>, String> {
     let task_folder = tasks_dir.join(task_id);
-    let conv_path = task_folder.join("conversation.json");
 
-    if !conv_path.exists() {
-        return Ok(None);
+    match conversation_log::load(&task_folder) {
+        Ok(conversation) => conversation.map(|conversation| conversation.to_json()).transpose(),
+        Err(error) => {
+            // Hand back an unreadable snapshot untouched so nothing is lost.
+            eprintln!("[rust:task-store] conversation for {task_id} failed to load: {error}");
+            let snapshot_path = task_folder.join(conversation_log::CONVERSATION_SNAPSHOT_FILE);
+            std::fs::read_to_string(snapshot_path)
+                .map(Some)
+                .map_err(|e| e.to_string())
+        }
     }
-
-    let content = std::fs::read_to_string(&conv_path).map_err(|e| e.to_string())?;
-    Ok(Some(content))
 }
 
 #[cfg(test)]
@@ -179,9 +706,11 @@ mod tests {
 
     fn sample_task(id: &str, updated_at: &str) -> TaskMetadata {
         TaskMetadata {
+            schema_version: TASK_SCHEMA_VERSION,
             id: id.to_string(),
             title: "Test".to_string(),
-            status: "idle".to_string(),
+            status: TaskStatus::Idle,
+            status_history: Vec::new(),
             created_at: "2026-02-04T00:00:00Z".to_string(),
             updated_at: updated_at.to_string(),
             session_file: None,
@@ -191,6 +720,11 @@ mod tests {
             model: None,
             thinking_level: None,
             connectors_enabled: None,
+            git_branch: None,
+            git_head: None,
+            isolated: None,
+            isolation: None,
+            schedule: None,
         }
     }
 
@@ -243,6 +777,29 @@ mod tests {
         std::fs::remove_dir_all(&dir).ok();
     }
 
+    #[test]
+    fn upsert_keeps_recorded_git_details() {
+        let dir = temp_dir();
+        let bound = TaskMetadata {
+            git_branch: Some("main".to_string()),
+            git_head: Some("abc123".to_string()),
+            ..sample_task_with_folder("task-1", "2026-02-04T00:00:01Z", Some("/tmp/work"))
+        };
+
+        upsert_task(&dir, &bound).expect("bind");
+        upsert_task(
+            &dir,
+            &sample_task_with_folder("task-1", "2026-02-04T00:00:02Z", Some("/tmp/work")),
+        )
+        .expect("update");
+        let loaded = load_task(&dir, "task-1").expect("load").expect("task");
+
+        assert_eq!(loaded.git_branch.as_deref(), Some("main"));
+        assert_eq!(loaded.git_head.as_deref(), Some("abc123"));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
     #[test]
     fn upsert_rejects_working_folder_change_after_bind() {
         let dir = temp_dir();
@@ -288,31 +845,251 @@ mod tests {
     }
 
     #[test]
-    fn delete_task_removes_dir() {
+    fn archive_and_restore_keep_task_files() {
         let dir = temp_dir();
         let task = sample_task("task-1", "2026-02-04T00:00:01Z");
 
         upsert_task(&dir, &task).expect("upsert");
-        delete_task(&dir, "task-1".to_string()).expect("delete");
+        std::fs::write(task_outputs_dir(&dir, "task-1").join("report.md"), "result").expect("write output");
+        std::fs::create_dir_all(task_session_dir(&dir, "task-1")).expect("create session");
+        archive_task(&dir, "task-1").expect("archive");
+
+        assert!(list_tasks(&dir).expect("list").is_empty());
+        assert!(!task_session_dir(&dir, "task-1").exists());
+        let archived = list_archived_tasks(&dir).expect("list archived").tasks;
+        assert_eq!(archived.len(), 1);
+        assert_eq!(archived[0].status, TaskStatus::Archived);
+
+        let restored = restore_task(&dir, "task-1").expect("restore");
+        assert_eq!(restored.status, TaskStatus::Idle);
+        assert_eq!(list_tasks(&dir).expect("list").len(), 1);
+        assert!(task_session_dir(&dir, "task-1").exists());
+        assert_eq!(
+            std::fs::read_to_string(task_outputs_dir(&dir, "task-1").join("report.md")).expect("read output"),
+            "result"
+        );
+        assert!(restore_task(&dir, "task-1").is_err());
 
-        let tasks = list_tasks(&dir).expect("list");
-        assert!(tasks.is_empty());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn expired_archives_are_purged() {
+        let dir = temp_dir();
+        upsert_task(&dir, &sample_task("task-1", "2026-02-04T00:00:01Z")).expect("upsert");
+        archive_task(&dir, "task-1").expect("archive");
+
+        let day_ms = 86_400_000;
+        assert!(purge_expired_archived_tasks(&dir, now_ms() + day_ms)
+            .expect("purge")
+            .is_empty());
+        let purged =
+            purge_expired_archived_tasks(&dir, now_ms() + (ARCHIVE_RETENTION_DAYS + 1) * day_ms).expect("purge");
+
+        assert_eq!(purged, ["task-1"]);
+        assert!(list_archived_tasks(&dir).expect("list archived").tasks.is_empty());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn upserts_keep_the_stored_status() {
+        let dir = temp_dir();
+        let stale = sample_task("task-1", "2026-02-04T00:00:01Z");
+        upsert_task(&dir, &stale).expect("create");
+        set_status(&dir, "task-1", TaskStatus::Running).expect("run");
+
+        upsert_task(
+            &dir,
+            &TaskMetadata {
+                status: TaskStatus::Archived,
+                ..stale.clone()
+            },
+        )
+        .expect("archive ignored");
+        // A client saving a title edit from the copy it loaded before the run started.
+        upsert_task(
+            &dir,
+            &TaskMetadata {
+                title: "Renamed".to_string(),
+                ..stale
+            },
+        )
+        .expect("rename");
+
+        let task = load_task(&dir, "task-1").expect("load").expect("task");
+        assert_eq!(task.title, "Renamed");
+        assert_eq!(task.status, TaskStatus::Running);
+        assert_eq!(task.status_history.len(), 2);
+
+        let archived = TaskMetadata {
+            status: TaskStatus::Archived,
+            ..sample_task("task-2", "2026-02-04T00:00:01Z")
+        };
+        assert!(upsert_task(&dir, &archived)
+            .expect_err("created archived")
+            .contains("archived"));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn status_changes_are_validated_and_recorded() {
+        let dir = temp_dir();
+        upsert_task(&dir, &sample_task("task-1", "2026-02-04T00:00:01Z")).expect("create");
+
+        let running = set_status(&dir, "task-1", TaskStatus::Running)
+            .expect("run")
+            .expect("changed");
+        assert_eq!(
+            set_status(&dir, "task-1", TaskStatus::Running)
+                .expect("same")
+                .map(|task| task.id),
+            None
+        );
+
+        let Err(error) = set_status(&dir, "task-1", TaskStatus::Draft) else {
+            panic!("running tasks can't go back to draft");
+        };
+        assert!(error.contains("can't move from running to draft"));
+
+        set_status(&dir, "task-1", TaskStatus::Completed).expect("complete");
+        set_status(&dir, "task-1", TaskStatus::Archived).expect("archive");
+
+        let task = load_task(&dir, "task-1").expect("load").expect("task");
+        let history: Vec<(Option<TaskStatus>, TaskStatus)> = task
+            .status_history
+            .iter()
+            .map(|transition| (transition.from, transition.to))
+            .collect();
+        assert_eq!(
+            history,
+            [
+                (None, TaskStatus::Idle),
+                (Some(TaskStatus::Idle), TaskStatus::Running),
+                (Some(TaskStatus::Running), TaskStatus::Completed),
+                (Some(TaskStatus::Completed), TaskStatus::Archived),
+            ]
+        );
+        assert_eq!(running.updated_at, running.status_history[1].at);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn scan_migrates_legacy_tasks_and_quarantines_corrupt_ones() {
+        let dir = temp_dir();
+        for (task_id, content) in [
+            (
+                "legacy",
+                r#"{"id":"legacy","title":"Old","status":"done","createdAt":"a","updatedAt":"b"}"#,
+            ),
+            ("broken", r#"{"id":"broken","title":"#),
+            ("future", r#"{"schemaVersion":99,"id":"future"}"#),
+        ] {
+            std::fs::create_dir_all(dir.join(task_id)).expect("create task dir");
+            std::fs::write(dir.join(task_id).join("task.json"), content).expect("write task");
+        }
+
+        let scan = scan_tasks(&dir).expect("scan");
+
+        assert_eq!(scan.tasks.len(), 1);
+        assert_eq!(scan.tasks[0].schema_version, TASK_SCHEMA_VERSION);
+        assert_eq!(scan.tasks[0].status, TaskStatus::Completed);
+        let quarantined: Vec<&str> = scan.quarantined.iter().map(|task| task.task_id.as_str()).collect();
+        assert_eq!(quarantined, ["broken", "future"]);
+        assert!(scan.quarantined[1].error.contains("newer than supported"));
+
+        assert_eq!(list_tasks(&dir).expect("list").len(), 1);
+        archive_task(&dir, "broken").expect("archive quarantined");
+        assert_eq!(scan_tasks(&dir).expect("rescan").quarantined.len(), 1);
 
         std::fs::remove_dir_all(&dir).ok();
     }
 
     #[test]
-    fn delete_all_tasks_removes_dirs() {
+    fn save_conversation_rejects_corrupt_payload_and_keeps_previous() {
+        let dir = temp_dir();
+        upsert_task(&dir, &sample_task("task-1", "2026-02-04T00:00:01Z")).expect("upsert");
+        save_conversation(&dir, "task-1", r#"{"messages":[]}"#).expect("save");
+
+        let error = save_conversation(&dir, "task-1", r#"{"messages":[{"role":"robot"}]}"#).expect_err("reject");
+        let loaded = load_conversation(&dir, "task-1").expect("load").expect("conversation");
+
+        assert!(error.contains("Invalid conversation"));
+        assert_eq!(loaded, r#"{"schemaVersion":1,"messages":[]}"#);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn archive_all_then_purge_all() {
         let dir = temp_dir();
         let task_one = sample_task("task-1", "2026-02-04T00:00:01Z");
         let task_two = sample_task("task-2", "2026-02-04T00:00:02Z");
 
         upsert_task(&dir, &task_one).expect("upsert one");
         upsert_task(&dir, &task_two).expect("upsert two");
-        delete_all_tasks(&dir).expect("delete all");
+        archive_all_tasks(&dir, Some("task-2")).expect("archive all");
 
         let tasks = list_tasks(&dir).expect("list");
-        assert!(tasks.is_empty());
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].id, "task-2");
+        assert_eq!(list_archived_tasks(&dir).expect("list archived").tasks.len(), 1);
+
+        purge_all_archived_tasks(&dir).expect("purge all");
+        assert!(list_archived_tasks(&dir).expect("list archived").tasks.is_empty());
+        assert!(!task_dir(&archive_dir(&dir), "task-1").exists());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn fork_truncates_conversation_and_session() {
+        let dir = temp_dir();
+        let source = TaskMetadata {
+            session_file: Some("/sessions/task-1/session.json".to_string()),
+            ..sample_task_with_folder("task-1", "2026-02-04T00:00:01Z", Some("/tmp/project"))
+        };
+        upsert_task(&dir, &source).expect("upsert");
+        let conversation = r#"{"messages":[
+            {"role":"user","blocks":[{"type":"text","text":"one"}]},
+            {"role":"assistant","blocks":[{"type":"text","text":"1"}]},
+            {"role":"user","blocks":[{"type":"text","text":"two"}]}
+        ]}"#;
+        save_conversation(&dir, "task-1", conversation).expect("save conversation");
+        std::fs::write(task_outputs_dir(&dir, "task-1").join("notes.md"), "draft").expect("output");
+        std::fs::create_dir_all(task_session_dir(&dir, "task-1")).expect("session dir");
+        std::fs::write(
+            task_session_dir(&dir, "task-1").join(pi_session::SESSION_FILE),
+            [
+                r#"{"type":"session","id":"task-1"}"#,
+                r#"{"type":"message","message":{"role":"user","content":"one"}}"#,
+                r#"{"type":"message","message":{"role":"assistant","content":"1"}}"#,
+                r#"{"type":"message","message":{"role":"user","content":"two"}}"#,
+            ]
+            .join("\n"),
+        )
+        .expect("session");
+
+        let fork = fork_task(&dir, "task-1", 2).expect("fork");
+
+        assert_ne!(fork.id, "task-1");
+        assert_eq!(fork.title, "Test (fork)");
+        assert_eq!(fork.working_folder, None);
+        assert_eq!(fork.session_file, Some(format!("/sessions/{}/session.json", fork.id)));
+        let forked_conversation = conversation_log::load(&task_dir(&dir, &fork.id))
+            .expect("load")
+            .expect("conversation");
+        assert_eq!(forked_conversation.messages.len(), 2);
+        assert_eq!(
+            std::fs::read_to_string(task_outputs_dir(&dir, &fork.id).join("notes.md")).expect("output"),
+            "draft"
+        );
+        let session = std::fs::read_to_string(task_session_dir(&dir, &fork.id).join(pi_session::SESSION_FILE))
+            .expect("forked session");
+        assert_eq!(session.lines().count(), 3);
+        assert!(fork_task(&dir, "task-1", 4).is_err());
 
         std::fs::remove_dir_all(&dir).ok();
     }
@@ -0,0 +1,289 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::isolation;
+use crate::persist;
+use crate::task_index;
+use crate::task_status::TaskStatus;
+use crate::task_store::{self, TaskMetadata, TaskMount};
+
+/// Version written by this build. Files without `schemaVersion` are version 0.
+pub const TEMPLATE_SCHEMA_VERSION: u64 = 1;
+pub const TEMPLATE_FILE: &str = "template.json";
+/// Files in a template's `uploads/` folder are copied into every task created from it.
+pub const TEMPLATE_UPLOADS_DIR: &str = "uploads";
+
+/// A reusable task setup ("run this skill on this folder"). Each template is a folder holding
+/// `template.json` and optional `uploads/`, so a team can share one by copying the folder.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskTemplate {
+    #[serde(default)]
+    pub schema_version: u64,
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Title of created tasks; `{name}`, `{folder}` (working folder name) and `{date}`
+    /// (YYYY-MM-DD) are filled in.
+    pub title_pattern: String,
+    /// Sent as the first message once the task is created.
+    #[serde(default)]
+    pub initial_prompt: Option<String>,
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    pub thinking_level: Option<String>,
+    pub connectors_enabled: Option<Vec<String>>,
+    #[serde(default)]
+    pub mounts: Vec<TaskMount>,
+    /// Names of the files in `uploads/`, filled in on load.
+    #[serde(default, skip_deserializing)]
+    pub uploads: Vec<String>,
+}
+
+/// A task created from a template, with the prompt the client should send to start it.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateInstance {
+    pub task: TaskMetadata,
+    pub initial_prompt: Option<String>,
+}
+
+pub fn template_dir(templates_dir: &Path, template_id: &str) -> PathBuf {
+    templates_dir.join(template_id)
+}
+
+pub fn template_uploads_dir(templates_dir: &Path, template_id: &str) -> PathBuf {
+    template_dir(templates_dir, template_id).join(TEMPLATE_UPLOADS_DIR)
+}
+
+pub fn load_template(templates_dir: &Path, template_id: &str) -> Result<Option<TaskTemplate>, String> {
+    let path = template_dir(templates_dir, template_id).join(TEMPLATE_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let content = std::fs::read_to_string(&path).map_err(|error| error.to_string())?;
+    let mut template: TaskTemplate =
+        serde_json::from_str(&content).map_err(|error| format!("Invalid template {template_id}: {error}"))?;
+    if template.schema_version > TEMPLATE_SCHEMA_VERSION {
+        return Err(format!(
+            "Template {template_id} has schema version {}, newer than supported ({TEMPLATE_SCHEMA_VERSION})",
+            template.schema_version
+        ));
+    }
+
+    // The folder name is the id, so a copied folder doesn't clash with its original.
+    template.id = template_id.to_string();
+    template.uploads = upload_names(&template_uploads_dir(templates_dir, template_id))?;
+    Ok(Some(template))
+}
+
+/// Every readable template, sorted by name. Unreadable ones are logged and left out so one bad
+/// shared file doesn't hide the rest.
+pub fn list_templates(templates_dir: &Path) -> Result<Vec<TaskTemplate>, String> {
+    if !templates_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut templates = Vec::new();
+    for entry in std::fs::read_dir(templates_dir).map_err(|error| error.to_string())? {
+        let entry = entry.map_err(|error| error.to_string())?;
+        if !entry.path().is_dir() {
+            continue;
+        }
+
+        let template_id = entry.file_name().to_string_lossy().to_string();
+        match load_template(templates_dir, &template_id) {
+            Ok(Some(template)) => templates.push(template),
+            Ok(None) => {}
+            Err(error) => eprintln!("[rust:template-store] skipping template {template_id}: {error}"),
+        }
+    }
+
+    templates.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(templates)
+}
+
+pub fn upsert_template(templates_dir: &Path, template: &TaskTemplate) -> Result<(), String> {
+    if template.name.trim().is_empty() {
+        return Err("Template name is required".to_string());
+    }
+    if template.title_pattern.trim().is_empty() {
+        return Err("Template title pattern is required".to_string());
+    }
+
+    let folder = template_dir(templates_dir, &template.id);
+    std::fs::create_dir_all(&folder).map_err(|error| error.to_string())?;
+
+    let mut template = template.clone();
+    template.schema_version = TEMPLATE_SCHEMA_VERSION;
+    template.uploads = Vec::new();
+
+    let path = folder.join(TEMPLATE_FILE);
+    let _lock = persist::lock(&path)?;
+    let content = serde_json::to_string_pretty(&template).map_err(|error| error.to_string())?;
+    persist::write_atomic(&path, content.as_bytes())
+}
+
+pub fn delete_template(templates_dir: &Path, template_id: &str) -> Result<(), String> {
+    let folder = template_dir(templates_dir, template_id);
+    if folder.exists() {
+        std::fs::remove_dir_all(folder).map_err(|error| error.to_string())?;
+    }
+    Ok(())
+}
+
+/// The task a template describes, not yet saved. It gets a fresh id and starts idle.
+pub fn new_task(template: &TaskTemplate, working_folder: Option<String>) -> TaskMetadata {
+    let now = task_index::format_timestamp_ms(task_store::now_ms());
+    let folder_name = working_folder
+        .as_deref()
+        .and_then(|folder| Path::new(folder).file_name())
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    TaskMetadata {
+        schema_version: task_store::TASK_SCHEMA_VERSION,
+        id: task_store::new_task_id(&template.id),
+        title: render_title(&template.title_pattern, &template.name, &folder_name, &now[..10]),
+        status: TaskStatus::Idle,
+        status_history: Vec::new(),
+        created_at: now.clone(),
+        updated_at: now,
+        session_file: None,
+        working_folder,
+        mounts: (!template.mounts.is_empty()).then(|| template.mounts.clone()),
+        provider: template.provider.clone(),
+        model: template.model.clone(),
+        thinking_level: template.thinking_level.clone(),
+        connectors_enabled: template.connectors_enabled.clone(),
+        git_branch: None,
+        git_head: None,
+        isolated: None,
+        isolation: None,
+        schedule: None,
+    }
+}
+
+/// Copies the template's `uploads/` into a task created from it.
+pub fn copy_uploads(templates_dir: &Path, template_id: &str, tasks_dir: &Path, task_id: &str) -> Result<(), String> {
+    let source = template_uploads_dir(templates_dir, template_id);
+    if !source.is_dir() {
+        return Ok(());
+    }
+
+    let destination = task_store::task_uploads_dir(tasks_dir, task_id);
+    if destination.exists() {
+        // copy_tree creates the destination itself.
+        std::fs::remove_dir(&destination).map_err(|error| error.to_string())?;
+    }
+    isolation::copy_tree(&source, &destination)
+}
+
+fn render_title(pattern: &str, name: &str, folder: &str, date: &str) -> String {
+    let title = pattern
+        .replace("{name}", name)
+        .replace("{folder}", folder)
+        .replace("{date}", date);
+    let title = title.trim();
+
+    if title.is_empty() {
+        name.to_string()
+    } else {
+        title.to_string()
+    }
+}
+
+fn upload_names(uploads_dir: &Path) -> Result<Vec<String>, String> {
+    if !uploads_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut names = Vec::new();
+    for entry in std::fs::read_dir(uploads_dir).map_err(|error| error.to_string())? {
+        let entry = entry.map_err(|error| error.to_string())?;
+        names.push(entry.file_name().to_string_lossy().to_string());
+    }
+
+    names.sort();
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    fn temp_dir() -> PathBuf {
+        let suffix = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let counter = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+        std::env::temp_dir().join(format!("piwork-template-store-{suffix}-{counter}"))
+    }
+
+    fn sample_template(id: &str) -> TaskTemplate {
+        TaskTemplate {
+            schema_version: 0,
+            id: id.to_string(),
+            name: "Review".to_string(),
+            description: None,
+            title_pattern: "{name}: {folder} ({date})".to_string(),
+            initial_prompt: Some("Review the documents in this folder.".to_string()),
+            provider: Some("anthropic".to_string()),
+            model: None,
+            thinking_level: Some("high".to_string()),
+            connectors_enabled: None,
+            mounts: Vec::new(),
+            uploads: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn templates_create_tasks_with_their_uploads() {
+        let dir = temp_dir();
+        let (templates_dir, tasks_dir) = (dir.join("templates"), dir.join("tasks"));
+        upsert_template(&templates_dir, &sample_template("review")).expect("upsert");
+        std::fs::create_dir_all(template_uploads_dir(&templates_dir, "review")).expect("uploads dir");
+        std::fs::write(
+            template_uploads_dir(&templates_dir, "review").join("checklist.md"),
+            "- [ ] spelling",
+        )
+        .expect("upload");
+
+        let template = load_template(&templates_dir, "review")
+            .expect("load")
+            .expect("template");
+        assert_eq!(template.uploads, vec!["checklist.md".to_string()]);
+
+        let task = new_task(&template, Some("/home/alice/Q3 report".to_string()));
+        task_store::upsert_task(&tasks_dir, &task).expect("upsert task");
+        copy_uploads(&templates_dir, "review", &tasks_dir, &task.id).expect("copy uploads");
+
+        assert!(task.title.starts_with("Review: Q3 report (20"));
+        assert_eq!(task.thinking_level.as_deref(), Some("high"));
+        assert!(task_store::task_uploads_dir(&tasks_dir, &task.id)
+            .join("checklist.md")
+            .is_file());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn unreadable_templates_are_left_out_of_listings() {
+        let dir = temp_dir();
+        upsert_template(&dir, &sample_template("review")).expect("upsert");
+        std::fs::create_dir_all(template_dir(&dir, "broken")).expect("broken dir");
+        std::fs::write(template_dir(&dir, "broken").join(TEMPLATE_FILE), "{").expect("broken file");
+
+        let templates = list_templates(&dir).expect("list");
+
+        assert_eq!(templates.len(), 1);
+        assert_eq!(templates[0].schema_version, TEMPLATE_SCHEMA_VERSION);
+        assert_eq!(render_title("{folder}", "Review", "", "2026-02-04"), "Review");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
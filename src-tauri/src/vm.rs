@@ -1,12 +1,17 @@
 use serde::{Deserialize, Serialize};
 use std::fmt::Write as _;
 use std::io::{BufRead, BufReader, Write};
-use std::net::TcpStream;
+use std::net::{Shutdown, TcpListener, TcpStream};
 use std::path::{Path, PathBuf};
 use std::process::{Child, Command, Stdio};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
+
+use crate::change_journal::{ChangeWatcherState, FileChange};
+use crate::mount::{self, AttachedMounts, MountInfo, MountRequest};
+use crate::persist::{self, FileLock};
+use crate::rpc_replay::{self, Direction, Recorder};
 use tauri::AppHandle;
 use tauri::Emitter;
 use tauri::Manager;
@@ -14,11 +19,58 @@ use tauri::Manager;
 const RPC_PORT: u16 = 19384;
 const READY_MARKER_TIMEOUT_SECS: u64 = 45;
 const RPC_CONNECT_TIMEOUT_SECS: u64 = 45;
+const VM_MEMORY_MB: u32 = 2048;
 
-#[derive(Default)]
+/// Shared handle to the VM; clones refer to the same instance.
+#[derive(Default, Clone)]
 pub struct VmState {
-    inner: Mutex<Option<VmInstance>>,
-    status: Mutex<VmStatus>,
+    inner: Arc<Mutex<Option<VmInstance>>>,
+    status: Arc<Mutex<VmStatus>>,
+    /// The data dir's VM lock, taken by [`reserve`] ahead of the next [`start`].
+    reservation: Arc<Mutex<Option<FileLock>>>,
+}
+
+/// What the VM needs from whoever embeds it (the app, or the headless CLI): where to keep its
+/// files, where to report progress, and where the task's file changes are journaled from.
+pub trait VmHost: Clone + Send + 'static {
+    /// Base directory; VM logs and disks go in its `vm/` subdirectory.
+    fn data_dir(&self) -> Result<PathBuf, String>;
+
+    /// `event` is `ready`, `error`, `warning`, or `rpc` with one guest RPC line as `message`.
+    fn emit_vm_event(&self, event: &str, message: String);
+
+    /// Holds the watcher for the task the VM runs.
+    fn change_watcher(&self) -> &ChangeWatcherState;
+
+    /// Called for each change journaled while a task runs.
+    fn file_changed(&self, change: &FileChange);
+}
+
+impl VmHost for AppHandle {
+    fn data_dir(&self) -> Result<PathBuf, String> {
+        self.path().app_data_dir().map_err(|error| error.to_string())
+    }
+
+    fn emit_vm_event(&self, event: &str, message: String) {
+        if let Some(events) = self.try_state::<crate::automation::Events>() {
+            events.publish(&crate::automation::HostEvent::from_vm_event(event, &message));
+        }
+        let _ = self.emit(
+            "vm_event",
+            VmEvent {
+                event: event.to_string(),
+                message,
+            },
+        );
+    }
+
+    fn change_watcher(&self) -> &ChangeWatcherState {
+        self.state::<ChangeWatcherState>().inner()
+    }
+
+    fn file_changed(&self, change: &FileChange) {
+        let _ = self.emit("file_changed", change);
+    }
 }
 
 #[derive(Clone, Serialize, Default)]
@@ -36,6 +88,9 @@ pub struct VmStatusResponse {
     pub status: VmStatus,
     pub rpc_port: Option<u16>,
     pub log_path: Option<String>,
+    /// Task whose state is mounted into the guest (`None` when no task state is mounted).
+    pub task_id: Option<String>,
+    pub mounts: Vec<MountInfo>,
 }
 
 #[derive(Deserialize)]
@@ -45,12 +100,58 @@ pub struct RuntimeManifest {
     pub initrd: String,
     pub cmdline: Option<String>,
     pub qemu: Option<String>,
+    pub virtiofsd: Option<String>,
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+}
+
+/// Host directories backing the active task's guest mounts.
+///
+/// Only the task's `outputs` (writable) and `uploads` (read-only) are exported, as separate
+/// shares the guest mounts under its own `<task>` dir. The task dir itself is never shared,
+/// so unmounting `uploads` in the guest exposes nothing writable behind it.
+pub struct TaskStateMounts {
+    pub outputs: PathBuf,
+    pub uploads: PathBuf,
+    pub session: PathBuf,
+}
+
+/// Who is using the running VM. A VM booted for a background run is stopped when the run ends,
+/// unless an interactive client started using it meanwhile.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VmOwner {
+    /// Booted, but not claimed by anyone yet.
+    #[default]
+    Unclaimed,
+    Background,
+    Interactive,
 }
 
 struct VmInstance {
-    child: Child,
+    /// `None` when replaying a recorded session instead of booting QEMU.
+    child: Option<Child>,
     log_path: PathBuf,
+    task_id: Option<String>,
+    owner: VmOwner,
+    mounts: AttachedMounts,
     rpc_writer: Arc<Mutex<Option<TcpStream>>>,
+    recorder: Option<Arc<Recorder>>,
+    /// Held for the VM's lifetime; see [`reserve`].
+    _vm_lock: FileLock,
+}
+
+impl VmInstance {
+    fn shutdown(&mut self) {
+        if let Some(child) = self.child.as_mut() {
+            child.kill().ok();
+            let _ = child.wait();
+        }
+        // Ends the RPC reader even when there is no QEMU process to take the connection down.
+        if let Some(stream) = self.rpc_writer.lock().unwrap().as_ref() {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+        self.mounts.shutdown();
+    }
 }
 
 #[derive(Clone, Serialize)]
@@ -60,23 +161,11 @@ struct VmEvent {
     message: String,
 }
 
-fn emit_event(app: &AppHandle, event: &str, message: String) {
-    let _ = app.emit(
-        "vm_event",
-        VmEvent {
-            event: event.to_string(),
-            message,
-        },
-    );
-}
-
-fn set_status(app: &AppHandle, status: VmStatus) {
-    let state: tauri::State<VmState> = app.state();
+fn set_status(state: &VmState, status: VmStatus) {
     *state.status.lock().unwrap() = status;
 }
 
-fn mark_stopped(app: &AppHandle) {
-    let state: tauri::State<VmState> = app.state();
+fn mark_stopped(state: &VmState) {
     let instance = {
         let mut inner = state.inner.lock().unwrap();
         inner.take()
@@ -85,76 +174,100 @@ fn mark_stopped(app: &AppHandle) {
     *state.status.lock().unwrap() = VmStatus::Stopped;
 
     if let Some(mut instance) = instance {
-        instance.child.kill().ok();
-        let _ = instance.child.wait();
+        instance.shutdown();
     }
 }
 
 pub fn status(state: &VmState) -> VmStatusResponse {
     let status = state.status.lock().unwrap().clone();
     let inner = state.inner.lock().unwrap();
-    let log_path = inner
-        .as_ref()
-        .map(|instance| instance.log_path.to_string_lossy().to_string());
+    status_response(status, inner.as_ref())
+}
 
+fn status_response(status: VmStatus, instance: Option<&VmInstance>) -> VmStatusResponse {
     VmStatusResponse {
         status,
         rpc_port: Some(RPC_PORT),
-        log_path,
+        log_path: instance.map(|instance| instance.log_path.to_string_lossy().to_string()),
+        task_id: instance.and_then(|instance| instance.task_id.clone()),
+        mounts: instance.map_or_else(Vec::new, |instance| instance.mounts.mounts.clone()),
     }
 }
 
 #[allow(clippy::too_many_arguments)]
 pub fn start(
-    app: &AppHandle,
+    host: &impl VmHost,
     state: &VmState,
     runtime_dir: &Path,
     working_folder: Option<&Path>,
-    task_state_dir: Option<&Path>,
+    task_state: Option<&TaskStateMounts>,
     auth_state_dir: Option<&Path>,
     initial_task_id: Option<&str>,
+    on_rpc_line: impl Fn(&str) + Send + 'static,
 ) -> Result<VmStatusResponse, String> {
     eprintln!("[rust:vm] start called");
     let mut inner = state.inner.lock().unwrap();
-    if inner.is_some() {
+    if let Some(instance) = inner.as_mut() {
         eprintln!("[rust:vm] already running");
+        // Whoever asks for a VM that is already up is using it alongside its booter.
+        instance.owner = VmOwner::Interactive;
         // Build response inline to avoid deadlock (we already hold inner lock)
         let status = state.status.lock().unwrap().clone();
-        let log_path = inner
-            .as_ref()
-            .map(|instance| instance.log_path.to_string_lossy().to_string());
-        return Ok(VmStatusResponse {
-            status,
-            rpc_port: Some(RPC_PORT),
-            log_path,
-        });
+        return Ok(status_response(status, inner.as_ref()));
     }
 
-    eprintln!("[rust:vm] loading manifest");
-    let manifest = load_manifest(runtime_dir)?;
-
-    let vm_dir = app.path().app_data_dir().map_err(|e| e.to_string())?.join("vm");
+    let vm_dir = host.data_dir()?.join("vm");
     std::fs::create_dir_all(&vm_dir).map_err(|e| e.to_string())?;
+    let reserved = state.reservation.lock().unwrap().take();
+    let vm_lock = match reserved {
+        Some(lock) => lock,
+        None => lock_vm_dir(&vm_dir)?,
+    };
     let log_path = vm_dir.join("qemu.log");
-
-    eprintln!("[rust:vm] spawning qemu");
-    let child = spawn_qemu(
-        &manifest,
-        runtime_dir,
-        &log_path,
-        working_folder,
-        task_state_dir,
-        auth_state_dir,
-        initial_task_id,
-    )?;
-    eprintln!("[rust:vm] qemu spawned");
+    let recorder = rpc_replay::recorder_from_env()?;
+    let replay = rpc_replay::fixture_from_env()?;
+
+    let (child, mounts) = if let Some(fixture) = replay {
+        eprintln!("[rust:vm] replaying recorded RPC session instead of booting qemu");
+        let listener = TcpListener::bind(("127.0.0.1", RPC_PORT)).map_err(|e| e.to_string())?;
+        let speed = rpc_replay::speed_from_env();
+        thread::spawn(move || {
+            if let Err(error) = rpc_replay::serve(&listener, fixture, speed) {
+                eprintln!("[rust:vm] RPC replay failed: {error}");
+            }
+        });
+        (None, AttachedMounts::default())
+    } else {
+        eprintln!("[rust:vm] loading manifest");
+        let manifest = load_manifest(runtime_dir)?;
+
+        eprintln!("[rust:vm] spawning qemu");
+        let (child, mounts) = spawn_qemu(
+            &manifest,
+            runtime_dir,
+            &vm_dir,
+            &log_path,
+            working_folder,
+            task_state,
+            auth_state_dir,
+            initial_task_id,
+        )?;
+        eprintln!("[rust:vm] qemu spawned");
+        (Some(child), mounts)
+    };
+    let replaying = child.is_none();
 
     let rpc_writer: Arc<Mutex<Option<TcpStream>>> = Arc::new(Mutex::new(None));
 
     let instance = VmInstance {
         child,
         log_path: log_path.clone(),
+        task_id: task_state.and(initial_task_id).map(str::to_string),
+        owner: VmOwner::Unclaimed,
+        mounts,
         rpc_writer: rpc_writer.clone(),
+        recorder: recorder.clone(),
+        _vm_lock: vm_lock,
     };
 
     *state.status.lock().unwrap() = VmStatus::Starting;
@@ -162,7 +275,8 @@ pub fn start(
     drop(inner); // Release lock before spawning thread
 
     // Thread to wait for READY and then connect RPC
-    let app_handle = app.clone();
+    let host = host.clone();
+    let thread_state = state.clone();
     thread::spawn(move || {
         let ready_timeout = timeout_from_env("PIWORK_VM_READY_TIMEOUT_SECS", READY_MARKER_TIMEOUT_SECS);
         let connect_timeout = timeout_from_env("PIWORK_VM_RPC_CONNECT_TIMEOUT_SECS", RPC_CONNECT_TIMEOUT_SECS);
@@ -171,7 +285,7 @@ pub fn start(
 
         // Wait for VM to boot by polling the log file for READY.
         // If READY is not observed in time, still attempt direct RPC connect as fallback.
-        let ready = wait_for_ready(&log_path, ready_timeout);
+        let ready = replaying || wait_for_ready(&log_path, ready_timeout);
         if ready {
             eprintln!("[rust:vm:rpc] READY received");
         } else {
@@ -188,10 +302,10 @@ pub fn start(
                     *rpc_writer.lock().unwrap() = Some(clone);
                 }
 
-                set_status(&app_handle, VmStatus::Ready);
-                emit_event(&app_handle, "ready", "READY".to_string());
+                set_status(&thread_state, VmStatus::Ready);
+                host.emit_vm_event("ready", "READY".to_string());
 
-                read_rpc_lines(&app_handle, stream);
+                read_rpc_lines(&host, stream, recorder.as_deref(), on_rpc_line);
             }
             Err(error) => {
                 eprintln!("[rust:vm:rpc] TCP connection failed: {error}");
@@ -199,21 +313,63 @@ pub fn start(
                 if !qemu_tail.is_empty() {
                     eprintln!("[rust:vm:rpc] qemu log tail:\n{qemu_tail}");
                 }
-                emit_event(&app_handle, "error", format!("RPC connection failed: {error}"));
+                host.emit_vm_event("error", format!("RPC connection failed: {error}"));
             }
         }
 
-        mark_stopped(&app_handle);
+        mark_stopped(&thread_state);
     });
 
     Ok(status(state))
 }
 
+/// Marks the running VM as used by `owner`. A background claim only takes an unclaimed VM;
+/// an interactive claim always wins.
+pub fn claim(state: &VmState, owner: VmOwner) {
+    if let Some(instance) = state.inner.lock().unwrap().as_mut() {
+        if owner == VmOwner::Interactive || instance.owner == VmOwner::Unclaimed {
+            instance.owner = owner;
+        }
+    }
+}
+
+/// Stops the VM only if `owner` still holds it. Returns whether it was stopped.
+pub fn stop_if_owned(state: &VmState, owner: VmOwner) -> bool {
+    let mut inner = state.inner.lock().unwrap();
+    if !inner.as_ref().is_some_and(|instance| instance.owner == owner) {
+        return false;
+    }
+    if let Some(mut instance) = inner.take() {
+        instance.shutdown();
+    }
+
+    *state.status.lock().unwrap() = VmStatus::Stopped;
+    true
+}
+
+/// Takes the VM lock for `data_dir` now, so a caller can fail fast before doing any work. The
+/// next [`start`] on `state` keeps it until [`stop`].
+pub fn reserve(state: &VmState, data_dir: &Path) -> Result<(), String> {
+    let lock = lock_vm_dir(&data_dir.join("vm"))?;
+    *state.reservation.lock().unwrap() = Some(lock);
+    Ok(())
+}
+
+/// One VM per data dir: the app and the CLI share its log, disks and the forwarded RPC port, so
+/// a second VM would fail to bind the port and end up talking to the first one's taskd.
+fn lock_vm_dir(vm_dir: &Path) -> Result<FileLock, String> {
+    persist::try_lock(vm_dir)?.ok_or_else(|| {
+        format!(
+            "piwork is already running a VM for {}; stop it (or quit the app) and try again",
+            vm_dir.parent().unwrap_or(vm_dir).display()
+        )
+    })
+}
+
 pub fn stop(state: &VmState) {
     let mut inner = state.inner.lock().unwrap();
     if let Some(mut instance) = inner.take() {
-        instance.child.kill().ok();
-        let _ = instance.child.wait();
+        instance.shutdown();
     }
 
     *state.status.lock().unwrap() = VmStatus::Stopped;
@@ -229,6 +385,9 @@ pub fn send(state: &VmState, message: &str) -> Result<(), String> {
     let Some(stream) = guard.as_mut() else {
         return Err("RPC not connected".to_string());
     };
+    if let Some(recorder) = &instance.recorder {
+        recorder.record(Direction::Host, message);
+    }
 
     stream
         .write_all(format!("{message}\n").as_bytes())
@@ -245,18 +404,30 @@ fn load_manifest(runtime_dir: &Path) -> Result<RuntimeManifest, String> {
     Ok(manifest)
 }
 
-fn attach_9p_mount(command: &mut Command, id: &str, mount_tag: &str, path: &Path, label: &str) {
-    if !path.is_dir() {
-        eprintln!("[rust:vm] {label} not found: {}", path.display());
-        return;
-    }
-
-    eprintln!("[rust:vm] mounting {label}: {}", path.display());
-    command
-        .arg("-fsdev")
-        .arg(format!("local,id={id},path={},security_model=none", path.display()))
-        .arg("-device")
-        .arg(format!("virtio-9p-pci,fsdev={id},mount_tag={mount_tag}"));
+fn task_state_mount_requests(task_state: &TaskStateMounts) -> [MountRequest<'_>; 3] {
+    [
+        MountRequest {
+            id: "taskoutputs",
+            mount_tag: "taskoutputs",
+            path: &task_state.outputs,
+            label: "task outputs dir",
+            read_only: false,
+        },
+        MountRequest {
+            id: "taskuploads",
+            mount_tag: "taskuploads",
+            path: &task_state.uploads,
+            label: "task uploads dir",
+            read_only: true,
+        },
+        MountRequest {
+            id: "tasksession",
+            mount_tag: "tasksession",
+            path: &task_state.session,
+            label: "task session dir",
+            read_only: false,
+        },
+    ]
 }
 
 fn default_qemu_accel() -> &'static str {
@@ -298,12 +469,13 @@ fn resolve_qemu_cpu_model(accel: &str) -> &'static str {
 fn spawn_qemu(
     manifest: &RuntimeManifest,
     runtime_dir: &Path,
+    vm_dir: &Path,
     log_path: &Path,
     working_folder: Option<&Path>,
-    task_state_dir: Option<&Path>,
+    task_state: Option<&TaskStateMounts>,
     auth_state_dir: Option<&Path>,
     initial_task_id: Option<&str>,
-) -> Result<Child, String> {
+) -> Result<(Child, AttachedMounts), String> {
     let qemu_binary = resolve_qemu_binary(manifest, runtime_dir)?;
 
     let kernel = runtime_dir.join(&manifest.kernel);
@@ -323,6 +495,14 @@ fn spawn_qemu(
         let _ = write!(&mut cmdline, " piwork.task_id={task_id}");
     }
 
+    let virtiofsd = mount::find_virtiofsd(runtime_dir, manifest.virtiofsd.as_deref());
+    let mount_backend = mount::resolve_backend(
+        mount::requested_backend_from_env(),
+        &manifest.capabilities,
+        virtiofsd.as_deref(),
+    );
+    let _ = write!(&mut cmdline, " piwork.mount_backend={}", mount_backend.as_str());
+
     // Open log file for serial output
     let log_file = std::fs::File::create(log_path).map_err(|e| e.to_string())?;
     let log_out = log_file.try_clone().map_err(|e| e.to_string())?;
@@ -341,7 +521,7 @@ fn spawn_qemu(
         .arg("-smp")
         .arg("2")
         .arg("-m")
-        .arg("2048")
+        .arg(VM_MEMORY_MB.to_string())
         .arg("-nographic")
         .arg("-kernel")
         .arg(&kernel)
@@ -355,21 +535,44 @@ fn spawn_qemu(
         .arg("-netdev")
         .arg(format!("user,id=net0,hostfwd=tcp:127.0.0.1:{RPC_PORT}-:{RPC_PORT}"));
 
-    // Working folder mount via virtio-9p
+    let mut mount_requests: Vec<MountRequest> = Vec::new();
+
+    // Working folder mount
     if let Some(folder) = working_folder {
-        attach_9p_mount(&mut command, "workdir", "workdir", folder, "working folder");
+        mount_requests.push(MountRequest {
+            id: "workdir",
+            mount_tag: "workdir",
+            path: folder,
+            label: "working folder",
+            read_only: false,
+        });
     }
 
-    // Task state mount via virtio-9p
-    if let Some(task_state) = task_state_dir {
-        attach_9p_mount(&mut command, "taskstate", "taskstate", task_state, "task state dir");
+    // Active task state mounts (uploads enforced read-only by the hypervisor)
+    if let Some(task_state) = task_state {
+        mount_requests.extend(task_state_mount_requests(task_state));
     }
 
-    // Auth state mount via virtio-9p
+    // Auth state mount
     if let Some(auth_state) = auth_state_dir {
-        attach_9p_mount(&mut command, "authstate", "authstate", auth_state, "auth state dir");
+        mount_requests.push(MountRequest {
+            id: "authstate",
+            mount_tag: "authstate",
+            path: auth_state,
+            label: "auth state dir",
+            read_only: false,
+        });
     }
 
+    let mut mounts = mount::attach_mounts(
+        &mut command,
+        mount_backend,
+        &mount_requests,
+        virtiofsd.as_deref(),
+        vm_dir,
+        VM_MEMORY_MB,
+    )?;
+
     command
         // Serial console to file (we read this for READY)
         .arg("-serial")
@@ -377,7 +580,13 @@ fn spawn_qemu(
         .stdout(Stdio::from(log_out))
         .stderr(Stdio::from(log_err));
 
-    command.spawn().map_err(|e| e.to_string())
+    match command.spawn() {
+        Ok(child) => Ok((child, mounts)),
+        Err(error) => {
+            mounts.shutdown();
+            Err(error.to_string())
+        }
+    }
 }
 
 fn resolve_qemu_binary(manifest: &RuntimeManifest, runtime_dir: &Path) -> Result<PathBuf, String> {
@@ -455,7 +664,7 @@ fn connect_rpc(port: u16, timeout: Duration) -> Result<TcpStream, String> {
     Err(last_error)
 }
 
-fn read_rpc_lines(app: &AppHandle, stream: TcpStream) {
+fn read_rpc_lines(host: &impl VmHost, stream: TcpStream, recorder: Option<&Recorder>, on_rpc_line: impl Fn(&str)) {
     eprintln!("[rust:vm:rpc] starting to read RPC lines");
     let reader = BufReader::new(stream);
 
@@ -463,9 +672,119 @@ fn read_rpc_lines(app: &AppHandle, stream: TcpStream) {
         let trimmed = line.trim();
         if !trimmed.is_empty() {
             eprintln!("[rust:vm:rpc] received: {trimmed:?}");
-            emit_event(app, "rpc", trimmed.to_string());
+            if let Some(recorder) = recorder {
+                recorder.record(Direction::Guest, trimmed);
+            }
+            on_rpc_line(trimmed);
+            host.emit_vm_event("rpc", trimmed.to_string());
         }
     }
 
     eprintln!("[rust:vm:rpc] RPC connection closed");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_task_state() -> TaskStateMounts {
+        TaskStateMounts {
+            outputs: PathBuf::from("/data/tasks/task-1/outputs"),
+            uploads: PathBuf::from("/data/tasks/task-1/uploads"),
+            session: PathBuf::from("/data/tasks/sessions/task-1"),
+        }
+    }
+
+    fn temp_dir() -> PathBuf {
+        static COUNTER: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+        let suffix = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let counter = COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        std::env::temp_dir().join(format!("piwork-vm-{suffix}-{counter}"))
+    }
+
+    fn running_state() -> VmState {
+        let state = VmState::default();
+        *state.inner.lock().unwrap() = Some(VmInstance {
+            child: None,
+            log_path: PathBuf::from("/tmp/qemu.log"),
+            task_id: Some("task-1".to_string()),
+            owner: VmOwner::Unclaimed,
+            mounts: AttachedMounts::default(),
+            rpc_writer: Arc::new(Mutex::new(None)),
+            recorder: None,
+            _vm_lock: lock_vm_dir(&temp_dir().join("vm")).expect("vm lock"),
+        });
+        set_status(&state, VmStatus::Ready);
+        state
+    }
+
+    #[test]
+    fn background_vm_is_only_stopped_while_nobody_else_uses_it() {
+        let state = running_state();
+        claim(&state, VmOwner::Background);
+        assert!(stop_if_owned(&state, VmOwner::Background));
+        assert!(matches!(status(&state).status, VmStatus::Stopped));
+
+        let state = running_state();
+        claim(&state, VmOwner::Background);
+        claim(&state, VmOwner::Interactive);
+        claim(&state, VmOwner::Background);
+        assert!(!stop_if_owned(&state, VmOwner::Background));
+        assert!(matches!(status(&state).status, VmStatus::Ready));
+    }
+
+    #[test]
+    fn one_vm_runs_per_data_dir() {
+        let data_dir = temp_dir();
+        let (app, cli) = (VmState::default(), VmState::default());
+
+        reserve(&app, &data_dir).expect("first reservation");
+        let error = reserve(&cli, &data_dir).expect_err("second reservation");
+        assert!(error.contains("already running a VM"));
+
+        *app.reservation.lock().unwrap() = None;
+        reserve(&cli, &data_dir).expect("free again");
+
+        std::fs::remove_dir_all(&data_dir).ok();
+    }
+
+    #[test]
+    fn task_state_mounts_export_uploads_read_only() {
+        let task_state = sample_task_state();
+        let requests = task_state_mount_requests(&task_state);
+
+        let uploads = requests
+            .iter()
+            .find(|request| request.mount_tag == "taskuploads")
+            .expect("uploads share");
+        assert!(uploads.read_only);
+        assert_eq!(uploads.path, task_state.uploads.as_path());
+
+        assert!(requests
+            .iter()
+            .filter(|request| request.mount_tag != "taskuploads")
+            .all(|request| !request.read_only));
+    }
+
+    #[test]
+    fn task_state_mounts_do_not_share_the_task_dir() {
+        let task_state = sample_task_state();
+        let requests = task_state_mount_requests(&task_state);
+
+        let outputs = requests
+            .iter()
+            .find(|request| request.mount_tag == "taskoutputs")
+            .expect("outputs share");
+        assert_eq!(outputs.path, Path::new("/data/tasks/task-1/outputs"));
+        // A writable parent of `uploads` would let the guest unmount the read-only share and write through.
+        assert!(
+            !requests
+                .iter()
+                .any(|request| request.path == Path::new("/data/tasks/task-1")
+                    || request.path == Path::new("/data/tasks"))
+        );
+    }
+}
@@ -0,0 +1,164 @@
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::mpsc::Sender;
+use std::sync::Mutex;
+#[cfg(debug_assertions)]
+use {
+    serde::Serialize,
+    std::sync::atomic::{AtomicU64, Ordering},
+    std::sync::mpsc::{self, RecvTimeoutError},
+    std::time::Duration,
+};
+
+// Only the debug test server sends requests, so that side is left out of release builds;
+// `reply` and `cancel_all` stay because the `webview_reply` command and reloads exist in both.
+
+/// The webview event carrying a [`WebviewRequest`]; the webview answers with `webview_reply`.
+#[cfg(debug_assertions)]
+pub const REQUEST_EVENT: &str = "webview_request";
+
+/// What the host can ask the webview for. Only the webview knows its UI and runtime state.
+#[cfg(debug_assertions)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RequestKind {
+    StateSnapshot,
+    RuntimeDiag,
+}
+
+#[cfg(debug_assertions)]
+impl RequestKind {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "state_snapshot" => Some(Self::StateSnapshot),
+            "runtime_diag" => Some(Self::RuntimeDiag),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::StateSnapshot => "state_snapshot",
+            Self::RuntimeDiag => "runtime_diag",
+        }
+    }
+
+    /// How long the webview gets to answer unless the caller asks for longer.
+    pub fn default_timeout(self) -> Duration {
+        match self {
+            Self::StateSnapshot => Duration::from_secs(5),
+            // Diagnostics round-trip through taskd.
+            Self::RuntimeDiag => Duration::from_secs(8),
+        }
+    }
+}
+
+#[cfg(debug_assertions)]
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct WebviewRequest {
+    pub request_id: String,
+    pub kind: &'static str,
+    pub payload: Value,
+}
+
+/// Host→webview requests waiting for their `webview_reply`.
+#[derive(Default)]
+pub struct WebviewBus {
+    pending: Mutex<HashMap<String, Sender<Result<Value, String>>>>,
+    #[cfg(debug_assertions)]
+    counter: AtomicU64,
+}
+
+impl WebviewBus {
+    /// Sends a request through `emit` and waits up to `timeout` for the webview's reply.
+    #[cfg(debug_assertions)]
+    pub fn request(
+        &self,
+        kind: RequestKind,
+        payload: Value,
+        timeout: Duration,
+        emit: impl FnOnce(&WebviewRequest) -> Result<(), String>,
+    ) -> Result<Value, String> {
+        let request = WebviewRequest {
+            request_id: format!("{}_{}", kind.as_str(), self.counter.fetch_add(1, Ordering::Relaxed) + 1),
+            kind: kind.as_str(),
+            payload,
+        };
+        let (sender, receiver) = mpsc::channel();
+        self.pending.lock().unwrap().insert(request.request_id.clone(), sender);
+
+        let label = kind.as_str().replace('_', " ");
+        let outcome = match emit(&request) {
+            Err(error) => Err(format!("Failed to request {label}: {error}")),
+            Ok(()) => match receiver.recv_timeout(timeout) {
+                Ok(reply) => reply,
+                Err(RecvTimeoutError::Timeout) => Err(format!("Timed out waiting for {label}")),
+                Err(RecvTimeoutError::Disconnected) => Err(format!("{label} request was dropped")),
+            },
+        };
+
+        self.pending.lock().unwrap().remove(&request.request_id);
+        outcome
+    }
+
+    /// Completes a pending request with the webview's answer (or its error).
+    pub fn reply(&self, request_id: &str, reply: Result<Value, String>) -> Result<(), String> {
+        let sender = self
+            .pending
+            .lock()
+            .unwrap()
+            .remove(request_id)
+            .ok_or_else(|| format!("Unknown webview request {request_id}"))?;
+
+        sender
+            .send(reply)
+            .map_err(|_| "Webview request is no longer waiting".to_string())
+    }
+
+    /// Fails every pending request, e.g. when the page that would answer them reloads.
+    pub fn cancel_all(&self, reason: &str) {
+        for (_, sender) in self.pending.lock().unwrap().drain() {
+            let _ = sender.send(Err(reason.to_string()));
+        }
+    }
+}
+
+#[cfg(all(test, debug_assertions))]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn replies_resolve_their_request_and_reloads_cancel_the_rest() {
+        let bus = Arc::new(WebviewBus::default());
+
+        let answered = bus.request(
+            RequestKind::StateSnapshot,
+            Value::Null,
+            Duration::from_secs(5),
+            |request| {
+                assert_eq!(request.kind, "state_snapshot");
+                bus.reply(&request.request_id, Ok(serde_json::json!({ "ok": true })))
+            },
+        );
+        assert_eq!(answered, Ok(serde_json::json!({ "ok": true })));
+        assert!(bus.reply("state_snapshot_1", Ok(Value::Null)).is_err());
+
+        let timed_out = bus.request(RequestKind::RuntimeDiag, Value::Null, Duration::from_millis(10), |_| {
+            Ok(())
+        });
+        assert_eq!(timed_out, Err("Timed out waiting for runtime diag".to_string()));
+
+        let cancelling = bus.clone();
+        let cancelled = bus.request(
+            RequestKind::RuntimeDiag,
+            Value::Null,
+            Duration::from_secs(30),
+            move |_| {
+                std::thread::spawn(move || cancelling.cancel_all("Webview reloaded"));
+                Ok(())
+            },
+        );
+        assert_eq!(cancelled, Err("Webview reloaded".to_string()));
+    }
+}
@@ -22,6 +22,8 @@ const TEST_SERVER_PORT = 19385;
 const START_TIMEOUT_MS = 360_000;
 const SNAPSHOT_TIMEOUT_MS = 180_000;
 const COMMAND_TIMEOUT_MS = 15_000;
+// Commands that open a task may restart the VM before they answer.
+const TASK_COMMAND_TIMEOUT_MS = 180_000;
 
 interface TaskSummary {
     id: string;
@@ -29,6 +31,24 @@ interface TaskSummary {
     workingFolder: string | null;
 }
 
+interface PromptAccepted {
+    taskId: string;
+    promptId: string;
+}
+
+export interface HostEventFilter {
+    topics?: ("vm" | "rpc" | "tasks")[];
+    taskId?: string;
+    events?: string[];
+}
+
+export interface HostEvent {
+    topic: "vm" | "rpc" | "tasks";
+    event: string;
+    taskId?: string;
+    payload: unknown;
+}
+
 interface ArtifactFileEntry {
     source: "outputs" | "uploads" | string;
     path: string;
@@ -81,6 +101,7 @@ export interface StateSnapshot {
         rpcConnected: boolean;
         rpcConnecting: boolean;
         rpcError: string | null;
+        rpcWarning: string | null;
         hasConnectedOnce: boolean;
         taskSwitching: boolean;
     };
@@ -196,6 +217,84 @@ function bestEffortStopProcesses(): void {
     }
 }
 
+/** Host events streamed by the test server's `subscribe` command. */
+export class EventSubscription {
+    private buffered: HostEvent[] = [];
+    private waiters: (() => void)[] = [];
+    private closedError: Error | null = null;
+
+    constructor(private readonly socket: net.Socket) {
+        let buffer = "";
+        socket.on("data", (chunk: Buffer) => {
+            buffer += chunk.toString("utf8");
+            let newlineIndex = buffer.indexOf("\n");
+            while (newlineIndex !== -1) {
+                const line = buffer.slice(0, newlineIndex).trim();
+                buffer = buffer.slice(newlineIndex + 1);
+                if (line) {
+                    this.buffered.push(JSON.parse(line) as HostEvent);
+                }
+                newlineIndex = buffer.indexOf("\n");
+            }
+            this.wake();
+        });
+        socket.on("close", () => {
+            this.closedError ??= new Error("Event subscription closed");
+            this.wake();
+        });
+        socket.on("error", (error) => {
+            this.closedError = error;
+            this.wake();
+        });
+    }
+
+    /** Resolves with the next event that matches `predicate`, skipping the ones before it. */
+    async next(
+        predicate: (event: HostEvent) => boolean = () => true,
+        timeoutMs = SNAPSHOT_TIMEOUT_MS,
+    ): Promise<HostEvent> {
+        const deadline = Date.now() + timeoutMs;
+
+        for (;;) {
+            const index = this.buffered.findIndex(predicate);
+            if (index !== -1) {
+                const [event] = this.buffered.splice(0, index + 1).slice(-1);
+                return event;
+            }
+            this.buffered = [];
+
+            if (this.closedError) {
+                throw this.closedError;
+            }
+            const remaining = deadline - Date.now();
+            if (remaining <= 0) {
+                throw new Error("Timed out waiting for host event");
+            }
+
+            await new Promise<void>((resolve) => {
+                const timer = setTimeout(resolve, remaining);
+                this.waiters.push(() => {
+                    clearTimeout(timer);
+                    resolve();
+                });
+            });
+        }
+    }
+
+    close(): void {
+        this.closedError ??= new Error("Event subscription closed");
+        this.socket.destroy();
+    }
+
+    private wake(): void {
+        const waiters = this.waiters;
+        this.waiters = [];
+        for (const waiter of waiters) {
+            waiter();
+        }
+    }
+}
+
 export class IntegrationHarness {
     private child: ChildProcess | null = null;
     private logStream: ReturnType<typeof createWriteStream> | null = null;
@@ -333,7 +432,7 @@ export class IntegrationHarness {
         await this.start();
     }
 
-    async sendCommand(command: Record<string, unknown>): Promise<string> {
+    async sendCommand(command: Record<string, unknown>, timeoutMs = COMMAND_TIMEOUT_MS): Promise<string> {
         return await new Promise<string>((resolve, reject) => {
             const socket = net.createConnection({ host: TEST_SERVER_HOST, port: TEST_SERVER_PORT });
             let buffer = "";
@@ -350,7 +449,7 @@ export class IntegrationHarness {
                 fn();
             };
 
-            socket.setTimeout(COMMAND_TIMEOUT_MS);
+            socket.setTimeout(timeoutMs);
 
             socket.on("connect", () => {
                 socket.write(`${JSON.stringify(command)}\n`);
@@ -392,8 +491,8 @@ export class IntegrationHarness {
         });
     }
 
-    async sendJson<T>(command: Record<string, unknown>): Promise<T> {
-        const response = await this.sendCommand(command);
+    async sendJson<T>(command: Record<string, unknown>, timeoutMs = COMMAND_TIMEOUT_MS): Promise<T> {
+        const response = await this.sendCommand(command, timeoutMs);
 
         if (response.startsWith("ERR:")) {
             throw new Error(response.replace(/^ERR:\s*/, ""));
@@ -406,6 +505,64 @@ export class IntegrationHarness {
         }
     }
 
+    /** Opens an event stream; subscribe before triggering what you want to wait for. */
+    async subscribe(filter: HostEventFilter = {}): Promise<EventSubscription> {
+        return await new Promise<EventSubscription>((resolve, reject) => {
+            const socket = net.createConnection({ host: TEST_SERVER_HOST, port: TEST_SERVER_PORT });
+            let buffer = "";
+
+            socket.setTimeout(COMMAND_TIMEOUT_MS);
+            socket.on("connect", () => {
+                socket.write(`${JSON.stringify({ cmd: "subscribe", ...filter })}\n`);
+            });
+            socket.on("timeout", () => {
+                socket.destroy();
+                reject(new Error("Test server subscribe timed out"));
+            });
+            socket.on("error", reject);
+
+            const onData = (chunk: Buffer) => {
+                buffer += chunk.toString("utf8");
+                const newlineIndex = buffer.indexOf("\n");
+                if (newlineIndex === -1) {
+                    return;
+                }
+
+                const response = buffer.slice(0, newlineIndex).trim();
+                socket.removeAllListeners();
+                socket.setTimeout(0);
+                if (!isOkResponse(response)) {
+                    socket.destroy();
+                    reject(new Error(`subscribe failed: ${response}`));
+                    return;
+                }
+
+                const subscription = new EventSubscription(socket);
+                const rest = buffer.slice(newlineIndex + 1);
+                if (rest) {
+                    socket.emit("data", Buffer.from(rest, "utf8"));
+                }
+                resolve(subscription);
+            };
+            socket.on("data", onData);
+        });
+    }
+
+    /** Sends `message` to the open task and waits for taskd to finish the run. */
+    async promptAndWait(message: string, timeoutMs = SNAPSHOT_TIMEOUT_MS): Promise<HostEvent> {
+        const events = await this.subscribe({ topics: ["rpc"], events: ["agent_end", "task_error"] });
+        try {
+            const { taskId } = await this.prompt(message);
+            const event = await events.next((candidate) => candidate.taskId === taskId, timeoutMs);
+            if (event.event === "task_error") {
+                throw new Error(`Prompt failed: ${JSON.stringify(event.payload)}`);
+            }
+            return event;
+        } finally {
+            events.close();
+        }
+    }
+
     async snapshot(): Promise<StateSnapshot> {
         const snapshot = await this.sendJson<StateSnapshot>({ cmd: "state_snapshot" });
         this.lastSnapshot = snapshot;
@@ -435,23 +592,12 @@ export class IntegrationHarness {
         );
     }
 
-    async createTask(title: string, workingFolder: string | null): Promise<void> {
-        const response = await this.sendCommand({
-            cmd: "create_task",
-            title,
-            workingFolder,
-        });
-
-        if (!isOkResponse(response)) {
-            throw new Error(`create_task failed: ${response}`);
-        }
+    async createTask(title: string, workingFolder: string | null): Promise<TaskSummary> {
+        return await this.sendJson<TaskSummary>({ cmd: "create_task", title, workingFolder }, TASK_COMMAND_TIMEOUT_MS);
     }
 
-    async prompt(message: string): Promise<void> {
-        const response = await this.sendCommand({ cmd: "prompt", message });
-        if (!isOkResponse(response)) {
-            throw new Error(`prompt failed: ${response}`);
-        }
+    async prompt(message: string): Promise<PromptAccepted> {
+        return await this.sendJson<PromptAccepted>({ cmd: "prompt", message });
     }
 
     async injectMessage(message: string): Promise<void> {
@@ -468,11 +614,8 @@ export class IntegrationHarness {
         }
     }
 
-    async setFolder(folder: string): Promise<void> {
-        const response = await this.sendCommand({ cmd: "set_folder", folder });
-        if (!isOkResponse(response)) {
-            throw new Error(`set_folder failed: ${response}`);
-        }
+    async setFolder(folder: string): Promise<TaskSummary> {
+        return await this.sendJson<TaskSummary>({ cmd: "set_folder", folder }, TASK_COMMAND_TIMEOUT_MS);
     }
 
     async writeWorkingFile(relativePath: string, content: string): Promise<void> {
@@ -599,11 +742,8 @@ export class IntegrationHarness {
         );
     }
 
-    async setTask(taskId: string): Promise<void> {
-        const response = await this.sendCommand({ cmd: "set_task", taskId });
-        if (!isOkResponse(response)) {
-            throw new Error(`set_task failed: ${response}`);
-        }
+    async setTask(taskId: string): Promise<TaskSummary> {
+        return await this.sendJson<TaskSummary>({ cmd: "set_task", taskId }, TASK_COMMAND_TIMEOUT_MS);
     }
 
     async deleteTask(taskId: string): Promise<void> {
@@ -65,28 +65,28 @@ describe("taskStore", () => {
         expect(get(taskStore)[0]).toEqual(normalized);
     });
 
-    it("deletes tasks", async () => {
+    it("archives tasks", async () => {
         const task = sampleTask();
         invokeMock.mockResolvedValue([task]);
         await taskStore.load();
 
         invokeMock.mockResolvedValue(undefined);
-        await taskStore.delete(task.id);
+        await taskStore.archive(task.id);
 
-        expect(invokeMock).toHaveBeenCalledWith("task_store_delete", { taskId: task.id });
+        expect(invokeMock).toHaveBeenCalledWith("task_store_archive", { taskId: task.id });
         expect(get(taskStore)).toEqual([]);
     });
 
-    it("deletes all tasks", async () => {
+    it("archives all tasks", async () => {
         const task = sampleTask();
         invokeMock.mockResolvedValue([task]);
         await taskStore.load();
         taskStore.setActive(task.id);
 
-        invokeMock.mockResolvedValue(undefined);
-        await taskStore.deleteAll();
+        invokeMock.mockResolvedValueOnce(undefined).mockResolvedValueOnce([]);
+        await taskStore.archiveAll();
 
-        expect(invokeMock).toHaveBeenCalledWith("task_store_delete_all");
+        expect(invokeMock).toHaveBeenCalledWith("task_store_archive_all");
         expect(get(taskStore)).toEqual([]);
         expect(get(taskStore.activeTaskId)).toBe(null);
     });
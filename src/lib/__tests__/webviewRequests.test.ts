@@ -0,0 +1,54 @@
+import { answerWebviewRequest } from "$lib/services/webviewRequests";
+
+const invokeMock = vi.fn();
+
+vi.mock("@tauri-apps/api/core", () => ({
+    invoke: (...args: unknown[]) => invokeMock(...args),
+}));
+
+vi.mock("@tauri-apps/api/event", () => ({
+    listen: vi.fn(),
+}));
+
+describe("webviewRequests", () => {
+    beforeEach(() => {
+        invokeMock.mockReset();
+        invokeMock.mockResolvedValue(undefined);
+    });
+
+    it("replies with the handler's result", async () => {
+        await answerWebviewRequest(
+            { state_snapshot: async () => ({ schemaVersion: 1 }) },
+            { requestId: "state_snapshot_1", kind: "state_snapshot", payload: null },
+        );
+
+        expect(invokeMock).toHaveBeenCalledWith("webview_reply", {
+            requestId: "state_snapshot_1",
+            result: { schemaVersion: 1 },
+            error: null,
+        });
+    });
+
+    it("fails requests that throw or have no handler", async () => {
+        await answerWebviewRequest(
+            {
+                runtime_diag: () => {
+                    throw new Error("taskd unavailable");
+                },
+            },
+            { requestId: "runtime_diag_1", kind: "runtime_diag", payload: null },
+        );
+        await answerWebviewRequest({}, { requestId: "composer_state_1", kind: "composer_state", payload: null });
+
+        expect(invokeMock).toHaveBeenNthCalledWith(1, "webview_reply", {
+            requestId: "runtime_diag_1",
+            result: null,
+            error: "taskd unavailable",
+        });
+        expect(invokeMock).toHaveBeenNthCalledWith(2, "webview_reply", {
+            requestId: "composer_state_1",
+            result: null,
+            error: "No webview handler for composer_state",
+        });
+    });
+});
@@ -14,6 +14,8 @@ export interface RuntimeServiceSnapshot {
     rpcConnected: boolean;
     rpcConnecting: boolean;
     rpcError: string | null;
+    /** Host warning for the current run, e.g. an incomplete working-folder snapshot. */
+    rpcWarning: string | null;
     currentTaskId: string | null;
     currentWorkingFolder: string | null;
     currentSessionFile: string | null;
@@ -61,12 +63,14 @@ interface VmStatusResponse {
     status: "starting" | "ready" | "stopped";
     rpcPath: string | null;
     logPath: string | null;
+    taskId: string | null;
 }
 
 interface WorkingFolderValidation {
     folder: string;
     workspaceRoot: string;
     relativePath: string;
+    git?: { root: string; branch: string | null; head: string | null } | null;
 }
 
 interface PendingRpcResponse {
@@ -216,6 +220,7 @@ export class RuntimeService {
     private workspaceRootInitialized = false;
     private workspaceRootLocked = false;
     private vmWorkspaceRoot: string | null = null;
+    private vmTaskId: string | null = null;
 
     constructor(callbacks: RuntimeServiceCallbacks = {}) {
         this.callbacks = callbacks;
@@ -223,6 +228,7 @@ export class RuntimeService {
             rpcConnected: false,
             rpcConnecting: false,
             rpcError: null,
+            rpcWarning: null,
             currentTaskId: null,
             currentWorkingFolder: null,
             currentSessionFile: null,
@@ -378,6 +384,7 @@ export class RuntimeService {
             this.clearPendingRpcResponses(message || "Failed to connect RPC");
             this.rpcClient = null;
             this.vmWorkspaceRoot = null;
+            this.vmTaskId = null;
             this.callbacks.onError?.(message);
             await client.disconnect().catch(() => undefined);
         } finally {
@@ -395,6 +402,7 @@ export class RuntimeService {
         this.clearPendingRpcResponses("RPC disconnected");
         this.rpcClient = null;
         this.vmWorkspaceRoot = null;
+        this.vmTaskId = null;
         this.patch({
             rpcConnected: false,
             rpcError: null,
@@ -425,6 +433,7 @@ export class RuntimeService {
 
         await client.stopVm();
         this.vmWorkspaceRoot = null;
+        this.vmTaskId = null;
         await this.connectRuntime(client);
         await this.waitForRpcReady();
         this.callbacks.onStateRefreshRequested?.();
@@ -438,12 +447,35 @@ export class RuntimeService {
         await this.handleFolderChangeRuntime(folder, deps);
     }
 
+    // The host opens tasks itself too (harness, automation), restarting the VM with the task mounted.
+    async adoptHostTask(task: TaskMetadata): Promise<void> {
+        this.vmTaskId = await this.resolveVmTaskId();
+        this.vmWorkspaceRoot = this.snapshot.workspaceRoot ?? task.workingFolder ?? null;
+
+        if (task.id === this.snapshot.currentTaskId) {
+            this.patch({ currentWorkingFolder: task.workingFolder ?? null });
+            this.callbacks.onStateRefreshRequested?.();
+        }
+    }
+
     private async connectRuntime(client: TauriRpcClient) {
         await this.ensureWorkspaceRootInitialized();
         const folderForConnect = this.snapshot.workspaceRoot ?? this.snapshot.currentWorkingFolder;
 
+        this.patch({ rpcWarning: null });
         await client.connect(folderForConnect, this.snapshot.currentTaskId);
         this.vmWorkspaceRoot = folderForConnect ?? null;
+        this.vmTaskId = await this.resolveVmTaskId();
+    }
+
+    private async resolveVmTaskId(): Promise<string | null> {
+        try {
+            const status = await invoke<VmStatusResponse>("vm_status");
+            return typeof status?.taskId === "string" ? status.taskId : null;
+        } catch (error) {
+            devLog("RuntimeService", `Failed to resolve mounted task: ${error}`);
+            return null;
+        }
     }
 
     private async ensureWorkspaceRootInitialized() {
@@ -467,7 +499,7 @@ export class RuntimeService {
         }
     }
 
-    private async restartVmWithWorkspaceRoot(): Promise<void> {
+    private async restartVmWithMounts(): Promise<void> {
         const client = this.rpcClient;
         if (!client) {
             throw new Error("RPC client unavailable");
@@ -475,7 +507,9 @@ export class RuntimeService {
 
         devLog(
             "RuntimeService",
-            `Restarting VM to apply workspace root mount: ${this.snapshot.workspaceRoot ?? "(none)"}`,
+            `Restarting VM to apply mounts: workspace root ${this.snapshot.workspaceRoot ?? "(none)"}, task ${
+                this.snapshot.currentTaskId ?? "(none)"
+            }`,
         );
 
         this.clearPendingRpcResponses("VM restarting");
@@ -486,6 +520,7 @@ export class RuntimeService {
 
         await client.stopVm();
         this.vmWorkspaceRoot = null;
+        this.vmTaskId = null;
         await this.connectRuntime(client);
         await this.waitForRpcReady();
     }
@@ -562,9 +597,13 @@ export class RuntimeService {
                 }
             }
 
+            // Task state is mounted per task, so switching tasks requires a VM restart.
             const requiredWorkspaceRoot = this.snapshot.workspaceRoot;
-            if (newTask.workingFolder && requiredWorkspaceRoot && this.vmWorkspaceRoot !== requiredWorkspaceRoot) {
-                await this.restartVmWithWorkspaceRoot();
+            const workspaceRootStale = Boolean(
+                newTask.workingFolder && requiredWorkspaceRoot && this.vmWorkspaceRoot !== requiredWorkspaceRoot,
+            );
+            if (workspaceRootStale || this.vmTaskId !== newTaskId) {
+                await this.restartVmWithMounts();
             }
 
             await this.ensureTaskdTaskReady(taskForRuntime);
@@ -635,7 +674,7 @@ export class RuntimeService {
 
             const requiredWorkspaceRoot = this.snapshot.workspaceRoot;
             if (requiredWorkspaceRoot && this.vmWorkspaceRoot !== requiredWorkspaceRoot) {
-                await this.restartVmWithWorkspaceRoot();
+                await this.restartVmWithMounts();
             }
 
             await this.stopTaskdTaskIfPresent(taskId);
@@ -696,6 +735,11 @@ export class RuntimeService {
             return;
         }
 
+        if (event.type === "warning") {
+            this.patch({ rpcWarning: typeof event.message === "string" ? event.message : null });
+            return;
+        }
+
         if (event.type === "rpc" && typeof event.message === "string") {
             this.markConnected();
 
@@ -0,0 +1,50 @@
+import { invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+import { devLog } from "$lib/utils/devLog";
+
+/** Request kinds the host can send; mirrors `webview_bus::RequestKind`. */
+export type WebviewRequestKind = "state_snapshot" | "runtime_diag";
+
+export interface WebviewRequest {
+    requestId: string;
+    kind: WebviewRequestKind | string;
+    payload: unknown;
+}
+
+export type WebviewRequestHandlers = Partial<Record<WebviewRequestKind, (payload: unknown) => unknown>>;
+
+/**
+ * Answers host→webview requests (`webview_request` events) with `handlers`, replying through
+ * `webview_reply`. A throwing handler, or a kind without one, fails the request on the host.
+ */
+export async function listenForWebviewRequests(handlers: WebviewRequestHandlers): Promise<UnlistenFn> {
+    return await listen<WebviewRequest>("webview_request", (event) => {
+        void answerWebviewRequest(handlers, event.payload);
+    });
+}
+
+export async function answerWebviewRequest(handlers: WebviewRequestHandlers, request: WebviewRequest): Promise<void> {
+    if (!request?.requestId) {
+        return;
+    }
+
+    const handler = handlers[request.kind as WebviewRequestKind];
+    let reply: { result?: unknown; error?: string };
+    if (!handler) {
+        reply = { error: `No webview handler for ${request.kind}` };
+    } else {
+        try {
+            reply = { result: await handler(request.payload) };
+        } catch (error) {
+            reply = { error: error instanceof Error ? error.message : String(error) };
+        }
+    }
+
+    await invoke("webview_reply", {
+        requestId: request.requestId,
+        result: reply.result ?? null,
+        error: reply.error ?? null,
+    }).catch((error) => {
+        devLog("WebviewRequests", `failed to reply to ${request.kind}: ${error}`);
+    });
+}
@@ -1,6 +1,6 @@
 import { derived, get, writable } from "svelte/store";
 import { invoke } from "@tauri-apps/api/core";
-import type { TaskMetadata } from "$lib/types/task";
+import type { QuarantinedTask, RunRecord, TaskBundleManifest, TaskMetadata } from "$lib/types/task";
 
 const tasks = writable<TaskMetadata[]>([]);
 const activeTaskId = writable<string | null>(null);
@@ -64,8 +64,25 @@ async function upsertTask(task: TaskMetadata) {
     ensureActiveTask(next);
 }
 
-async function deleteTask(id: string) {
-    await invoke("task_store_delete", { taskId: id });
+// Host-side updates (e.g. status driven by runtime events) replace the cached copy in place.
+function applyHostUpdate(task: TaskMetadata) {
+    const normalized = normalizeTask(task);
+    tasks.update((current) => current.map((item) => (item.id === normalized.id ? normalized : item)));
+}
+
+// Tasks the host created or changed itself are added, or replace the cached copy.
+function applyHostTask(task: TaskMetadata) {
+    const normalized = normalizeTask(task);
+    tasks.update((current) => {
+        const exists = current.some((item) => item.id === normalized.id);
+        return exists
+            ? current.map((item) => (item.id === normalized.id ? normalized : item))
+            : [normalized, ...current];
+    });
+}
+
+async function archiveTask(id: string) {
+    await invoke("task_store_archive", { taskId: id });
     let next: TaskMetadata[] = [];
     tasks.update((current) => {
         next = current.filter((item) => item.id !== id);
@@ -74,10 +91,44 @@ async function deleteTask(id: string) {
     ensureActiveTask(next);
 }
 
-async function deleteAllTasks() {
-    await invoke("task_store_delete_all");
-    tasks.set([]);
-    activeTaskId.set(null);
+// The task mounted in a running VM is kept, so reload rather than assume an empty list.
+async function archiveAllTasks() {
+    await invoke("task_store_archive_all");
+    await loadTasks();
+}
+
+async function loadArchivedTasks(): Promise<TaskMetadata[]> {
+    return await invoke<TaskMetadata[]>("task_store_list_archived");
+}
+
+async function restoreTask(id: string) {
+    const restored = normalizeTask(await invoke<TaskMetadata>("task_store_restore", { taskId: id }));
+    tasks.update((current) => [restored, ...current.filter((item) => item.id !== id)]);
+}
+
+// Permanently deletes one archived task, or all of them when no id is given.
+async function purgeArchivedTasks(id: string | null = null) {
+    await invoke("task_store_purge", { taskId: id });
+}
+
+// Branches a task before the message at `messageIndex`; pass a folder to bind the fork to it.
+async function forkTask(id: string, messageIndex: number, workingFolder: string | null = null): Promise<TaskMetadata> {
+    const fork = normalizeTask(
+        await invoke<TaskMetadata>("task_store_fork", { taskId: id, messageIndex, workingFolder }),
+    );
+    tasks.update((current) => [fork, ...current]);
+    return fork;
+}
+
+async function exportTask(id: string, destination: string, includeSession = false): Promise<TaskBundleManifest> {
+    return await invoke<TaskBundleManifest>("task_export", { taskId: id, destination, includeSession });
+}
+
+// Imported tasks get a new id and no working folder; the user re-binds one before running.
+async function importTask(bundlePath: string): Promise<TaskMetadata> {
+    const imported = normalizeTask(await invoke<TaskMetadata>("task_import", { bundlePath }));
+    tasks.update((current) => [imported, ...current]);
+    return imported;
 }
 
 function createTask(title: string, workingFolder: string | null = null) {
@@ -153,6 +204,14 @@ async function loadConversation(taskId: string): Promise<string | null> {
     });
 }
 
+async function loadRuns(taskId: string): Promise<RunRecord[]> {
+    return await invoke<RunRecord[]>("task_store_runs", { taskId });
+}
+
+async function loadQuarantined(): Promise<QuarantinedTask[]> {
+    return await invoke<QuarantinedTask[]>("task_store_quarantined");
+}
+
 export const taskStore = {
     subscribe: tasks.subscribe,
     activeTaskId: { subscribe: activeTaskId.subscribe },
@@ -160,11 +219,21 @@ export const taskStore = {
     recentFolders: { subscribe: recentFolders.subscribe },
     load: loadTasks,
     upsert: upsertTask,
-    delete: deleteTask,
-    deleteAll: deleteAllTasks,
+    applyHostUpdate,
+    applyHostTask,
+    archive: archiveTask,
+    archiveAll: archiveAllTasks,
+    loadArchived: loadArchivedTasks,
+    restore: restoreTask,
+    purge: purgeArchivedTasks,
+    fork: forkTask,
+    exportTask,
+    importTask,
     create: createTask,
     setActive: setActiveTask,
     saveConversation,
     loadConversation,
+    loadQuarantined,
+    loadRuns,
     addRecentFolder,
 };
@@ -0,0 +1,36 @@
+import { writable } from "svelte/store";
+import { invoke } from "@tauri-apps/api/core";
+import type { TaskTemplate, TemplateInstance } from "$lib/types/task";
+import { taskStore } from "$lib/stores/taskStore";
+
+const templates = writable<TaskTemplate[]>([]);
+
+async function loadTemplates() {
+    templates.set(await invoke<TaskTemplate[]>("template_store_list"));
+}
+
+async function upsertTemplate(template: TaskTemplate) {
+    await invoke("template_store_upsert", { template });
+    await loadTemplates();
+}
+
+async function deleteTemplate(id: string) {
+    await invoke("template_store_delete", { templateId: id });
+    templates.update((current) => current.filter((template) => template.id !== id));
+}
+
+// Creates and selects the task; the caller sends `initialPrompt` once the runtime is ready.
+async function createTask(templateId: string, workingFolder: string | null = null): Promise<TemplateInstance> {
+    const instance = await invoke<TemplateInstance>("task_create_from_template", { templateId, workingFolder });
+    await taskStore.load();
+    taskStore.setActive(instance.task.id);
+    return instance;
+}
+
+export const templateStore = {
+    subscribe: templates.subscribe,
+    load: loadTemplates,
+    upsert: upsertTemplate,
+    delete: deleteTemplate,
+    createTask,
+};
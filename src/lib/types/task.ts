@@ -4,9 +4,11 @@ export interface TaskMount {
 }
 
 export interface TaskMetadata {
+    schemaVersion?: number; // stamped by the host on save
     id: string;
     title: string;
-    status: string;
+    status: TaskStatus;
+    statusHistory?: StatusTransition[]; // recorded by the host
     createdAt: string;
     updatedAt: string;
     sessionFile?: string | null;
@@ -16,4 +18,142 @@ export interface TaskMetadata {
     model?: string | null;
     thinkingLevel?: string | null;
     connectorsEnabled?: string[];
+    gitBranch?: string | null;
+    gitHead?: string | null;
+    isolated?: boolean | null;
+    isolation?: TaskIsolation | null;
+    schedule?: TaskSchedule | null;
+}
+
+export interface TaskSchedule {
+    cron: string; // "minute hour day-of-month month day-of-week", or @hourly/@daily/@weekly/@monthly
+    prompt: string;
+    enabled?: boolean; // defaults to true
+    utcOffsetMinutes?: number; // clock the cron fields use; -new Date().getTimezoneOffset() for local time
+}
+
+export type RunOutcome = "running" | "completed" | "failed" | "skipped";
+
+export interface RunRecord {
+    runId: string;
+    scheduledFor: string;
+    startedAt: string;
+    finishedAt: string | null;
+    outcome: RunOutcome;
+    error: string | null;
+}
+
+export type TaskStatus =
+    | "draft"
+    | "idle"
+    | "running"
+    | "waiting_for_approval"
+    | "completed"
+    | "failed"
+    | "archived";
+
+export interface StatusTransition {
+    from: TaskStatus | null;
+    to: TaskStatus;
+    at: string;
+}
+
+export interface TaskIsolation {
+    mode: "worktree" | "copy";
+    folder: string;
+    source: string;
+    repoRoot: string | null;
+    branch: string | null;
+    baseHead: string | null;
+}
+
+export interface FileChange {
+    taskId: string;
+    timestamp: number;
+    kind: "created" | "modified" | "deleted";
+    root: "workingFolder" | "outputs";
+    path: string;
+}
+
+export interface RestoreReport {
+    restored: string[];
+    removed: string[];
+    skipped: string[];
+}
+
+export interface GitStatusEntry {
+    path: string;
+    originalPath: string | null;
+    index: string;
+    worktree: string;
+}
+
+export interface MergeReport {
+    applied: string[];
+    removed: string[];
+    skipped?: string[]; // copy mode: symlinks (or paths under one), left alone
+}
+
+export interface TaskQuery {
+    status?: string;
+    provider?: string;
+    model?: string;
+    workingFolder?: string;
+    sort?: "updatedDesc" | "updatedAsc" | "createdDesc" | "createdAsc";
+    limit?: number;
+    offset?: number;
+}
+
+export interface TaskPage {
+    tasks: TaskMetadata[];
+    total: number;
+}
+
+export interface QuarantinedTask {
+    taskId: string;
+    path: string;
+    error: string;
+}
+
+export interface TaskBundleFile {
+    path: string;
+    size: number;
+    sha256: string;
+}
+
+export interface TaskBundleManifest {
+    format: string;
+    version: number;
+    taskId: string;
+    title: string;
+    exportedAt: string;
+    includesSession: boolean;
+    files: TaskBundleFile[];
+    skipped?: string[]; // session files left out because they can't be redacted
+}
+
+export interface TaskTemplate {
+    id: string;
+    name: string;
+    description?: string | null;
+    titlePattern: string; // {name}, {folder} and {date} are filled in
+    initialPrompt?: string | null;
+    provider?: string | null;
+    model?: string | null;
+    thinkingLevel?: string | null;
+    connectorsEnabled?: string[] | null;
+    mounts?: TaskMount[];
+    uploads?: string[]; // read-only: files in the template's uploads/ folder
+}
+
+export interface TemplateInstance {
+    task: TaskMetadata;
+    initialPrompt: string | null;
+}
+
+export interface TaskSearchHit {
+    taskId: string;
+    title: string; // HTML-escaped, matches wrapped in <mark>
+    snippet: string; // HTML-escaped, matches wrapped in <mark>
+    rank: number;
 }